@@ -0,0 +1,386 @@
+use chrono::{Datelike, Timelike};
+use clap::ArgEnum;
+use futures::future::join_all;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::HashMap;
+
+use crate::canvas;
+use crate::chart;
+use crate::cli;
+use crate::error;
+use crate::id;
+use crate::section;
+use crate::sis;
+use crate::term;
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ActivityLayout {
+    // Day of the week against hour of the day, to see when people work.
+    Week,
+    // One cell per day, a column per week, to see how activity moves over a
+    // term.
+    Calendar,
+}
+
+// A count of activity in a single day, or a single hour of a day when the
+// source is that precise.
+struct Heat {
+    day: chrono::NaiveDate,
+    hour: Option<u32>,
+    count: f64,
+}
+
+struct CourseHeat {
+    name: String,
+    heat: Vec<Heat>,
+}
+
+pub struct ActivityChart {
+    courses: Vec<CourseHeat>,
+    layout: ActivityLayout,
+}
+
+pub async fn graph(
+    config: &cli::CliValid,
+    args: &cli::ActivityArgs,
+) -> Result<(), error::AppError> {
+    let (courses, _terms) = term::courses_selected(
+        config,
+        &config.course_query,
+    ).await?;
+    let courses = sis::courses_selected(
+        config,
+        courses,
+        args.course.as_slice(),
+    ).await?;
+    let mut course_heats = vec![];
+    for course in &courses {
+        // Running for a student, there's only ever the one section.
+        if !args.by_section || config.student.is_some() {
+            course_heats.push(CourseHeat {
+                name: course.name.clone(),
+                heat: course_heat(
+                    config,
+                    course,
+                    args,
+                    &config.enrollment_query.sections,
+                ).await?,
+            });
+            continue;
+        }
+        for section in section::sections_selected(config, course.id).await? {
+            course_heats.push(CourseHeat {
+                name: format!(
+                    "{}: {}",
+                    course.name,
+                    section::section_label(&section),
+                ),
+                heat: course_heat(
+                    config,
+                    course,
+                    args,
+                    &[id::IdRef::Id(section.id)],
+                ).await?,
+            });
+        }
+    }
+    chart::render(
+        &cli::chart_output(config, &args.chart, "activity"),
+        &ActivityChart {
+            courses: course_heats,
+            layout: args.layout,
+        },
+    )
+}
+
+// Prefer the analytics endpoints, but they are often turned off or closed to
+// students. Every enrollment carries its last activity time though, which at
+// least says when each person was last around. Given sections, only their
+// students count.
+async fn course_heat(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+    args: &cli::ActivityArgs,
+    sections: &[id::IdRef<id::SectionId>],
+) -> Result<Vec<Heat>, error::AppError> {
+    let analytics = match (config.student, args.layout) {
+        (Some(student_id), _) => {
+            canvas::student_activity(config, course.id, student_id)
+                .await
+                .map(|a| student_heat(&a))
+        },
+        // Course wide analytics only count by day, which is all a calendar
+        // needs, but can't be narrowed to sections.
+        (None, ActivityLayout::Calendar) if sections.is_empty() => {
+            canvas::course_activity(config, course.id)
+                .await
+                .map(|days| course_activity_heat(&days))
+        },
+        (None, _) => students_heat(config, course, sections).await,
+    };
+    match analytics {
+        Ok(heat) => Ok(heat),
+        Err(e) => {
+            log::info!(
+                "Analytics unavailable for course {}, falling back to \
+                 enrollment activity: {:?}",
+                course.id,
+                e,
+            );
+            let mut query = config.enrollment_query.clone();
+            query.types = vec![canvas::EnrollmentType::Student];
+            query.user_id = config.student
+                .map(id::IdRef::Id)
+                .or(query.user_id);
+            query.sections = sections.to_vec();
+            let enrollments = canvas::enrollments_by_course(
+                config,
+                course.id,
+                &query,
+            ).await?;
+            Ok(enrollment_heat(&enrollments))
+        },
+    }
+}
+
+// Hourly analytics for everyone in a course means asking about each student.
+// Students without analytics, such as those who've dropped, are left out with
+// a warning, unless nobody has any, which is most likely analytics being off
+// for the course.
+async fn students_heat(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+    sections: &[id::IdRef<id::SectionId>],
+) -> Result<Vec<Heat>, error::AppError> {
+    let mut query = config.enrollment_query.clone();
+    query.types = vec![canvas::EnrollmentType::Student];
+    query.sections = sections.to_vec();
+    let enrollments = canvas::enrollments_by_course(
+        config,
+        course.id,
+        &query,
+    ).await?;
+    let activity_futures = enrollments
+        .iter()
+        .map(|e| canvas::student_activity(config, course.id, e.user_id));
+    let activities = join_all(activity_futures).await;
+    let mut heat = vec![];
+    let mut failed = vec![];
+    for (enrollment, activity) in enrollments.iter().zip(activities) {
+        match activity {
+            Ok(activity) => heat.extend(student_heat(&activity)),
+            Err(e) => failed.push((enrollment.user_id, e)),
+        }
+    }
+    if failed.len() == enrollments.len() {
+        if let Some((_, e)) = failed.pop() {
+            return Err(e);
+        }
+    }
+    for (user_id, e) in failed {
+        log::warn!(
+            "Could not get the activity of student {} in course {}: {:?}",
+            user_id,
+            course.id,
+            e,
+        );
+    }
+    Ok(heat)
+}
+
+fn student_heat(activity: &canvas::StudentActivity) -> Vec<Heat> {
+    let views = activity.page_views
+        .iter()
+        .filter_map(|(hour, count)| {
+            chrono::DateTime::parse_from_rfc3339(hour)
+                .ok()
+                .map(|at| {
+                    timestamp_heat(
+                        &at.with_timezone(&chrono::Local),
+                        *count as f64,
+                    )
+                })
+        });
+    let participations = activity.participations
+        .iter()
+        .map(|p| timestamp_heat(&p.created_at, 1.0));
+    views.chain(participations).collect()
+}
+
+fn course_activity_heat(days: &[canvas::CourseActivity]) -> Vec<Heat> {
+    days.iter()
+        .map(|d| Heat {
+            day: d.date,
+            hour: None,
+            count: (d.views.unwrap_or(0) + d.participations.unwrap_or(0))
+                as f64,
+        })
+        .collect()
+}
+
+fn enrollment_heat(enrollments: &[canvas::Enrollment]) -> Vec<Heat> {
+    enrollments
+        .iter()
+        .filter_map(|e| e.last_activity_at)
+        .map(|at| timestamp_heat(&at, 1.0))
+        .collect()
+}
+
+fn timestamp_heat(at: &chrono::DateTime<chrono::Local>, count: f64) -> Heat {
+    Heat {
+        day: at.naive_local().date(),
+        hour: Some(at.hour()),
+        count,
+    }
+}
+
+// From white for nothing to a deep green for the busiest cell.
+fn heat_color(count: f64, max: f64) -> RGBColor {
+    let t = if max > 0.0 { (count / max).min(1.0) } else { 0.0 };
+    let channel = |from: f64, to: f64| (from + (to - from) * t) as u8;
+    RGBColor(
+        channel(255.0, 0x1b as f64),
+        channel(255.0, 0x5e as f64),
+        channel(255.0, 0x20 as f64),
+    )
+}
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+// Rows count up from the bottom, but weeks read best with Monday on top, so
+// day n of the week (Monday being 0) spans weekday_row(n + 1) to
+// weekday_row(n).
+fn weekday_row(weekday: u32) -> SegmentValue<u32> {
+    SegmentValue::Exact(7 - weekday)
+}
+
+fn weekday_label(y: &SegmentValue<u32>) -> String {
+    match y {
+        SegmentValue::CenterOf(row) => WEEKDAYS
+            .get(6 - (*row as usize).min(6))
+            .unwrap_or(&"")
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+impl chart::Chart for ActivityChart {
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        root.fill(&WHITE)?;
+        let panels = root.split_evenly((self.courses.len().max(1), 1));
+        for (panel, course) in panels.iter().zip(self.courses.iter()) {
+            match self.layout {
+                ActivityLayout::Week => draw_week(panel, course)?,
+                ActivityLayout::Calendar => draw_calendar(panel, course)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn draw_week<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    course: &CourseHeat,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let mut cells = HashMap::new();
+    for heat in &course.heat {
+        if let Some(hour) = heat.hour {
+            let weekday = heat.day.weekday().num_days_from_monday();
+            *cells.entry((hour, weekday)).or_insert(0.0) += heat.count;
+        }
+    }
+    let max = cells.values().cloned().fold(0.0, f64::max);
+    // Plotters treats integer ranges as inclusive when segmenting.
+    let mut chart = ChartBuilder::on(area)
+        .caption(&course.name, ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(40)
+        .build_cartesian_2d(
+            (0u32..23u32).into_segmented(),
+            (0u32..6u32).into_segmented(),
+        )?;
+    let hour_label = |x: &SegmentValue<u32>| match x {
+        SegmentValue::CenterOf(hour) => hour.to_string(),
+        _ => String::new(),
+    };
+    chart.configure_mesh()
+        .disable_mesh()
+        .x_labels(24)
+        .x_label_formatter(&hour_label)
+        .y_labels(7)
+        .y_label_formatter(&weekday_label)
+        .x_desc("Hour")
+        .draw()?;
+    chart.draw_series(cells.iter().map(|((hour, weekday), count)| {
+        Rectangle::new(
+            [
+                (SegmentValue::Exact(*hour), weekday_row(*weekday)),
+                (SegmentValue::Exact(hour + 1), weekday_row(*weekday + 1)),
+            ],
+            heat_color(*count, max).filled(),
+        )
+    }))?;
+    Ok(())
+}
+
+fn draw_calendar<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    course: &CourseHeat,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let mut days = HashMap::new();
+    for heat in &course.heat {
+        *days.entry(heat.day).or_insert(0.0) += heat.count;
+    }
+    let max = days.values().cloned().fold(0.0, f64::max);
+    let first = days.keys().min().cloned()
+        .unwrap_or_else(|| chrono::Local::now().naive_local().date());
+    // Start on the Monday of the first week so rows line up with weekdays.
+    let start = first - chrono::Duration::days(
+        first.weekday().num_days_from_monday() as i64,
+    );
+    let week_of = |day: &chrono::NaiveDate| {
+        ((*day - start).num_days() / 7) as u32
+    };
+    let weeks = days.keys().map(week_of).max().unwrap_or(0) + 1;
+    let mut chart = ChartBuilder::on(area)
+        .caption(&course.name, ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(40)
+        .build_cartesian_2d(
+            (0u32..weeks - 1).into_segmented(),
+            (0u32..6u32).into_segmented(),
+        )?;
+    let week_label = |x: &SegmentValue<u32>| match x {
+        SegmentValue::CenterOf(week) => {
+            (start + chrono::Duration::weeks(*week as i64))
+                .format("%b %d")
+                .to_string()
+        },
+        _ => String::new(),
+    };
+    chart.configure_mesh()
+        .disable_mesh()
+        .x_labels(weeks.min(12) as usize)
+        .x_label_formatter(&week_label)
+        .y_labels(7)
+        .y_label_formatter(&weekday_label)
+        .draw()?;
+    chart.draw_series(days.iter().map(|(day, count)| {
+        let week = week_of(day);
+        let weekday = day.weekday().num_days_from_monday();
+        Rectangle::new(
+            [
+                (SegmentValue::Exact(week), weekday_row(weekday)),
+                (SegmentValue::Exact(week + 1), weekday_row(weekday + 1)),
+            ],
+            heat_color(*count, max).filled(),
+        )
+    }))?;
+    Ok(())
+}
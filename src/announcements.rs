@@ -0,0 +1,412 @@
+use chrono::TimeZone;
+use clap::ArgEnum;
+use std::collections::HashMap;
+
+use crate::canvas;
+use crate::cli;
+use crate::error;
+use crate::id;
+use crate::output;
+use crate::report;
+use crate::sis;
+use crate::state;
+use crate::term;
+
+// The name last run times are remembered under.
+const STATE_NAME: &str = "announcements";
+
+// When each server and student last had a digest with --since-last-run.
+type LastRuns = HashMap<String, chrono::DateTime<chrono::Utc>>;
+
+/// The formats the announcements digest can be written as.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum AnnouncementsFormat {
+    // Plain text for reading in a terminal.
+    Text,
+    Markdown,
+    // A standalone page, styled like the reports.
+    Html,
+}
+
+struct Announcement {
+    course: String,
+    title: String,
+    author: Option<String>,
+    posted_at: chrono::DateTime<chrono::Local>,
+    url: String,
+    // The message with its markup stripped.
+    text: String,
+}
+
+/// Write the announcements posted across your courses over a range of dates,
+/// oldest first. With --since-last-run the range starts where the last such
+/// run's range ended instead, and this run's end is remembered for the next.
+pub async fn digest(
+    config: &cli::CliValid,
+    args: &cli::AnnouncementsArgs,
+) -> Result<(), error::AppError> {
+    let now = chrono::Utc::now();
+    let (courses, _terms) = term::courses_selected(
+        config,
+        &config.course_query,
+    ).await?;
+    let courses = sis::courses_selected(
+        config,
+        courses,
+        &args.courses,
+    ).await?;
+    let mut last_runs = state::read::<LastRuns>(config, STATE_NAME);
+    let last_run = if args.since_last_run {
+        last_runs.get(&state_key(config)).copied()
+    } else {
+        None
+    };
+    let (start, end) = digest_range(last_run, args.from, args.to, now)?;
+    let names = courses
+        .iter()
+        .map(|c| (format!("course_{}", c.id), c.name.clone()))
+        .collect::<HashMap<String, String>>();
+    let mut announcements = canvas::announcements(
+        config,
+        &courses.iter().map(|c| c.id).collect::<Vec<id::CourseId>>(),
+        start,
+        end,
+    )
+        .await?
+        .into_iter()
+        .filter_map(|topic| {
+            // Canvas rounds the range out to whole days, so a run earlier
+            // today would still list today's announcements without this.
+            let posted_at = topic.posted_at?;
+            if posted_at < start || posted_at > end {
+                return None;
+            }
+            Some(Announcement {
+                course: topic.context_code
+                    .as_ref()
+                    .and_then(|code| names.get(code))
+                    .cloned()
+                    .unwrap_or_default(),
+                title: topic.title,
+                author: topic.author
+                    .map(|a| a.display_name)
+                    .or(topic.user_name),
+                posted_at,
+                url: topic.html_url,
+                text: output::html_to_text(
+                    topic.message.as_deref().unwrap_or_default(),
+                ),
+            })
+        })
+        .collect::<Vec<Announcement>>();
+    announcements.sort_by_key(|a| a.posted_at);
+    let start = start.with_timezone(&chrono::Local);
+    let end = end.with_timezone(&chrono::Local);
+    output::write(
+        config,
+        args.file.as_deref(),
+        &match args.output {
+            AnnouncementsFormat::Text => to_text(&announcements),
+            AnnouncementsFormat::Markdown => {
+                to_markdown(&announcements, start, end)
+            },
+            AnnouncementsFormat::Html => to_html(&announcements, start, end),
+        },
+    )?;
+    // Only once the digest is out, so a failed run is picked up again next
+    // time. The next run starts where this one's range ended, so nothing
+    // after --to is skipped.
+    if args.since_last_run {
+        last_runs.insert(state_key(config), end.with_timezone(&chrono::Utc));
+        state::write(config, STATE_NAME, &last_runs)?;
+    }
+    Ok(())
+}
+
+// The range a digest covers: from the last run with --since-last-run, or
+// else from --from, or else the two weeks before it ends. It ends after the
+// day --to gives, or now if that's sooner, since nothing has been posted past
+// now yet.
+fn digest_range(
+    last_run: Option<chrono::DateTime<chrono::Utc>>,
+    from: Option<chrono::NaiveDate>,
+    to: Option<chrono::NaiveDate>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<
+    (chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>),
+    error::AppError,
+> {
+    let end = to
+        .map(|to| day_start(to + chrono::Duration::days(1)))
+        .filter(|end| *end < now)
+        .unwrap_or(now);
+    let start = last_run.unwrap_or_else(|| {
+        from.map(day_start)
+            .unwrap_or_else(|| end - chrono::Duration::days(14))
+    });
+    // Canvas would be asked for a range running backwards, and the last run
+    // would be moved back to an earlier end.
+    if end <= start {
+        let time = |t: chrono::DateTime<chrono::Utc>| {
+            t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+        };
+        return Err(error::AppError::CliDateRangeError(format!(
+            "The digest would end at {}, before it starts at {}{}.",
+            time(end),
+            time(start),
+            if last_run.is_some() { ", the end of the last run" } else { "" },
+        )));
+    }
+    Ok((start, end))
+}
+
+// Runs are remembered per server and per student being observed, since each
+// sees different announcements.
+fn state_key(config: &cli::CliValid) -> String {
+    match config.student {
+        Some(student) => format!("{}/{}", config.server.name, student),
+        None => config.server.name.clone(),
+    }
+}
+
+// The first moment of a local day.
+fn day_start(date: chrono::NaiveDate) -> chrono::DateTime<chrono::Utc> {
+    let midnight = date.and_hms(0, 0, 0);
+    chrono::Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|t| t.with_timezone(&chrono::Utc))
+        .unwrap_or_else(|| chrono::DateTime::from_utc(midnight, chrono::Utc))
+}
+
+fn to_text(announcements: &[Announcement]) -> String {
+    if announcements.is_empty() {
+        return "No announcements.\n".to_string();
+    }
+    announcements
+        .iter()
+        .map(|a| {
+            format!(
+                "{}  {}\n{}\n{}\n\n{}\n",
+                posted_label(a),
+                a.course,
+                a.title,
+                byline(a),
+                a.text,
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn to_markdown(
+    announcements: &[Announcement],
+    start: chrono::DateTime<chrono::Local>,
+    end: chrono::DateTime<chrono::Local>,
+) -> String {
+    let mut markdown = format!(
+        "# Announcements\n\n{}\n",
+        range_label(start, end),
+    );
+    if announcements.is_empty() {
+        markdown.push_str("\nNo announcements.\n");
+    }
+    for a in announcements {
+        markdown.push_str(&format!(
+            "\n## {}\n\n*{} · {} · {}* · [Open in Canvas]({})\n\n{}\n",
+            a.title,
+            a.course,
+            posted_label(a),
+            a.author.as_deref().unwrap_or("Unknown author"),
+            a.url,
+            a.text,
+        ));
+    }
+    markdown
+}
+
+fn to_html(
+    announcements: &[Announcement],
+    start: chrono::DateTime<chrono::Local>,
+    end: chrono::DateTime<chrono::Local>,
+) -> String {
+    let sections = announcements
+        .iter()
+        .map(|a| {
+            let paragraphs = a.text
+                .split("\n\n")
+                .map(|p| {
+                    format!(
+                        "<p>{}</p>\n",
+                        report::escape(p).replace('\n', "<br>"),
+                    )
+                })
+                .collect::<String>();
+            report::section(
+                &a.title,
+                &format!(
+                    "<p class=\"empty\">{} · {} · <a href=\"{}\">{}</a>\
+                     </p>\n{}",
+                    report::escape(&a.course),
+                    report::escape(&posted_label(a)),
+                    report::escape(&a.url),
+                    report::escape(&byline(a)),
+                    paragraphs,
+                ),
+            )
+        })
+        .collect::<Vec<String>>();
+    let sections = if sections.is_empty() {
+        vec![report::section(
+            "Announcements",
+            "<p class=\"empty\">No announcements.</p>\n",
+        )]
+    } else {
+        sections
+    };
+    report::page("Announcements", &range_label(start, end), &sections)
+}
+
+fn posted_label(announcement: &Announcement) -> String {
+    announcement.posted_at.format("%a %Y-%m-%d %H:%M").to_string()
+}
+
+fn byline(announcement: &Announcement) -> String {
+    match &announcement.author {
+        Some(author) => format!("Posted by {}", author),
+        None => "Posted".to_string(),
+    }
+}
+
+fn range_label(
+    start: chrono::DateTime<chrono::Local>,
+    end: chrono::DateTime<chrono::Local>,
+) -> String {
+    format!(
+        "Posted from {} to {}.",
+        start.format("%Y-%m-%d %H:%M"),
+        end.format("%Y-%m-%d %H:%M"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd(2021, 10, day)
+    }
+
+    fn local(day: u32, hour: u32) -> chrono::DateTime<chrono::Local> {
+        chrono::Local.ymd(2021, 10, day).and_hms(hour, 0, 0)
+    }
+
+    fn utc(day: u32, hour: u32) -> chrono::DateTime<chrono::Utc> {
+        local(day, hour).with_timezone(&chrono::Utc)
+    }
+
+    fn announcement(author: Option<&str>) -> Announcement {
+        Announcement {
+            course: "Biology 101".to_string(),
+            title: "Lab moved".to_string(),
+            author: author.map(|a| a.to_string()),
+            posted_at: local(4, 9),
+            url: "https://c.test/courses/1/discussion_topics/2".to_string(),
+            text: "Room 12 this week.".to_string(),
+        }
+    }
+
+    #[test]
+    fn day_start_is_local_midnight() {
+        assert_eq!(day_start(date(4)), utc(4, 0));
+    }
+
+    #[test]
+    fn range_defaults_to_two_weeks_until_now() {
+        let now = utc(20, 12);
+        assert_eq!(
+            digest_range(None, None, None, now).unwrap(),
+            (now - chrono::Duration::days(14), now),
+        );
+    }
+
+    #[test]
+    fn range_from_and_to_are_whole_days() {
+        assert_eq!(
+            digest_range(None, Some(date(1)), Some(date(7)), utc(20, 12))
+                .unwrap(),
+            (utc(1, 0), utc(8, 0)),
+        );
+    }
+
+    #[test]
+    fn range_never_ends_after_now() {
+        let now = utc(20, 12);
+        assert_eq!(
+            digest_range(None, Some(date(1)), Some(date(25)), now).unwrap(),
+            (utc(1, 0), now),
+        );
+    }
+
+    #[test]
+    fn range_starts_at_last_run_over_from() {
+        let now = utc(20, 12);
+        assert_eq!(
+            digest_range(Some(utc(18, 9)), Some(date(1)), None, now).unwrap(),
+            (utc(18, 9), now),
+        );
+    }
+
+    #[test]
+    fn range_ending_before_last_run_is_refused() {
+        let range = digest_range(
+            Some(utc(18, 9)),
+            None,
+            Some(date(10)),
+            utc(20, 12),
+        );
+        assert!(matches!(range, Err(error::AppError::CliDateRangeError(_))));
+    }
+
+    #[test]
+    fn range_from_after_to_is_refused() {
+        let range = digest_range(
+            None,
+            Some(date(10)),
+            Some(date(9)),
+            utc(20, 12),
+        );
+        assert!(range.is_err());
+    }
+
+    #[test]
+    fn text_digest() {
+        assert_eq!(to_text(&[]), "No announcements.\n");
+        assert_eq!(
+            to_text(&[announcement(Some("Dr. Reed")), announcement(None)]),
+            "Mon 2021-10-04 09:00  Biology 101\nLab moved\n\
+             Posted by Dr. Reed\n\nRoom 12 this week.\n\n\
+             Mon 2021-10-04 09:00  Biology 101\nLab moved\nPosted\n\n\
+             Room 12 this week.\n",
+        );
+    }
+
+    #[test]
+    fn markdown_digest() {
+        assert_eq!(
+            to_markdown(&[], local(1, 0), local(8, 0)),
+            "# Announcements\n\n\
+             Posted from 2021-10-01 00:00 to 2021-10-08 00:00.\n\n\
+             No announcements.\n",
+        );
+        assert_eq!(
+            to_markdown(&[announcement(None)], local(1, 0), local(8, 0)),
+            "# Announcements\n\n\
+             Posted from 2021-10-01 00:00 to 2021-10-08 00:00.\n\n\
+             ## Lab moved\n\n\
+             *Biology 101 · Mon 2021-10-04 09:00 · Unknown author* · \
+             [Open in Canvas](https://c.test/courses/1/discussion_topics/2)\
+             \n\nRoom 12 this week.\n",
+        );
+    }
+}
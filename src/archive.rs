@@ -0,0 +1,799 @@
+use futures::future::join_all;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::canvas;
+use crate::cli;
+use crate::download;
+use crate::error;
+use crate::id;
+use crate::observer;
+use crate::output;
+use crate::report;
+use crate::sis;
+use crate::term;
+
+// Something kept in the archive, rendered for reading alongside the JSON
+// Canvas gave us for it.
+struct Rendering {
+    title: String,
+    // A line under the title, such as when it was posted.
+    subtitle: String,
+    html: String,
+    markdown: String,
+}
+
+impl Rendering {
+    // Most of what Canvas keeps is HTML already, so the Markdown is just the
+    // text of it.
+    fn from_html(title: &str, subtitle: String, html: String) -> Self {
+        Rendering {
+            markdown: format!(
+                "# {}\n\n{}\n\n{}\n",
+                title,
+                subtitle,
+                output::html_to_text(&html),
+            ),
+            title: title.to_string(),
+            subtitle,
+            html,
+        }
+    }
+}
+
+// A titled link from a course's index to something archived, relative to the
+// course's directory.
+type Link = (String, String);
+
+/// Keep everything in your courses which you'd want after losing access to
+/// them: pages, assignments, the syllabus, modules, announcements, discussions
+/// and files, along with your own submissions and grades. Each course gets a
+/// directory of JSON with HTML and Markdown renderings, and an index.html to
+/// start from.
+pub async fn archive(
+    config: &cli::CliValid,
+    args: &cli::ArchiveArgs,
+) -> Result<(), error::AppError> {
+    let (courses, _terms) = term::courses_selected(
+        config,
+        &config.course_query,
+    ).await?;
+    let courses = sis::courses_selected(
+        config,
+        courses,
+        &args.courses,
+    ).await?;
+    // Submissions are listed by user, so "self" needs resolving.
+    let user_id = match config.student {
+        Some(student) => student,
+        None => canvas::profile(config, None).await?.id,
+    };
+    let dir = observer::output_path(config, &args.dir);
+    for course in &courses {
+        log::info!("Archiving {}.", course.name);
+        archive_course(
+            config,
+            course,
+            user_id,
+            &dir.join(download::course_dir_name(course)),
+        ).await?;
+    }
+    println!("Archived {} courses to {:?}.", courses.len(), dir);
+    Ok(())
+}
+
+// Teachers can turn parts of a course off, or hide them from students, so
+// each part is kept on its own and one which can't be had doesn't cost the
+// rest.
+async fn archive_course(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+    user_id: id::UserId,
+    dir: &Path,
+) -> Result<(), error::AppError> {
+    let sections = vec![
+        (
+            "Course",
+            links(course, "details", course_part(config, course, dir).await),
+        ),
+        (
+            "Syllabus",
+            links(course, "syllabus", syllabus_part(config, course, dir).await),
+        ),
+        (
+            "Assignments and grades",
+            links(
+                course,
+                "assignments",
+                assignments_part(config, course, user_id, dir).await,
+            ),
+        ),
+        (
+            "Modules",
+            links(course, "modules", modules_part(config, course, dir).await),
+        ),
+        (
+            "Pages",
+            links(course, "pages", pages_part(config, course, dir).await),
+        ),
+        (
+            "Announcements",
+            links(
+                course,
+                "announcements",
+                announcements_part(config, course, dir).await,
+            ),
+        ),
+        (
+            "Discussions",
+            links(
+                course,
+                "discussions",
+                discussions_part(config, course, dir).await,
+            ),
+        ),
+        (
+            "Files",
+            links(course, "files", files_part(config, course, dir).await),
+        ),
+    ];
+    write_index(course, dir, &sections)
+}
+
+fn links(
+    course: &canvas::Course,
+    part: &str,
+    result: Result<Vec<Link>, error::AppError>,
+) -> Vec<Link> {
+    result.unwrap_or_else(|e| {
+        log::warn!(
+            "Could not archive the {} of {}: {:?}",
+            part,
+            course.name,
+            e,
+        );
+        vec![]
+    })
+}
+
+// The course itself, with its term and your scores in it.
+async fn course_part(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+    dir: &Path,
+) -> Result<Vec<Link>, error::AppError> {
+    let details = canvas::course(config, course.id).await?;
+    write_file(&dir.join("course.json"), &to_json(&details)?)?;
+    Ok(vec![("Course details".to_string(), "course.json".to_string())])
+}
+
+async fn syllabus_part(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+    dir: &Path,
+) -> Result<Vec<Link>, error::AppError> {
+    match canvas::syllabus(config, course.id).await? {
+        Some(body) => Ok(vec![write_item(
+            dir,
+            "syllabus",
+            &serde_json::json!({
+                "course_id": course.id,
+                "syllabus_body": body,
+            }),
+            &Rendering::from_html("Syllabus", course.name.clone(), body),
+        )?]),
+        None => Ok(vec![]),
+    }
+}
+
+// Each assignment along with your submission to it, every attempt and the
+// comments on it included, and a page of grades across them all.
+async fn assignments_part(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+    user_id: id::UserId,
+    dir: &Path,
+) -> Result<Vec<Link>, error::AppError> {
+    let mut assignments = canvas::assignments(config, course.id).await?;
+    let mut submissions = canvas::student_submissions(
+        config,
+        course.id,
+        user_id,
+        &[
+            canvas::SubmissionInclude::SubmissionHistory,
+            canvas::SubmissionInclude::SubmissionComments,
+        ],
+    )
+        .await?
+        .into_iter()
+        .map(|s| (s.assignment_id, s))
+        .collect::<HashMap<id::AssignmentId, canvas::Submission>>();
+    for assignment in assignments.iter_mut() {
+        if let Some(submission) = submissions.remove(&assignment.id) {
+            assignment.submission = Some(submission);
+        }
+    }
+    let mut links = vec![write_item(
+        dir,
+        "grades",
+        &assignments
+            .iter()
+            .map(|a| {
+                serde_json::json!({
+                    "assignment_id": a.id,
+                    "name": a.name,
+                    "points_possible": a.points_possible,
+                    "submission": a.submission,
+                })
+            })
+            .collect::<Vec<serde_json::Value>>(),
+        &grades_rendering(course, &assignments),
+    )?];
+    for assignment in &assignments {
+        let stem = format!(
+            "assignments/{}-{}",
+            assignment.id,
+            output::slug(&assignment.name),
+        );
+        let attachments = match &assignment.submission {
+            Some(submission) => {
+                attachments(config, submission, dir, &stem).await
+            },
+            None => vec![],
+        };
+        links.push(write_item(
+            dir,
+            &stem,
+            assignment,
+            &assignment_rendering(assignment, &attachments),
+        )?);
+    }
+    Ok(links)
+}
+
+// The files handed in with a submission, downloaded next to its page. Hands
+// back where each ended up, relative to the course directory.
+async fn attachments(
+    config: &cli::CliValid,
+    submission: &canvas::Submission,
+    dir: &Path,
+    stem: &str,
+) -> Vec<Link> {
+    let files = submission.attachments
+        .iter()
+        .flatten()
+        .map(|file| {
+            let href = format!(
+                "{}/{}",
+                stem,
+                download::path_component(&file.display_name),
+            );
+            let path = dir.join(&href);
+            (file, href, path)
+        })
+        .collect::<Vec<(&canvas::File, String, PathBuf)>>();
+    let downloads = files
+        .iter()
+        .map(|(file, _, path)| download::download_file(config, file, path));
+    join_all(downloads)
+        .await
+        .into_iter()
+        .zip(files.iter())
+        .filter_map(|(result, (file, href, _))| match result {
+            Ok(_) => Some((file.display_name.clone(), href.clone())),
+            Err(e) => {
+                log::warn!(
+                    "Could not download {} for your submission: {:?}",
+                    file.display_name,
+                    e,
+                );
+                None
+            },
+        })
+        .collect()
+}
+
+async fn modules_part(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+    dir: &Path,
+) -> Result<Vec<Link>, error::AppError> {
+    let modules = canvas::modules(config, course.id, config.student).await?;
+    if modules.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut html = String::new();
+    let mut markdown = format!("# Modules\n\n{}\n", course.name);
+    for module in &modules {
+        let items = module.items.iter().flatten().collect::<Vec<_>>();
+        html.push_str(&format!(
+            "<h2>{}</h2>\n<ul>\n{}</ul>\n",
+            report::escape(&module.name),
+            items
+                .iter()
+                .map(|item| {
+                    format!(
+                        "<li style=\"margin-left: {}em\">{} \
+                         <span class=\"empty\">{}</span></li>\n",
+                        item.indent.unwrap_or(0) * 2,
+                        report::escape(&item.title),
+                        report::escape(&item.type_),
+                    )
+                })
+                .collect::<String>(),
+        ));
+        markdown.push_str(&format!("\n## {}\n\n", module.name));
+        for item in items {
+            markdown.push_str(&format!(
+                "{}- {} ({})\n",
+                "  ".repeat(item.indent.unwrap_or(0) as usize),
+                item.title,
+                item.type_,
+            ));
+        }
+    }
+    Ok(vec![write_item(
+        dir,
+        "modules",
+        &modules,
+        &Rendering {
+            title: "Modules".to_string(),
+            subtitle: course.name.clone(),
+            html,
+            markdown,
+        },
+    )?])
+}
+
+// Pages are listed without their bodies, so each is fetched on its own. A
+// page locked for you is left out.
+async fn pages_part(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+    dir: &Path,
+) -> Result<Vec<Link>, error::AppError> {
+    let listed = canvas::pages(config, course.id).await?;
+    let pages = join_all(
+        listed.iter().map(|p| canvas::page(config, course.id, &p.url)),
+    ).await;
+    let mut links = vec![];
+    for (listed, page) in listed.iter().zip(pages) {
+        match page {
+            Ok(page) => links.push(write_item(
+                dir,
+                &format!("pages/{}", download::path_component(&page.url)),
+                &page,
+                &Rendering::from_html(
+                    &page.title,
+                    format!("Updated {}", date_label(page.updated_at)),
+                    page.body.clone().unwrap_or_default(),
+                ),
+            )?),
+            Err(e) => log::warn!(
+                "Could not archive the page {}: {:?}",
+                listed.title,
+                e,
+            ),
+        }
+    }
+    Ok(links)
+}
+
+async fn announcements_part(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+    dir: &Path,
+) -> Result<Vec<Link>, error::AppError> {
+    // Canvas only goes back two weeks unless asked, so ask for the whole life
+    // of the course. Welcome announcements often go out before it starts.
+    let start = course.start_at
+        .map(|start_at| start_at.min(course.created_at))
+        .unwrap_or(course.created_at);
+    let mut announcements = canvas::announcements(
+        config,
+        &[course.id],
+        start.with_timezone(&chrono::Utc),
+        chrono::Utc::now(),
+    ).await?;
+    announcements.sort_by_key(|a| a.posted_at);
+    announcements
+        .iter()
+        .map(|announcement| {
+            write_item(
+                dir,
+                &format!(
+                    "announcements/{}-{}",
+                    announcement.id,
+                    output::slug(&announcement.title),
+                ),
+                announcement,
+                &Rendering::from_html(
+                    &announcement.title,
+                    topic_subtitle(announcement),
+                    announcement.message.clone().unwrap_or_default(),
+                ),
+            )
+        })
+        .collect()
+}
+
+async fn discussions_part(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+    dir: &Path,
+) -> Result<Vec<Link>, error::AppError> {
+    let topics = canvas::discussion_topics(config, course.id).await?;
+    let views = join_all(
+        topics
+            .iter()
+            .map(|t| canvas::discussion_view(config, course.id, t.id)),
+    ).await;
+    let mut links = vec![];
+    for (topic, view) in topics.iter().zip(views) {
+        // The topic is still worth keeping without its replies.
+        let view = view
+            .map_err(|e| {
+                log::warn!(
+                    "Could not get the replies to {}: {:?}",
+                    topic.title,
+                    e,
+                );
+            })
+            .ok();
+        links.push(write_item(
+            dir,
+            &format!(
+                "discussions/{}-{}",
+                topic.id,
+                output::slug(&topic.title),
+            ),
+            &serde_json::json!({ "topic": topic, "view": view }),
+            &discussion_rendering(topic, view.as_ref()),
+        )?);
+    }
+    Ok(links)
+}
+
+async fn files_part(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+    dir: &Path,
+) -> Result<Vec<Link>, error::AppError> {
+    let files = download::course_files(config, course, &dir.join("files"))
+        .await?;
+    let downloaded = download::download_files(config, &files)
+        .await
+        .into_iter()
+        .filter(|outcome| outcome.is_ok())
+        .count();
+    Ok(vec![(
+        format!("{} of {} files", downloaded, files.len()),
+        "files/".to_string(),
+    )])
+}
+
+fn grades_rendering(
+    course: &canvas::Course,
+    assignments: &[canvas::Assignment],
+) -> Rendering {
+    let rows = assignments
+        .iter()
+        .map(|a| {
+            let submission = a.submission.as_ref();
+            vec![
+                a.name.clone(),
+                date_label(a.due_at),
+                submission
+                    .and_then(|s| s.score)
+                    .map(|score| score.to_string())
+                    .unwrap_or_default(),
+                a.points_possible
+                    .map(|points| points.to_string())
+                    .unwrap_or_default(),
+                submission
+                    .and_then(|s| s.grade.clone())
+                    .unwrap_or_default(),
+                submission.map(submission_status).unwrap_or_default(),
+            ]
+        })
+        .collect::<Vec<Vec<String>>>();
+    let headers = ["Assignment", "Due", "Score", "Points", "Grade", "Status"];
+    let markdown_rows = rows
+        .iter()
+        .map(|row| format!("| {} |\n", row.join(" | ")))
+        .collect::<String>();
+    Rendering {
+        title: "Grades".to_string(),
+        subtitle: course.name.clone(),
+        html: report::table(
+            &headers,
+            &rows
+                .iter()
+                .map(|row| row.iter().map(|c| report::escape(c)).collect())
+                .collect::<Vec<Vec<String>>>(),
+            "No assignments.",
+        ),
+        markdown: format!(
+            "# Grades\n\n{}\n\n| {} |\n|{}\n{}",
+            course.name,
+            headers.join(" | "),
+            "---|".repeat(headers.len()),
+            markdown_rows,
+        ),
+    }
+}
+
+fn assignment_rendering(
+    assignment: &canvas::Assignment,
+    attachments: &[Link],
+) -> Rendering {
+    let subtitle = format!(
+        "Due {}, out of {} points",
+        date_label(assignment.due_at),
+        assignment.points_possible.unwrap_or(0.0),
+    );
+    let mut html = assignment.description.clone().unwrap_or_default();
+    if let Some(submission) = &assignment.submission {
+        html.push_str(&format!(
+            "\n<h2>Your submission</h2>\n<p>{}</p>\n",
+            report::escape(&submission_summary(submission)),
+        ));
+        if let Some(body) = &submission.body {
+            html.push_str(&format!("<blockquote>{}</blockquote>\n", body));
+        }
+        if let Some(url) = &submission.url {
+            html.push_str(&format!(
+                "<p><a href=\"{}\">{}</a></p>\n",
+                report::escape(url),
+                report::escape(url),
+            ));
+        }
+        for (name, href) in attachments {
+            // Links are relative to the course directory, and this page sits
+            // one directory down in it.
+            html.push_str(&format!(
+                "<p><a href=\"../{}\">{}</a></p>\n",
+                report::escape(href),
+                report::escape(name),
+            ));
+        }
+        for comment in submission.submission_comments.iter().flatten() {
+            html.push_str(&format!(
+                "<blockquote><p class=\"empty\">{} on {}</p>\n<p>{}</p>\
+                 </blockquote>\n",
+                report::escape(comment.author_name.as_deref().unwrap_or("")),
+                report::escape(&date_label(Some(comment.created_at))),
+                report::escape(&comment.comment),
+            ));
+        }
+    }
+    Rendering::from_html(&assignment.name, subtitle, html)
+}
+
+// A discussion's replies, each nested under the one it answers.
+fn discussion_rendering(
+    topic: &canvas::DiscussionTopic,
+    view: Option<&canvas::DiscussionView>,
+) -> Rendering {
+    let names = view
+        .map(|v| {
+            v.participants
+                .iter()
+                .map(|p| (p.id, p.display_name.clone()))
+                .collect::<HashMap<id::UserId, String>>()
+        })
+        .unwrap_or_default();
+    let message = topic.message.clone().unwrap_or_default();
+    let mut html = message.clone();
+    let mut markdown = format!(
+        "# {}\n\n{}\n\n{}\n",
+        topic.title,
+        topic_subtitle(topic),
+        output::html_to_text(&message),
+    );
+    if let Some(view) = view {
+        let viewed = view.view
+            .iter()
+            .map(|e| e.id)
+            .collect::<Vec<id::EntryId>>();
+        let new_entries = view.new_entries
+            .iter()
+            .filter(|e| !viewed.contains(&e.id));
+        for e in view.view.iter().chain(new_entries) {
+            entry_rendering(e, 0, &names, &mut html, &mut markdown);
+        }
+    }
+    Rendering {
+        title: topic.title.clone(),
+        subtitle: topic_subtitle(topic),
+        html,
+        markdown,
+    }
+}
+
+// An entry and its replies, each a level further in.
+fn entry_rendering(
+    entry: &canvas::DiscussionEntry,
+    depth: usize,
+    names: &HashMap<id::UserId, String>,
+    html: &mut String,
+    markdown: &mut String,
+) {
+    if entry.deleted == Some(true) {
+        return;
+    }
+    let author = entry.user_id
+        .and_then(|id| names.get(&id).cloned())
+        .unwrap_or_default();
+    let when = date_label(entry.created_at);
+    let message = entry.message.clone().unwrap_or_default();
+    html.push_str(&format!(
+        "<blockquote>\n<p class=\"empty\">{} on {}</p>\n{}\n",
+        report::escape(&author),
+        report::escape(&when),
+        message,
+    ));
+    let quote = "> ".repeat(depth + 1);
+    markdown.push_str(&format!(
+        "\n{}**{}** on {}\n{}\n",
+        quote,
+        author,
+        when,
+        quote,
+    ));
+    for line in output::html_to_text(&message).lines() {
+        markdown.push_str(&format!("{}{}\n", quote, line));
+    }
+    for reply in &entry.replies {
+        entry_rendering(reply, depth + 1, names, html, markdown);
+    }
+    html.push_str("</blockquote>\n");
+}
+
+fn submission_summary(submission: &canvas::Submission) -> String {
+    let mut parts = vec![format!(
+        "Submitted {}",
+        date_label(submission.submitted_at),
+    )];
+    if let Some(score) = submission.score {
+        parts.push(format!("scored {}", score));
+    }
+    if let Some(grade) = &submission.grade {
+        parts.push(format!("graded {}", grade));
+    }
+    let status = submission_status(submission);
+    if !status.is_empty() {
+        parts.push(status);
+    }
+    parts.join(", ")
+}
+
+fn submission_status(submission: &canvas::Submission) -> String {
+    if submission.excused == Some(true) {
+        "excused".to_string()
+    } else if submission.missing == Some(true) {
+        "missing".to_string()
+    } else if submission.late == Some(true) {
+        "late".to_string()
+    } else {
+        String::new()
+    }
+}
+
+fn topic_subtitle(topic: &canvas::DiscussionTopic) -> String {
+    let author = topic.author
+        .as_ref()
+        .map(|a| a.display_name.clone())
+        .or_else(|| topic.user_name.clone());
+    match author {
+        Some(author) => format!(
+            "Posted {} by {}",
+            date_label(topic.posted_at),
+            author,
+        ),
+        None => format!("Posted {}", date_label(topic.posted_at)),
+    }
+}
+
+fn date_label(at: Option<chrono::DateTime<chrono::Local>>) -> String {
+    at.map(|at| at.format("%a %Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "with no date".to_string())
+}
+
+// The course's front page, linking to everything archived from it.
+fn write_index(
+    course: &canvas::Course,
+    dir: &Path,
+    sections: &[(&str, Vec<Link>)],
+) -> Result<(), error::AppError> {
+    let subtitle = format!(
+        "Archived {}.",
+        chrono::Local::now().format("%Y-%m-%d %H:%M"),
+    );
+    let html_sections = sections
+        .iter()
+        .filter(|(_, links)| !links.is_empty())
+        .map(|(heading, links)| {
+            report::section(
+                heading,
+                &format!(
+                    "<ul>\n{}</ul>\n",
+                    links
+                        .iter()
+                        .map(|(title, href)| {
+                            format!(
+                                "<li><a href=\"{}\">{}</a></li>\n",
+                                report::escape(href),
+                                report::escape(title),
+                            )
+                        })
+                        .collect::<String>(),
+                ),
+            )
+        })
+        .collect::<Vec<String>>();
+    let markdown = sections
+        .iter()
+        .filter(|(_, links)| !links.is_empty())
+        .map(|(heading, links)| {
+            format!(
+                "\n## {}\n\n{}",
+                heading,
+                links
+                    .iter()
+                    .map(|(title, href)| {
+                        format!(
+                            "- [{}]({})\n",
+                            title,
+                            href.replace(".html", ".md"),
+                        )
+                    })
+                    .collect::<String>(),
+            )
+        })
+        .collect::<String>();
+    write_file(
+        &dir.join("index.html"),
+        &report::page(&course.name, &subtitle, &html_sections),
+    )?;
+    write_file(
+        &dir.join("index.md"),
+        &format!("# {}\n\n{}\n{}", course.name, subtitle, markdown),
+    )
+}
+
+// Write something out as JSON, HTML and Markdown, each named by the stem.
+// Hands back the link to the HTML for the index.
+fn write_item<A: Serialize>(
+    dir: &Path,
+    stem: &str,
+    item: &A,
+    rendering: &Rendering,
+) -> Result<Link, error::AppError> {
+    write_file(&dir.join(format!("{}.json", stem)), &to_json(item)?)?;
+    write_file(
+        &dir.join(format!("{}.html", stem)),
+        &report::page(
+            &rendering.title,
+            &rendering.subtitle,
+            &[format!("<section>\n{}\n</section>\n", rendering.html)],
+        ),
+    )?;
+    write_file(&dir.join(format!("{}.md", stem)), &rendering.markdown)?;
+    Ok((rendering.title.clone(), format!("{}.html", stem)))
+}
+
+fn write_file(path: &Path, text: &str) -> Result<(), error::AppError> {
+    path.parent()
+        .map(fs::create_dir_all)
+        .unwrap_or(Ok(()))
+        .and_then(|_| fs::write(path, text))
+        .map_err(error::AppError::OutputWriteError)
+}
+
+fn to_json<A: Serialize>(item: &A) -> Result<String, error::AppError> {
+    serde_json::to_string_pretty(item)
+        .map_err(|e| error::AppError::OutputWriteError(e.into()))
+}
@@ -0,0 +1,432 @@
+use clap::ArgEnum;
+use futures::future::join_all;
+use std::collections::{HashMap, HashSet};
+
+use crate::canvas;
+use crate::cli;
+use crate::error;
+use crate::ical;
+use crate::id;
+use crate::output;
+use crate::sis;
+use crate::term;
+
+/// The kinds of dated things a course's calendar is made up of.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum EventKind {
+    Assignment,
+    Quiz,
+    Event,
+}
+
+impl EventKind {
+    fn label(&self) -> &'static str {
+        match self {
+            EventKind::Assignment => "Assignment",
+            EventKind::Quiz => "Quiz",
+            EventKind::Event => "Event",
+        }
+    }
+}
+
+/// Where calendar events are gathered from. The API has more detail, but the
+/// feeds are readable even when the API refuses.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum CalendarSource {
+    Api,
+    Feed,
+    Both,
+}
+
+/// An event along with what kind of thing in the course it is.
+#[derive(Clone, Debug)]
+pub struct CourseEvent {
+    pub kind: EventKind,
+    pub event: ical::Event,
+}
+
+pub async fn export(
+    config: &cli::CliValid,
+    args: &cli::IcalArgs,
+) -> Result<(), error::AppError> {
+    let (courses, _terms) = term::courses_selected(
+        config,
+        &config.course_query,
+    ).await?;
+    let courses = sis::courses_selected(
+        config,
+        courses,
+        &args.courses,
+    ).await?;
+    let events = course_events(config, &courses, &args.kinds).await?;
+    // Canvas' UIDs are only unique within one instance, so they're qualified
+    // with its host to keep exports from two instances apart.
+    let host = reqwest::Url::parse(&config.server.host_url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or_else(|| config.server.name.clone());
+    output::write(
+        config,
+        args.file.as_deref(),
+        &ical::to_ics(
+            "Canvas",
+            &events
+                .into_iter()
+                .map(|e| ical::Event {
+                    uid: format!("{}@{}", e.event.uid, host),
+                    ..e.event
+                })
+                .collect::<Vec<_>>(),
+            chrono::Utc::now(),
+        ),
+    )
+}
+
+/// List the events across your courses within a range of dates, merged from
+/// the API and each course's calendar feed.
+pub async fn list(
+    config: &cli::CliValid,
+    args: &cli::CalendarArgs,
+) -> Result<(), error::AppError> {
+    let (courses, _terms) = term::courses_selected(
+        config,
+        &config.course_query,
+    ).await?;
+    let courses = sis::courses_selected(
+        config,
+        courses,
+        &args.courses,
+    ).await?;
+    let api_events = if args.source == CalendarSource::Feed {
+        vec![]
+    } else {
+        match course_events(config, &courses, &args.kinds).await {
+            Ok(events) => events,
+            // The feeds can still fill in what the API won't give us.
+            Err(e) if args.source == CalendarSource::Both => {
+                log::warn!(
+                    "Could not get events from the API, using only the \
+                     calendar feeds: {:?}",
+                    e,
+                );
+                vec![]
+            },
+            Err(e) => return Err(e),
+        }
+    };
+    let feed_events = if args.source == CalendarSource::Api {
+        vec![]
+    } else {
+        feed_events(&courses)
+            .await
+            .into_iter()
+            .filter(|e| args.kinds.is_empty() || args.kinds.contains(&e.kind))
+            .collect()
+    };
+    let from = args.from.unwrap_or_else(|| {
+        chrono::Local::now().naive_local().date()
+    });
+    let to = args.to.unwrap_or_else(|| from + chrono::Duration::days(14));
+    let mut events = merge(api_events, feed_events)
+        .into_iter()
+        .filter(|e| {
+            let start = e.event.start.local_date();
+            let end = e.event.end
+                .as_ref()
+                .map(|end| end.local_date())
+                .unwrap_or(start);
+            start <= to && end >= from
+        })
+        .collect::<Vec<CourseEvent>>();
+    events.sort_by_key(|e| e.event.start.sort_key());
+    for event in events {
+        println!("{}", event_line(&event));
+    }
+    Ok(())
+}
+
+/// Everything dated across the given courses, narrowed to the given kinds
+/// when there are any.
+pub async fn course_events(
+    config: &cli::CliValid,
+    courses: &[canvas::Course],
+    kinds: &[EventKind],
+) -> Result<Vec<CourseEvent>, error::AppError> {
+    let wants = |kind| kinds.is_empty() || kinds.contains(&kind);
+    let codes = courses
+        .iter()
+        .map(|c| (c.id, c.course_code.clone()))
+        .collect::<HashMap<id::CourseId, String>>();
+    let mut events = vec![];
+    let mut quiz_events = vec![];
+    // The assignments behind the quizzes we could list.
+    let mut quiz_assignment_ids = HashSet::new();
+    if wants(EventKind::Quiz) {
+        let quiz_futures = courses
+            .iter()
+            .map(|c| canvas::quizzes(config, c.id));
+        // Courses with the quizzes tab hidden refuse to list them to
+        // students, which shouldn't sink everything else.
+        for (course, quizzes) in courses
+            .iter()
+            .zip(join_all(quiz_futures).await) {
+            match quizzes {
+                Ok(quizzes) => {
+                    quiz_assignment_ids.extend(
+                        quizzes.iter().filter_map(|q| q.assignment_id),
+                    );
+                    quiz_events.extend(
+                        quizzes.iter().filter_map(|q| quiz_event(course, q)),
+                    );
+                },
+                Err(e) => log::warn!(
+                    "Could not list quizzes for course {}: {:?}",
+                    course.id,
+                    e,
+                ),
+            }
+        }
+    }
+    if wants(EventKind::Assignment) {
+        let assignments = canvas::assignments_by_courses(
+            config,
+            courses,
+        ).await?;
+        // Graded quizzes are assignments too. Those that come out as quizzes,
+        // with their whole window, are left out here. The rest stay, so a
+        // course whose quizzes can't be listed still has its due dates.
+        events.extend(
+            assignments
+                .iter()
+                .filter(|a| !quiz_assignment_ids.contains(&a.id))
+                .filter_map(|a| assignment_event(a, &codes)),
+        );
+    }
+    events.extend(quiz_events);
+    if wants(EventKind::Event) {
+        let ids = courses.iter().map(|c| c.id).collect::<Vec<id::CourseId>>();
+        events.extend(
+            canvas::calendar_events(config, &ids)
+                .await?
+                .iter()
+                .filter(|e| e.workflow_state != "deleted")
+                .filter_map(|e| calendar_event(e, &codes)),
+        );
+    }
+    Ok(events)
+}
+
+// UIDs follow the ones in Canvas's own feeds, so the same event coming from
+// both can be recognised as one.
+fn assignment_event(
+    assignment: &canvas::Assignment,
+    codes: &HashMap<id::CourseId, String>,
+) -> Option<CourseEvent> {
+    let due = assignment.due_at?;
+    let code = codes.get(&assignment.course_id).cloned().unwrap_or_default();
+    Some(CourseEvent {
+        kind: EventKind::Assignment,
+        event: ical::Event {
+            uid: format!("event-assignment-{}", assignment.id),
+            summary: format!("{}: {}", code, assignment.name),
+            description: assignment.description
+                .as_deref()
+                .map(output::html_to_text)
+                .filter(|d| !d.is_empty()),
+            location: None,
+            url: Some(assignment.html_url.clone()),
+            start: ical::EventTime::At(due.with_timezone(&chrono::Utc)),
+            end: None,
+            categories: vec![code, EventKind::Assignment.label().to_string()],
+            last_modified: assignment.updated_at
+                .map(|at| at.with_timezone(&chrono::Utc)),
+        },
+    })
+}
+
+// A quiz runs from when it unlocks until it locks, falling back to its due
+// date for whichever end is missing.
+fn quiz_event(
+    course: &canvas::Course,
+    quiz: &canvas::Quiz,
+) -> Option<CourseEvent> {
+    let start = quiz.unlock_at.or(quiz.due_at).or(quiz.lock_at)?;
+    let end = quiz.lock_at.or(quiz.due_at).filter(|end| *end > start);
+    let mut description = vec![];
+    if let Some(due) = quiz.due_at {
+        description.push(format!(
+            "Due {}",
+            due.with_timezone(&chrono::Utc).format("%Y-%m-%d %H:%M UTC"),
+        ));
+    }
+    if let Some(minutes) = quiz.time_limit {
+        description.push(format!("Time limit: {} minutes", minutes));
+    }
+    if let Some(text) = quiz.description.as_deref().map(output::html_to_text) {
+        if !text.is_empty() {
+            description.push(text);
+        }
+    }
+    let uid = match quiz.assignment_id {
+        Some(id) => format!("event-assignment-{}", id),
+        None => format!("event-quiz-{}", quiz.id),
+    };
+    Some(CourseEvent {
+        kind: EventKind::Quiz,
+        event: ical::Event {
+            uid,
+            summary: format!("{}: {}", course.course_code, quiz.title),
+            description: Some(description.join("\n\n"))
+                .filter(|d| !d.is_empty()),
+            location: None,
+            url: Some(quiz.html_url.clone()),
+            start: ical::EventTime::At(start.with_timezone(&chrono::Utc)),
+            end: end.map(|at| {
+                ical::EventTime::At(at.with_timezone(&chrono::Utc))
+            }),
+            categories: vec![
+                course.course_code.clone(),
+                EventKind::Quiz.label().to_string(),
+            ],
+            last_modified: None,
+        },
+    })
+}
+
+fn calendar_event(
+    event: &canvas::CalendarEvent,
+    codes: &HashMap<id::CourseId, String>,
+) -> Option<CourseEvent> {
+    let course_id = event.context_code
+        .strip_prefix("course_")
+        .and_then(|n| n.parse::<id::CourseId>().ok())?;
+    let code = codes.get(&course_id).cloned().unwrap_or_default();
+    let (start, end) = match (event.all_day, event.all_day_date) {
+        (true, Some(day)) => (
+            ical::EventTime::Day(day),
+            Some(ical::EventTime::Day(day.succ())),
+        ),
+        _ => (
+            ical::EventTime::At(event.start_at?.with_timezone(&chrono::Utc)),
+            event.end_at
+                .map(|at| ical::EventTime::At(at.with_timezone(&chrono::Utc))),
+        ),
+    };
+    let location = match (&event.location_name, &event.location_address) {
+        (Some(name), Some(address)) => Some(format!("{}, {}", name, address)),
+        (name, address) => name.clone().or_else(|| address.clone()),
+    }.filter(|l| !l.is_empty());
+    Some(CourseEvent {
+        kind: EventKind::Event,
+        event: ical::Event {
+            uid: format!("event-calendar-event-{}", event.id),
+            summary: format!("{}: {}", code, event.title),
+            description: event.description
+                .as_deref()
+                .map(output::html_to_text)
+                .filter(|d| !d.is_empty()),
+            location,
+            url: event.html_url.clone(),
+            start,
+            end,
+            categories: vec![code, EventKind::Event.label().to_string()],
+            last_modified: event.updated_at
+                .map(|at| at.with_timezone(&chrono::Utc)),
+        },
+    })
+}
+
+/// Every event in each course's calendar feed. A feed which can't be fetched
+/// only costs that course's events.
+pub async fn feed_events(courses: &[canvas::Course]) -> Vec<CourseEvent> {
+    let feed_futures = courses.iter().map(canvas::course_calendar_feed);
+    let mut events = vec![];
+    for (course, feed) in courses.iter().zip(join_all(feed_futures).await) {
+        match feed {
+            Ok(feed) => events.extend(
+                ical::parse(&feed)
+                    .into_iter()
+                    .map(|e| feed_event(course, e)),
+            ),
+            Err(e) => log::warn!(
+                "Could not fetch the calendar feed for course {}: {:?}",
+                course.id,
+                e,
+            ),
+        }
+    }
+    events
+}
+
+// Canvas marks which course an event belongs to by tacking its code onto the
+// end of the summary in brackets. Put it up front instead, to match the events
+// built from the API.
+fn feed_event(course: &canvas::Course, mut event: ical::Event) -> CourseEvent {
+    let kind = if event.uid.starts_with("event-assignment-") {
+        EventKind::Assignment
+    } else {
+        EventKind::Event
+    };
+    let summary = event.summary
+        .strip_suffix(']')
+        .and_then(|s| s.rsplit_once(" ["))
+        .map(|(summary, _)| summary.to_string())
+        .unwrap_or_else(|| event.summary.clone());
+    event.summary = format!("{}: {}", course.course_code, summary);
+    event.categories.push(course.course_code.clone());
+    event.categories.push(kind.label().to_string());
+    CourseEvent {
+        kind,
+        event,
+    }
+}
+
+/// Combine events from the API with those from the feeds. The two share
+/// UIDs, and the API's version wins since it carries more detail.
+pub fn merge(
+    api_events: Vec<CourseEvent>,
+    feed_events: Vec<CourseEvent>,
+) -> Vec<CourseEvent> {
+    let mut uids = api_events
+        .iter()
+        .map(|e| e.event.uid.clone())
+        .collect::<HashSet<String>>();
+    let mut events = api_events;
+    for event in feed_events {
+        if uids.insert(event.event.uid.clone()) {
+            events.push(event);
+        }
+    }
+    events
+}
+
+fn event_line(event: &CourseEvent) -> String {
+    let when = match (&event.event.start, &event.event.end) {
+        (ical::EventTime::Day(day), _) => day.format("%a %Y-%m-%d").to_string(),
+        (ical::EventTime::At(start), end) => {
+            let end = match end {
+                Some(ical::EventTime::At(end)) if end > start => {
+                    let start = start.with_timezone(&chrono::Local);
+                    let end = end.with_timezone(&chrono::Local);
+                    if end.naive_local().date() == start.naive_local().date() {
+                        format!("-{}", end.format("%H:%M"))
+                    } else {
+                        format!(" until {}", end.format("%a %Y-%m-%d %H:%M"))
+                    }
+                },
+                _ => String::new(),
+            };
+            format!(
+                "{}{}",
+                start.with_timezone(&chrono::Local).format("%a %Y-%m-%d %H:%M"),
+                end,
+            )
+        },
+    };
+    format!(
+        "{:<26}  {:<10}  {}",
+        when,
+        event.kind.label(),
+        event.event.summary,
+    )
+}
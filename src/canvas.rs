@@ -1,21 +1,29 @@
 use chrono;
+use clap::ArgEnum;
+use futures::future::{join_all, try_join_all};
 use serde::{Deserialize, Serialize};
 use reqwest;
+use std::collections::HashMap;
 
 use crate::cli;
 use crate::error;
 use crate::http;
+use crate::id;
+use crate::sis;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Course {
-    pub id: u64,
-    pub root_account_id: u64,
-    pub account_id: u64,
+    pub id: id::CourseId,
+    // The SIS identifier for the course, if defined. Only included for those
+    // with permission to view SIS information.
+    pub sis_course_id: Option<String>,
+    pub root_account_id: id::AccountId,
+    pub account_id: id::AccountId,
     pub name: String,
-    pub enrollment_term_id: u64,
+    pub enrollment_term_id: id::TermId,
     pub uuid: String,
     pub start_at: Option<chrono::DateTime<chrono::prelude::Local>>,
-    pub grading_standard_id: Option<u64>,
+    pub grading_standard_id: Option<id::GradingStandardId>,
     pub is_public: bool,
     pub created_at: chrono::DateTime<chrono::prelude::Local>,
     pub course_code: String,
@@ -40,150 +48,245 @@ pub struct Course {
     pub workflow_state: String,
     pub restrict_enrollments_to_course_dates: bool,
     pub overridden_course_visibility: String,
+    // Optional: The enrollment term the course is in. Included when 'term' is
+    // passed in the request's 'include' parameter.
+    pub term: Option<EnrollmentTerm>,
+    // Optional: The course's teachers. Included when 'teachers' is passed in the
+    // request's 'include' parameter.
+    pub teachers: Option<Vec<UserDisplay>>,
+    // Optional: The number of active student enrollments. Included when
+    // 'total_students' is passed in the request's 'include' parameter.
+    pub total_students: Option<u64>,
+    // Optional: The course syllabus as HTML. Included when 'syllabus_body' is
+    // passed in the request's 'include' parameter.
+    pub syllabus_body: Option<String>,
+    // Optional: The current user's progress through the course's module
+    // requirements. Included when 'course_progress' is passed in the request's
+    // 'include' parameter.
+    pub course_progress: Option<CourseProgress>,
+}
+
+impl Course {
+    /// Whether the caller teaches the course, as a teacher or a TA, and so
+    /// can see the whole class's work. Students, observers and designers
+    /// can't.
+    pub fn teaching(&self) -> bool {
+        self.enrollments
+            .iter()
+            .any(|e| e.type_ == "teacher" || e.type_ == "ta")
+    }
+}
+
+// https://canvas.instructure.com/doc/api/enrollment_terms.html
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EnrollmentTerm {
+    // The unique identifier for the enrollment term.
+    pub id: id::TermId,
+    // The name of the term.
+    pub name: String,
+    // The datetime of the start of the term.
+    pub start_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The datetime of the end of the term.
+    pub end_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+}
+
+// The accounts terms endpoint wraps its list rather than returning it bare.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EnrollmentTermList {
+    pub enrollment_terms: Vec<EnrollmentTerm>,
+}
+
+// https://canvas.instructure.com/doc/api/courses.html#CourseProgress
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CourseProgress {
+    // Total number of requirements from all modules.
+    pub requirement_count: Option<u64>,
+    // Total number of requirements the user has completed from all modules.
+    pub requirement_completed_count: Option<u64>,
+    // URL to next module item that has an unmet requirement. Null if the user
+    // has completed the course or the current module does not require
+    // sequential progress.
+    pub next_requirement_url: Option<String>,
+    // Date the user completed the course. Null if the user has not completed
+    // all requirements.
+    pub completed_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+}
+
+// A short, public description of a user, as found in a course's teacher list.
+// https://canvas.instructure.com/doc/api/users.html#UserDisplay
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserDisplay {
+    // The ID of the user.
+    pub id: id::UserId,
+    // A short name the user has selected, for use in conversations or other less
+    // formal places through the site.
+    pub display_name: String,
+    // If avatars are enabled, this field will be included and contain a url to
+    // retrieve the user's avatar.
+    pub avatar_image_url: Option<String>,
+    // URL to access user, either nested to a context or directly.
+    pub html_url: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CourseCalendar {
-    ics: String,
+    pub ics: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CourseEnrollment {
     #[serde(rename="type")]
-    type_: String,
-    role: String,
-    role_id: u64,
-    user_id: u64,
-    enrollment_state: String,
-    limit_privileges_to_course_section: bool,
-    associated_user_id: u64,
+    pub type_: String,
+    pub role: String,
+    pub role_id: id::RoleId,
+    pub user_id: id::UserId,
+    pub enrollment_state: String,
+    pub limit_privileges_to_course_section: bool,
+    pub associated_user_id: Option<id::UserId>,
+    // The following are only present when 'total_scores' is passed in the
+    // request's 'include' parameter, and only for student enrollments.
+    pub computed_current_score: Option<f64>,
+    pub computed_final_score: Option<f64>,
+    pub computed_current_grade: Option<String>,
+    pub computed_final_grade: Option<String>,
+    // The following are only present when 'current_grading_period_scores' is
+    // passed in the request's 'include' parameter.
+    pub has_grading_periods: Option<bool>,
+    pub multiple_grading_periods_enabled: Option<bool>,
+    pub totals_for_all_grading_periods_option: Option<bool>,
+    pub current_grading_period_title: Option<String>,
+    pub current_grading_period_id: Option<id::GradingPeriodId>,
+    pub current_period_computed_current_score: Option<f64>,
+    pub current_period_computed_final_score: Option<f64>,
+    pub current_period_computed_current_grade: Option<String>,
+    pub current_period_computed_final_grade: Option<String>,
 }
 
 // https://canvas.instructure.com/doc/api/enrollments.html
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Enrollment {
     // The ID of the enrollment.
-    id: u64,
+    pub id: id::EnrollmentId,
     // The unique id of the course.
-    course_id: u64,
+    pub course_id: id::CourseId,
     // The SIS Course ID in which the enrollment is associated. Only displayed if
     // present. This field is only included if the user has permission to view SIS
     // information.
-    sis_course_id: Option<String>,
+    pub sis_course_id: Option<String>,
     // The Course Integration ID in which the enrollment is associated. This field
     // is only included if the user has permission to view SIS information.
-    course_integration_id: Option<String>,
+    pub course_integration_id: Option<String>,
     // The unique id of the user's section.
-    course_section_id: u64,
+    pub course_section_id: id::SectionId,
     // The Section Integration ID in which the enrollment is associated. This
     // field is only included if the user has permission to view SIS information.
-    section_integration_id: Option<String>,
+    pub section_integration_id: Option<String>,
     // The SIS Account ID in which the enrollment is associated. Only displayed if
     // present. This field is only included if the user has permission to view SIS
     // information.
-    sis_account_id: Option<String>,
+    pub sis_account_id: Option<String>,
     // The SIS Section ID in which the enrollment is associated. Only displayed if
     // present. This field is only included if the user has permission to view SIS
     // information.
-    sis_section_id: Option<String>,
+    pub sis_section_id: Option<String>,
     // The SIS User ID in which the enrollment is associated. Only displayed if
     // present. This field is only included if the user has permission to view SIS
     // information.
-    sis_user_id: Option<String>,
+    pub sis_user_id: Option<String>,
     // The state of the user's enrollment in the course.
-    enrollment_state: String,
+    pub enrollment_state: String,
     // User can only access his or her own course section.
-    limit_privileges_to_course_section: bool,
+    pub limit_privileges_to_course_section: bool,
     // The unique identifier for the SIS import. This field is only included if
     // the user has permission to manage SIS information.
-    sis_import_id: Option<u64>,
+    pub sis_import_id: Option<id::SisImportId>,
     // The unique id of the user's account.
-    root_account_id: u64,
+    pub root_account_id: id::AccountId,
     // The enrollment type. One of 'StudentEnrollment', 'TeacherEnrollment',
     // 'TaEnrollment', 'DesignerEnrollment', 'ObserverEnrollment'.
     #[serde(rename="type")]
-    type_: String,
+    pub type_: String,
     // The unique id of the user.
-    user_id: u64,
+    pub user_id: id::UserId,
     // The unique id of the associated user. Will be null unless type is
     // ObserverEnrollment.
     // associated_user_id: null,
     // The enrollment role, for course-level permissions. This field will match
     // `type` if the enrollment role has not been customized.
-    role: String,
+    pub role: String,
     // The id of the enrollment role.
-    role_id: u64,
+    pub role_id: id::RoleId,
     // The created time of the enrollment, in ISO8601 format.
-    created_at: chrono::DateTime<chrono::prelude::Local>,
+    pub created_at: chrono::DateTime<chrono::prelude::Local>,
     // The updated time of the enrollment, in ISO8601 format.
-    updated_at: chrono::DateTime<chrono::prelude::Local>,
+    pub updated_at: chrono::DateTime<chrono::prelude::Local>,
     // The start time of the enrollment, in ISO8601 format.
-    start_at: chrono::DateTime<chrono::prelude::Local>,
+    pub start_at: Option<chrono::DateTime<chrono::prelude::Local>>,
     // The end time of the enrollment, in ISO8601 format.
-    end_at: chrono::DateTime<chrono::prelude::Local>,
+    pub end_at: Option<chrono::DateTime<chrono::prelude::Local>>,
     // The last activity time of the user for the enrollment, in ISO8601 format.
-    last_activity_at: chrono::DateTime<chrono::prelude::Local>,
+    pub last_activity_at: Option<chrono::DateTime<chrono::prelude::Local>>,
     // The last attended date of the user for the enrollment in a course, in ISO8601
     // format.
-    last_attended_at: chrono::DateTime<chrono::prelude::Local>,
+    pub last_attended_at: Option<chrono::DateTime<chrono::prelude::Local>>,
     // The total activity time of the user for the enrollment, in seconds.
-    total_activity_time: u64,
+    pub total_activity_time: u64,
     // The URL to the Canvas web UI page for this course enrollment.
-    html_url: String,
+    pub html_url: String,
     // The URL to the Canvas web UI page containing the grades associated with this
-    // enrollment.
-    grades: EnrollmentGrade,
+    // enrollment. Only present on student enrollments.
+    pub grades: Option<EnrollmentGrade>,
     // A description of the user.
-    user: User,
+    pub user: User,
     // The user's override grade for the course.
-    override_grade: String,
+    pub override_grade: Option<String>,
     // The user's override score for the course.
-    override_score: f64,
+    pub override_score: Option<f64>,
     // The user's current grade in the class including muted/unposted assignments.
     // Only included if user has permissions to view this grade, typically teachers,
     // TAs, and admins.
-    unposted_current_grade: Option<String>,
+    pub unposted_current_grade: Option<String>,
     // The user's final grade for the class including muted/unposted assignments.
     // Only included if user has permissions to view this grade, typically teachers,
     // TAs, and admins..
-    unposted_final_grade: Option<String>,
+    pub unposted_final_grade: Option<String>,
     // The user's current score in the class including muted/unposted assignments.
     // Only included if user has permissions to view this score, typically teachers,
     // TAs, and admins..
-    unposted_current_score: Option<String>,
+    pub unposted_current_score: Option<f64>,
     // The user's final score for the class including muted/unposted assignments.
     // Only included if user has permissions to view this score, typically teachers,
     // TAs, and admins..
-    unposted_final_score: Option<String>,
+    pub unposted_final_score: Option<f64>,
     // optional: Indicates whether the course the enrollment belongs to has grading
     // periods set up. (applies only to student enrollments, and only available in
     // course endpoints)
-    has_grading_periods: Option<bool>,
+    pub has_grading_periods: Option<bool>,
     // optional: Indicates whether the course the enrollment belongs to has the
     // Display Totals for 'All Grading Periods' feature enabled. (applies only to
     // student enrollments, and only available in course endpoints)
-    totals_for_all_grading_periods_option: Option<bool>,
+    pub totals_for_all_grading_periods_option: Option<bool>,
     // optional: The name of the currently active grading period, if one exists. If
     // the course the enrollment belongs to does not have grading periods, or if no
     // currently active grading period exists, the value will be null. (applies only
     // to student enrollments, and only available in course endpoints)
-    current_grading_period_title: Option<String>,
+    pub current_grading_period_title: Option<String>,
     // optional: The id of the currently active grading period, if one exists. If
     // the course the enrollment belongs to does not have grading periods, or if no
     // currently active grading period exists, the value will be null. (applies only
     // to student enrollments, and only available in course endpoints)
-    current_grading_period_id: Option<u64>,
+    pub current_grading_period_id: Option<id::GradingPeriodId>,
     // The user's override grade for the current grading period.
-    current_period_override_grade: String,
+    pub current_period_override_grade: Option<String>,
     // The user's override score for the current grading period.
-    current_period_override_score: f64,
+    pub current_period_override_score: Option<f64>,
     // optional: The student's score in the course for the current grading period,
     // including muted/unposted assignments. Only included if user has permission to
     // view this score, typically teachers, TAs, and admins. If the course the
     // enrollment belongs to does not have grading periods, or if no currently
     // active grading period exists, the value will be null. (applies only to
     // student enrollments, and only available in course endpoints)
-    current_period_unposted_current_score: Option<f64>,
+    pub current_period_unposted_current_score: Option<f64>,
     // optional: The student's score in the course for the current grading period,
     // including muted/unposted assignments and including ungraded assignments with
     // a score of 0. Only included if user has permission to view this score,
@@ -191,21 +294,21 @@ pub struct Enrollment {
     // does not have grading periods, or if no currently active grading period
     // exists, the value will be null. (applies only to student enrollments, and
     // only available in course endpoints)
-    current_period_unposted_final_score: Option<f64>,
+    pub current_period_unposted_final_score: Option<f64>,
     // optional: The letter grade equivalent of
     // current_period_unposted_current_score, if available. Only included if user
     // has permission to view this grade, typically teachers, TAs, and admins. If
     // the course the enrollment belongs to does not have grading periods, or if no
     // currently active grading period exists, the value will be null. (applies only
     // to student enrollments, and only available in course endpoints)
-    current_period_unposted_current_grade: Option<String>,
+    pub current_period_unposted_current_grade: Option<String>,
     // optional: The letter grade equivalent of current_period_unposted_final_score,
     // if available. Only included if user has permission to view this grade,
     // typically teachers, TAs, and admins. If the course the enrollment belongs to
     // does not have grading periods, or if no currently active grading period
     // exists, the value will be null. (applies only to student enrollments, and
     // only available in course endpoints)
-    current_period_unposted_final_grade: Option<String>,
+    pub current_period_unposted_final_grade: Option<String>,
 }
 
 // https://canvas.instructure.com/doc/api/enrollments.html
@@ -213,106 +316,1240 @@ pub struct Enrollment {
 pub struct EnrollmentGrade {
     // The URL to the Canvas web UI page for the user's grades, if this is a student
     // enrollment.
-    html_url: String,
+    pub html_url: String,
     // The user's current grade in the class. Only included if user has permissions
     // to view this grade.
-    current_grade: Option<String>,
+    pub current_grade: Option<String>,
     // The user's final grade for the class. Only included if user has permissions
     // to view this grade.
-    final_grade: Option<String>,
+    pub final_grade: Option<String>,
     // The user's current score in the class. Only included if user has permissions
     // to view this score.
-    current_score: Option<String>,
+    pub current_score: Option<f64>,
     // The user's final score for the class. Only included if user has permissions
     // to view this score.
-    final_score: Option<String>,
+    pub final_score: Option<f64>,
     // The total points the user has earned in the class. Only included if user has
     // permissions to view this score and 'current_points' is passed in the
     // request's 'include' parameter.
-    current_points: Option<u64>,
+    pub current_points: Option<f64>,
     // The user's current grade in the class including muted/unposted assignments.
     // Only included if user has permissions to view this grade, typically teachers,
     // TAs, and admins.
-    unposted_current_grade: Option<String>,
+    pub unposted_current_grade: Option<String>,
     // The user's final grade for the class including muted/unposted assignments.
     // Only included if user has permissions to view this grade, typically teachers,
     // TAs, and admins..
-    unposted_final_grade: Option<String>,
+    pub unposted_final_grade: Option<String>,
     // The user's current score in the class including muted/unposted assignments.
     // Only included if user has permissions to view this score, typically teachers,
     // TAs, and admins..
-    unposted_current_score: Option<String>,
+    pub unposted_current_score: Option<f64>,
     // The user's final score for the class including muted/unposted assignments.
     // Only included if user has permissions to view this score, typically teachers,
     // TAs, and admins..
-    unposted_final_score: Option<String>,
+    pub unposted_final_score: Option<f64>,
     // The total points the user has earned in the class, including muted/unposted
     // assignments. Only included if user has permissions to view this score
     // (typically teachers, TAs, and admins) and 'current_points' is passed in the
     // request's 'include' parameter.
-    unposted_current_points: u64
+    pub unposted_current_points: Option<f64>,
+}
+
+// https://canvas.instructure.com/doc/api/sections.html
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Section {
+    // The unique identifier for the section.
+    pub id: id::SectionId,
+    // The name of the section.
+    pub name: String,
+    // The sis id of the section. This field is only included if the user has
+    // permission to view SIS information.
+    pub sis_section_id: Option<String>,
+    // Optional: The integration ID of the section. This field is only included
+    // if the user has permission to view SIS information.
+    pub integration_id: Option<String>,
+    // The unique identifier for the SIS import if created through SIS. This
+    // field is only included if the user has permission to manage SIS
+    // information.
+    pub sis_import_id: Option<id::SisImportId>,
+    // The unique Canvas identifier for the course in which the section belongs.
+    pub course_id: id::CourseId,
+    // The unique SIS identifier for the course in which the section belongs.
+    // This field is only included if the user has permission to view SIS
+    // information.
+    pub sis_course_id: Option<String>,
+    // The start date for the section, if applicable.
+    pub start_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The end date for the section, if applicable.
+    pub end_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // Restrict user enrollments to the start and end dates of the section.
+    pub restrict_enrollments_to_section_dates: Option<bool>,
+    // The unique identifier of the original course of a cross-listed section.
+    pub nonxlist_course_id: Option<id::CourseId>,
+    // Optional: The students in the section. Included when 'students' is
+    // passed in the request's 'include' parameter, and null when there are
+    // none.
+    pub students: Option<Vec<User>>,
+    // Optional: The number of enrolled students in the section. Included when
+    // 'total_students' is passed in the request's 'include' parameter.
+    pub total_students: Option<u64>,
+}
+
+// https://canvas.instructure.com/doc/api/groups.html
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Group {
+    // The ID of the group.
+    pub id: id::GroupId,
+    // The display name of the group.
+    pub name: String,
+    // A description of the group. This is plain text.
+    pub description: Option<String>,
+    // Whether or not the group is public.  Currently only community groups can
+    // be made public.  Also, once a group has been set to public, it cannot be
+    // changed back to private.
+    pub is_public: Option<bool>,
+    // How people are allowed to join the group.  For all groups except for
+    // community groups, the user must share the group's parent course or
+    // account.  For student organized or community groups, where a user can be
+    // a member of as many or few as they want, the applicable levels are
+    // 'parent_context_auto_join', 'parent_context_request', and
+    // 'invitation_only'.  For class groups, where students are divided up and
+    // should only be part of one group of the category, this value will always
+    // be 'invitation_only', and is not relevant.
+    pub join_level: Option<String>,
+    // The number of members currently in the group.
+    pub members_count: u64,
+    // The url of the group's avatar.
+    pub avatar_url: Option<String>,
+    // The course or account that the group belongs to. The pattern here is that
+    // whatever the context_type is, there will be an _id field named after that
+    // type. So if instead context_type was 'Course', the course_id field would
+    // be replaced by an course_id field.
+    pub context_type: String,
+    pub course_id: Option<id::CourseId>,
+    // Certain types of groups have special role designations. Currently, these
+    // include: 'communities', 'student_organized', and 'imported'. Regular
+    // course/account groups have a role of null.
+    pub role: Option<String>,
+    // The ID of the group's category.
+    pub group_category_id: id::GroupCategoryId,
+    // The SIS ID of the group. Only included if the user has permission to
+    // view SIS information.
+    pub sis_group_id: Option<String>,
+    // The id of the SIS import if created through SIS. Only included if the
+    // user has permission to manage SIS information.
+    pub sis_import_id: Option<id::SisImportId>,
+    // The storage quota for the group, in megabytes.
+    pub storage_quota_mb: Option<u64>,
+}
+
+// https://canvas.instructure.com/doc/api/group_categories.html
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GroupCategory {
+    // The ID of the group category.
+    pub id: id::GroupCategoryId,
+    // The display name of the group category.
+    pub name: String,
+    // Certain types of group categories have special role designations.
+    // Currently, these include: 'communities', 'student_organized', and
+    // 'imported'. Regular course/account group categories have a role of null.
+    pub role: Option<String>,
+    // If the group category allows users to join a group themselves, thought
+    // they may only be a member of one group per group category at a time.
+    // Values include 'restricted', 'enabled', and null 'enabled' allows
+    // students to assign themselves to a group 'restricted' restricts them to
+    // only joining a group in their section null disallows students from
+    // joining groups
+    pub self_signup: Option<String>,
+    // Gives instructors the ability to automatically have group leaders
+    // assigned.  Values include 'random', 'first', and null; 'random' picks a
+    // student from the group at random as the leader, 'first' sets the first
+    // student to be assigned to the group as the leader
+    pub auto_leader: Option<String>,
+    // The course or account that the category group belongs to. The pattern
+    // here is that whatever the context_type is, there will be an _id field
+    // named after that type. So if instead context_type was 'Course', the
+    // course_id field would be replaced by an course_id field.
+    pub context_type: String,
+    pub course_id: Option<id::CourseId>,
+    // If self-signup is enabled, group_limit can be set to cap the number of
+    // users in each group. If null, there is no limit.
+    pub group_limit: Option<u64>,
+    // The SIS identifier for the group category. This field is only included
+    // if the user has permission to manage or view SIS information.
+    pub sis_group_category_id: Option<String>,
+    // The unique identifier for the SIS import. This field is only included if
+    // the user has permission to manage SIS information.
+    pub sis_import_id: Option<id::SisImportId>,
 }
 
 // A Canvas user, e.g. a student, teacher, administrator, observer, etc.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct User {
     // The ID of the user.
-    id: u64,
+    pub id: id::UserId,
     // The name of the user.
-    name: String,
+    pub name: String,
     // The name of the user that is should be used for sorting groups of users, such
     // as in the gradebook.
-    sortable_name: String,
+    pub sortable_name: String,
     // The last name of the user.
-    last_name: String,
+    pub last_name: String,
     // The first name of the user.
-    first_name: String,
+    pub first_name: String,
     // A short name the user has selected, for use in conversations or other less
     // formal places through the site.
-    short_name: Option<String>,
+    pub short_name: Option<String>,
     // The SIS ID associated with the user.  This field is only included if the user
     // came from a SIS import and has permissions to view SIS information.
-    sis_user_id: Option<String>,
+    pub sis_user_id: Option<String>,
     // The id of the SIS import.  This field is only included if the user came from
     // a SIS import and has permissions to manage SIS information.
-    sis_import_id: u64,
+    pub sis_import_id: Option<id::SisImportId>,
     // The integration_id associated with the user.  This field is only included if
     // the user came from a SIS import and has permissions to view SIS information.
-    integration_id: Option<String>,
+    pub integration_id: Option<String>,
     // The unique login id for the user.  This is what the user uses to log in to
     // Canvas.
-    login_id: String,
+    pub login_id: Option<String>,
     // If avatars are enabled, this field will be included and contain a url to
     // retrieve the user's avatar.
-    avatar_url: Option<String>,
+    pub avatar_url: Option<String>,
     // Optional: This field can be requested with certain API calls, and will return
     // a list of the users active enrollments. See the List enrollments API for more
     // details about the format of these records.
-    enrollments: Option<Vec<Enrollment>>,
+    pub enrollments: Option<Vec<Enrollment>>,
     // Optional: This field can be requested with certain API calls, and will return
     // the users primary email address.
-    email: Option<String>,
+    pub email: Option<String>,
     // Optional: This field can be requested with certain API calls, and will return
     // the users locale in RFC 5646 format.
-    locale: Option<String>,
+    pub locale: Option<String>,
     // Optional: This field is only returned in certain API calls, and will return a
     // timestamp representing the last time the user logged in to canvas.
-    last_login: Option<String>,
+    pub last_login: Option<String>,
     // Optional: This field is only returned in certain API calls, and will return
     // the IANA time zone name of the user's preferred timezone.
-    time_zone: Option<String>,
+    pub time_zone: Option<String>,
     // Optional: The user's bio.
-    bio: Option<String>,
+    pub bio: Option<String>,
+    // Optional: The user's UUID, included when 'uuid' is passed in the request's
+    // 'include' parameter.
+    pub uuid: Option<String>,
+    // Optional: The ids of the groups the user belongs to, included when
+    // 'group_ids' is passed in the request's 'include' parameter.
+    pub group_ids: Option<Vec<id::GroupId>>,
+}
+
+// https://canvas.instructure.com/doc/api/users.html#Profile
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Profile {
+    // The ID of the user.
+    pub id: id::UserId,
+    // The name of the user.
+    pub name: String,
+    // A short name the user has selected, for use in conversations or other
+    // less formal places through the site.
+    pub short_name: Option<String>,
+    // The name of the user that is should be used for sorting groups of
+    // users, such as in the gradebook.
+    pub sortable_name: Option<String>,
+    pub title: Option<String>,
+    pub bio: Option<String>,
+    // The user's primary email address.
+    pub primary_email: Option<String>,
+    // The unique login id for the user.
+    pub login_id: Option<String>,
+    // The SIS ID associated with the user, for those with permission to view
+    // SIS information.
+    pub sis_user_id: Option<String>,
+    // The avatar_url can change over time, so we recommend not caching it for
+    // more than a few hours.
+    pub avatar_url: Option<String>,
+    // The IANA time zone name of the user's preferred timezone.
+    pub time_zone: Option<String>,
+    // The users locale.
+    pub locale: Option<String>,
+}
+
+// https://canvas.instructure.com/doc/api/modules.html#Module
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Module {
+    // The unique identifier for the module.
+    pub id: id::ModuleId,
+    // The state of the module: 'active', 'deleted'.
+    pub workflow_state: Option<String>,
+    // The position of this module in the course (1-based).
+    pub position: u64,
+    // The name of this module.
+    pub name: String,
+    // (Optional) The date this module will unlock.
+    pub unlock_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // Whether module items must be unlocked in order.
+    pub require_sequential_progress: Option<bool>,
+    // IDs of Modules that must be completed before this one is unlocked.
+    #[serde(default)]
+    pub prerequisite_module_ids: Vec<id::ModuleId>,
+    // The number of items in the module.
+    pub items_count: u64,
+    // The API URL to retrive this module's items.
+    pub items_url: String,
+    // The contents of this module, as an array of Module Items. (Present only if
+    // requested via include[]=items AND the module is not deemed too large by
+    // Canvas.)
+    pub items: Option<Vec<ModuleItem>>,
+    // The state of this Module for the calling user one of 'locked', 'unlocked',
+    // 'started', 'completed' (Optional; present only if the caller is a student
+    // or if the optional parameter 'student_id' is included).
+    pub state: Option<String>,
+    // The date the calling user completed the module (Optional; present only if
+    // the caller is a student or if the optional parameter 'student_id' is
+    // included).
+    pub completed_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // If the student's final grade for the course should be published to the SIS
+    // upon completion of this module.
+    pub publish_final_grade: Option<bool>,
+    // (Optional) Whether this module is published. This field is present only if
+    // the caller has permission to view unpublished modules.
+    pub published: Option<bool>,
+}
+
+// https://canvas.instructure.com/doc/api/modules.html#ModuleItem
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModuleItem {
+    // The unique identifier for the module item.
+    pub id: id::ModuleItemId,
+    // The id of the Module this item appears in.
+    pub module_id: id::ModuleId,
+    // The position of this item in the module (1-based).
+    pub position: u64,
+    // The title of this item.
+    pub title: String,
+    // 0-based indent level; module items may be indented to show a hierarchy.
+    pub indent: Option<u64>,
+    // The type of object referred to one of 'File', 'Page', 'Discussion',
+    // 'Assignment', 'Quiz', 'SubHeader', 'ExternalUrl', 'ExternalTool'.
+    #[serde(rename="type")]
+    pub type_: String,
+    // The id of the object referred to applies to 'File', 'Discussion',
+    // 'Assignment', 'Quiz', 'ExternalTool' types.
+    pub content_id: Option<id::ContentId>,
+    // Link to the item in Canvas.
+    pub html_url: Option<String>,
+    // (Optional) Link to the Canvas API object, if applicable.
+    pub url: Option<String>,
+    // (only for 'Page' type) unique locator for the linked wiki page.
+    pub page_url: Option<String>,
+    // (only for 'ExternalUrl' and 'ExternalTool' types) external url that the
+    // item points to.
+    pub external_url: Option<String>,
+    // Completion requirement for this module item.
+    pub completion_requirement: Option<CompletionRequirement>,
+    // (Optional) Whether this module item is published. This field is present
+    // only if the caller has permission to view unpublished items.
+    pub published: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CompletionRequirement {
+    // One of 'must_view', 'must_submit', 'must_contribute', 'min_score',
+    // 'must_mark_done'.
+    #[serde(rename="type")]
+    pub type_: String,
+    // Minimum score required to complete (only present when type ==
+    // 'min_score').
+    pub min_score: Option<f64>,
+    // Whether the calling user has met this requirement (Optional; present only
+    // if the caller is a student or if the optional parameter 'student_id' is
+    // included).
+    pub completed: Option<bool>,
+}
+
+// A day of activity across a whole course.
+// https://canvas.instructure.com/doc/api/analytics.html#method.analytics_api.course_participation
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CourseActivity {
+    pub date: chrono::NaiveDate,
+    pub participations: Option<u64>,
+    pub views: Option<u64>,
+}
+
+// One student's activity within a course.
+// https://canvas.instructure.com/doc/api/analytics.html#method.analytics_api.student_in_course_participation
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StudentActivity {
+    // Page view counts, keyed by the hour they fall in as an ISO8601 timestamp.
+    #[serde(default)]
+    pub page_views: HashMap<String, u64>,
+    #[serde(default)]
+    pub participations: Vec<Participation>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Participation {
+    pub created_at: chrono::DateTime<chrono::prelude::Local>,
+    pub url: Option<String>,
+}
+
+// https://canvas.instructure.com/doc/api/assignments.html#Assignment
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Assignment {
+    // The ID of the assignment.
+    pub id: id::AssignmentId,
+    // The name of the assignment.
+    pub name: String,
+    // The assignment description, in an HTML fragment.
+    pub description: Option<String>,
+    // The due date for the assignment. If a user id is supplied, this will be
+    // the due date that applies to that user.
+    pub due_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The day/time the assignment is locked after.
+    pub lock_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The day/time the assignment is unlocked.
+    pub unlock_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The time at which this assignment was last modified in any way.
+    pub updated_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The ID of the course the assignment belongs to.
+    pub course_id: id::CourseId,
+    // The URL to the assignment's web page.
+    pub html_url: String,
+    // The ID of the assignment's group.
+    pub assignment_group_id: Option<id::AssignmentGroupId>,
+    // The sorting order of the assignment in the group.
+    pub position: Option<u64>,
+    // The maximum points possible for the assignment.
+    pub points_possible: Option<f64>,
+    // The types of submissions allowed for this assignment.
+    #[serde(default)]
+    pub submission_types: Vec<String>,
+    // The type of grading the assignment receives; one of 'pass_fail',
+    // 'percent', 'letter_grade', 'gpa_scale', 'points'.
+    pub grading_type: Option<String>,
+    // Whether the assignment is published.
+    pub published: Option<bool>,
+    // Whether the assignment is muted. Superseded by post policies, but older
+    // instances still only report this.
+    pub muted: Option<bool>,
+    // Whether grades for this assignment are only posted to students when a
+    // teacher posts them by hand.
+    pub post_manually: Option<bool>,
+    // If true, the assignment will be omitted from the student's final grade.
+    pub omit_from_final_grade: Option<bool>,
+    // (Optional) Statistics about the assignment's scores, shown to students
+    // when the teacher allows it. Included when 'score_statistics' and
+    // 'submission' are passed in the request's 'include' parameter.
+    pub score_statistics: Option<ScoreStatistic>,
+    // (Optional) The caller's submission for this assignment. Included when
+    // 'submission' is passed in the request's 'include' parameter.
+    pub submission: Option<Submission>,
+}
+
+// https://canvas.instructure.com/doc/api/assignments.html#ScoreStatistic
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ScoreStatistic {
+    // Min score.
+    pub min: f64,
+    // Max score.
+    pub max: f64,
+    // Mean score.
+    pub mean: f64,
+    // Upper quartile score.
+    pub upper_q: Option<f64>,
+    // Median score.
+    pub median: Option<f64>,
+    // Lower quartile score.
+    pub lower_q: Option<f64>,
+}
+
+// https://canvas.instructure.com/doc/api/assignment_groups.html#AssignmentGroup
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AssignmentGroup {
+    // The id of the Assignment Group.
+    pub id: id::AssignmentGroupId,
+    // The name of the Assignment Group.
+    pub name: String,
+    // The position of the Assignment Group.
+    pub position: u64,
+    // The weight of the Assignment Group.
+    pub group_weight: Option<f64>,
+}
+
+// https://canvas.instructure.com/doc/api/submissions.html#Submission
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Submission {
+    // The submission's assignment id.
+    pub assignment_id: id::AssignmentId,
+    // The id of the user who created the submission.
+    pub user_id: id::UserId,
+    // This is the submission attempt number.
+    pub attempt: Option<u64>,
+    // The grade for the submission, translated into the assignment grading
+    // scheme (so a letter grade, for example).
+    pub grade: Option<String>,
+    // The raw score.
+    pub score: Option<f64>,
+    // The timestamp when the assignment was submitted.
+    pub submitted_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The timestamp when the assignment was graded.
+    pub graded_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The date this submission was posted to the student, or null if it has
+    // not been posted.
+    pub posted_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The current state of the submission: 'submitted', 'unsubmitted',
+    // 'graded', 'pending_review'.
+    pub workflow_state: String,
+    // Whether the submission was made after the applicable due date.
+    pub late: Option<bool>,
+    // Whether the assignment is missing.
+    pub missing: Option<bool>,
+    // Whether the assignment is excused. Excused assignments have no impact
+    // on a user's grade.
+    pub excused: Option<bool>,
+    // The status of the submission in relation to the late policy. Can be
+    // 'late', 'missing', 'extended', 'none', or null.
+    pub late_policy_status: Option<String>,
+    // The amount of points automatically deducted from the score by the
+    // missing/late policy for a late or missing assignment.
+    pub points_deducted: Option<f64>,
+    // The content of the submission, if it was submitted directly in a text
+    // field.
+    pub body: Option<String>,
+    // The URL of the submission (for 'online_url' submissions).
+    pub url: Option<String>,
+    // The files submitted, for 'online_upload' submissions.
+    pub attachments: Option<Vec<File>>,
+    // (Optional) Comments on the submission. Included when
+    // 'submission_comments' is passed in the request's 'include' parameter.
+    pub submission_comments: Option<Vec<SubmissionComment>>,
+    // (Optional) An array of submissions, one for each attempt. Included when
+    // 'submission_history' is passed in the request's 'include' parameter.
+    pub submission_history: Option<Vec<Submission>>,
+}
+
+// https://canvas.instructure.com/doc/api/submissions.html#SubmissionComment
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SubmissionComment {
+    pub id: id::SubmissionCommentId,
+    pub author_id: Option<id::UserId>,
+    pub author_name: Option<String>,
+    pub comment: String,
+    pub created_at: chrono::DateTime<chrono::prelude::Local>,
+    pub edited_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+}
+
+// https://canvas.instructure.com/doc/api/pages.html#Page
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Page {
+    // The unique locator for the page.
+    pub url: String,
+    // The title of the page.
+    pub title: String,
+    // The creation date for the page.
+    pub created_at: chrono::DateTime<chrono::prelude::Local>,
+    // The date the page was last updated.
+    pub updated_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The page content, in HTML. Left out when listing pages.
+    pub body: Option<String>,
+    // Whether the page is published (true) or draft state (false).
+    pub published: Option<bool>,
+    // Whether this page is the front page for the wiki.
+    pub front_page: Option<bool>,
+    // Whether or not this is locked for the user.
+    pub locked_for_user: Option<bool>,
+}
+
+// https://canvas.instructure.com/doc/api/quizzes.html#Quiz
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Quiz {
+    // The ID of the quiz.
+    pub id: id::QuizId,
+    // The title of the quiz.
+    pub title: String,
+    // The HTTP/HTTPS URL to the quiz.
+    pub html_url: String,
+    // The description of the quiz.
+    pub description: Option<String>,
+    // Type of quiz possible values: 'practice_quiz', 'assignment',
+    // 'graded_survey', 'survey'.
+    pub quiz_type: String,
+    // The ID of the quiz's assignment group.
+    pub assignment_group_id: Option<id::AssignmentGroupId>,
+    // The ID of the assignment graded quizzes are backed by.
+    pub assignment_id: Option<id::AssignmentId>,
+    // Quiz time limit in minutes.
+    pub time_limit: Option<u64>,
+    // How many times a student can take the quiz -1 = unlimited attempts.
+    pub allowed_attempts: Option<i64>,
+    // The number of questions in the quiz.
+    pub question_count: Option<u64>,
+    // The total point value given to the quiz.
+    pub points_possible: Option<f64>,
+    // When the quiz is due.
+    pub due_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // When to lock the quiz.
+    pub lock_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // When to unlock the quiz.
+    pub unlock_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // Whether the quiz has a published or unpublished draft state.
+    pub published: Option<bool>,
+    // Whether the quiz is locked for the calling user.
+    pub locked_for_user: Option<bool>,
+}
+
+// https://canvas.instructure.com/doc/api/quiz_submissions.html#QuizSubmission
+#[derive(Debug, Deserialize, Serialize)]
+pub struct QuizSubmission {
+    // The ID of the quiz submission.
+    pub id: id::QuizSubmissionId,
+    // The ID of the Quiz the quiz submission belongs to.
+    pub quiz_id: id::QuizId,
+    // The ID of the Student that made the quiz submission.
+    pub user_id: Option<id::UserId>,
+    // The ID of the Submission the quiz submission represents.
+    pub submission_id: Option<id::SubmissionId>,
+    // The time at which the student started the quiz submission.
+    pub started_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The time at which the student submitted the quiz submission.
+    pub finished_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The time at which the quiz submission will be overdue, and be flagged
+    // as a late submission.
+    pub end_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // For quizzes that allow multiple attempts, this field specifies the
+    // quiz submission attempt number.
+    pub attempt: Option<u64>,
+    // Number of times the student was allowed to re-take the quiz over the
+    // multiple-attempt limit.
+    pub extra_attempts: Option<u64>,
+    // Amount of extra time allowed for the quiz submission, in minutes.
+    pub extra_time: Option<u64>,
+    // Amount of time spent, in seconds.
+    pub time_spent: Option<u64>,
+    // The score of the quiz submission, if graded.
+    pub score: Option<f64>,
+    // The original score of the quiz submission prior to any re-grading.
+    pub score_before_regrade: Option<f64>,
+    // For quizzes that allow multiple attempts, this is the score that will
+    // be used, which might be the score of the latest, or the highest, quiz
+    // submission.
+    pub kept_score: Option<f64>,
+    // Number of points the quiz submission's score was fudged by.
+    pub fudge_points: Option<f64>,
+    // Whether the student has viewed their results to the quiz.
+    pub has_seen_results: Option<bool>,
+    // The current state of the quiz submission. Possible values:
+    // ['untaken'|'pending_review'|'complete'|'settings_only'|'preview'].
+    pub workflow_state: String,
+    // Indicates whether the quiz submission is overdue and needs submission.
+    pub overdue_and_needs_submission: Option<bool>,
+}
+
+// The quiz submissions endpoint wraps its list rather than returning it bare.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct QuizSubmissionList {
+    pub quiz_submissions: Vec<QuizSubmission>,
+}
+
+// https://canvas.instructure.com/doc/api/quiz_statistics.html#QuizStatistics
+#[derive(Debug, Deserialize, Serialize)]
+pub struct QuizStatistics {
+    // The ID of the quiz statistics report.
+    pub id: id::QuizStatisticsId,
+    // The ID of the Quiz the statistics report is for.
+    pub quiz_id: id::QuizId,
+    // Whether there are any students that have made mutliple submissions for
+    // this quiz.
+    pub multiple_attempts_exist: Option<bool>,
+    // In the presence of multiple attempts, this field describes whether the
+    // statistics describe all the submission attempts and not only the latest
+    // ones.
+    pub includes_all_versions: Option<bool>,
+    // The time at which the statistics were generated, which is usually after
+    // the occurrence of a quiz event, like a student submitting it.
+    pub generated_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // Question-specific statistics for each question and its answers.
+    #[serde(default)]
+    pub question_statistics: Vec<serde_json::Value>,
+    // Question-agnostic submission statistics.
+    pub submission_statistics: Option<QuizSubmissionStatistics>,
+}
+
+// https://canvas.instructure.com/doc/api/quiz_statistics.html#QuizSubmissionStatistics
+#[derive(Debug, Deserialize, Serialize)]
+pub struct QuizSubmissionStatistics {
+    // The number of students who have taken the quiz.
+    pub unique_count: Option<u64>,
+    // The mean of the student submission scores.
+    pub score_average: Option<f64>,
+    // The highest submission score.
+    pub score_high: Option<f64>,
+    // The lowest submission score.
+    pub score_low: Option<f64>,
+    // Standard deviation of the submission scores.
+    pub score_stdev: Option<f64>,
+    // A percentile distribution of the student scores, each key is the
+    // percentile (ranges between 0 and 100%) while the value is the number of
+    // students who received that score.
+    #[serde(default)]
+    pub scores: HashMap<String, u64>,
+    // The mean of the number of questions answered correctly by each student.
+    pub correct_count_average: Option<f64>,
+    // The mean of the number of questions answered incorrectly by each
+    // student.
+    pub incorrect_count_average: Option<f64>,
+    // The mean of the duration (in seconds) each student has taken to submit
+    // the quiz.
+    pub duration_average: Option<f64>,
+}
+
+// The quiz statistics endpoint wraps its report rather than returning it
+// bare.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct QuizStatisticsList {
+    pub quiz_statistics: Vec<QuizStatistics>,
+}
+
+// A quiz made with New Quizzes, which Canvas keeps apart from classic quizzes
+// behind an API of its own. Each is backed by an assignment with the same id.
+// https://canvas.instructure.com/doc/api/new_quizzes.html
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NewQuiz {
+    // The ID of the quiz.
+    pub id: id::AssignmentId,
+    // The title of the quiz.
+    pub title: String,
+    // The HTML for the instructions of the quiz.
+    pub instructions: Option<String>,
+    // The ID of the quiz's assignment group.
+    pub assignment_group_id: Option<id::AssignmentGroupId>,
+    // The maximum points possible for the quiz.
+    pub points_possible: Option<f64>,
+    // When the quiz is due.
+    pub due_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // When to lock the quiz.
+    pub lock_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // When to unlock the quiz.
+    pub unlock_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // Whether the quiz is published.
+    pub published: Option<bool>,
+    // The type of grading the assignment receives.
+    pub grading_type: Option<String>,
+    // The settings for the quiz.
+    pub quiz_settings: Option<NewQuizSettings>,
+}
+
+// https://canvas.instructure.com/doc/api/new_quizzes.html#QuizSettings
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NewQuizSettings {
+    // Whether there is a time limit for the quiz.
+    pub has_time_limit: Option<bool>,
+    // Time limit for the quiz, in seconds.
+    pub session_time_limit_in_seconds: Option<u64>,
+    // Settings related to multiple attempts.
+    pub multiple_attempts: Option<NewQuizMultipleAttempts>,
+}
+
+// https://canvas.instructure.com/doc/api/new_quizzes.html#MultipleAttemptsSettings
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NewQuizMultipleAttempts {
+    // Whether multiple attempts for this quiz is true.
+    pub multiple_attempts_enabled: Option<bool>,
+    // Whether there is an attempt limit. Only set if
+    // multiple_attempts_enabled is true.
+    pub attempt_limit: Option<bool>,
+    // The allowed attempts a student has on this quiz. Only set if
+    // attempt_limit is true.
+    pub max_attempts: Option<u64>,
+    // Whichever score to keep for the attempts. One of 'average',
+    // 'first', 'highest', 'latest'.
+    pub score_to_keep: Option<String>,
+}
+
+// https://canvas.instructure.com/doc/api/discussion_topics.html#DiscussionTopic
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DiscussionTopic {
+    // The ID of this topic.
+    pub id: id::TopicId,
+    // The topic title.
+    pub title: String,
+    // The HTML content of the message body.
+    pub message: Option<String>,
+    // The URL to the discussion topic in canvas.
+    pub html_url: String,
+    // The datetime the topic was posted. If it is null it hasn't been posted
+    // yet.
+    pub posted_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The datetime for when the last reply was in the topic.
+    pub last_reply_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The count of entries in the topic.
+    pub discussion_subentry_count: Option<u64>,
+    // The unique identifier of the assignment if the topic is for grading,
+    // otherwise null.
+    pub assignment_id: Option<id::AssignmentId>,
+    // The type of discussion. Values are 'side_comment', for discussions
+    // that only allow one level of nested comments, and 'threaded' for fully
+    // threaded discussions.
+    pub discussion_type: Option<String>,
+    // Whether or not the discussion is 'closed for comments'.
+    pub locked: Option<bool>,
+    // Whether or not the discussion has been 'pinned' by an instructor.
+    pub pinned: Option<bool>,
+    // The username of the topic creator.
+    pub user_name: Option<String>,
+    // The user who posted the topic. Canvas leaves this empty for topics
+    // whose author is hidden, such as anonymous discussions.
+    #[serde(default, deserialize_with = "empty_object_as_none")]
+    pub author: Option<UserDisplay>,
+    // Whether this discussion topic is published (true) or draft state
+    // (false).
+    pub published: Option<bool>,
+    // The context the topic belongs to, such as course_123. Only included
+    // when listing announcements across several contexts.
+    pub context_code: Option<String>,
+}
+
+// The full tree of a discussion, as the view endpoint hands it back.
+// https://canvas.instructure.com/doc/api/discussion_topics.html#method.discussion_topics_api.view
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DiscussionView {
+    // The ids of the entries the caller hasn't read.
+    #[serde(default)]
+    pub unread_entries: Vec<u64>,
+    // The ids of the entries the caller has marked as read by hand.
+    #[serde(default)]
+    pub forced_entries: Vec<u64>,
+    // Everyone who has posted in the discussion.
+    #[serde(default)]
+    pub participants: Vec<UserDisplay>,
+    // The top-level entries, each with its replies nested beneath it.
+    #[serde(default)]
+    pub view: Vec<DiscussionEntry>,
+    // Entries too recent to have made it into the view yet, each with the id
+    // of its parent. Only included when 'include_new_entries' is passed.
+    #[serde(default)]
+    pub new_entries: Vec<DiscussionEntry>,
+}
+
+// https://canvas.instructure.com/doc/api/discussion_topics.html#method.discussion_topics_api.view
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DiscussionEntry {
+    // The unique identifier for the entry.
+    pub id: id::EntryId,
+    // The unique identifier for the author of the entry. Deleted entries
+    // have none.
+    pub user_id: Option<id::UserId>,
+    // The unique identifier of the entry this entry replies to, or null for
+    // a top-level entry.
+    pub parent_id: Option<id::EntryId>,
+    // The unique identifier of the user who last edited the entry, if it
+    // isn't the author.
+    pub editor_id: Option<id::UserId>,
+    // The content of the entry.
+    pub message: Option<String>,
+    // The creation time of the entry.
+    pub created_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The time the entry was last updated.
+    pub updated_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The number of ratings the entry has.
+    pub rating_count: Option<u64>,
+    // The sum of the ratings the entry has.
+    pub rating_sum: Option<u64>,
+    // Whether the entry has been deleted.
+    pub deleted: Option<bool>,
+    // The entries replying to this one.
+    #[serde(default)]
+    pub replies: Vec<DiscussionEntry>,
+}
+
+// https://canvas.instructure.com/doc/api/files.html#Folder
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Folder {
+    pub id: id::FolderId,
+    // The folder's name.
+    pub name: String,
+    // The folder's path from the root of its context, such as
+    // "course files/Readings/Week 1".
+    pub full_name: String,
+    // The folder this one is in, or null for the root folder.
+    pub parent_folder_id: Option<id::FolderId>,
+    pub files_count: u64,
+    pub folders_count: u64,
+    pub updated_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    pub locked: Option<bool>,
+    pub hidden: Option<bool>,
+    // Whether the folder is locked or hidden for the current user.
+    pub locked_for_user: Option<bool>,
+    pub hidden_for_user: Option<bool>,
+}
+
+// https://canvas.instructure.com/doc/api/files.html#File
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct File {
+    pub id: id::FileId,
+    pub folder_id: id::FolderId,
+    // The name shown in Canvas, which may differ from the name the file was
+    // uploaded with.
+    pub display_name: String,
+    pub filename: String,
+    #[serde(rename = "content-type")]
+    pub content_type: Option<String>,
+    // Where to download the file from. Empty when the file is locked for the
+    // current user.
+    pub url: String,
+    // The file size in bytes.
+    pub size: u64,
+    pub created_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    pub updated_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // When the file's content last changed, as opposed to its name or place.
+    pub modified_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    pub locked: Option<bool>,
+    pub hidden: Option<bool>,
+    pub locked_for_user: Option<bool>,
+}
+
+// https://canvas.instructure.com/doc/api/calendar_events.html#CalendarEvent
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CalendarEvent {
+    // The ID of the calendar event.
+    pub id: id::CalendarEventId,
+    // The title of the calendar event.
+    pub title: String,
+    // The start timestamp of the event.
+    pub start_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The end timestamp of the event.
+    pub end_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+    // The HTML description of the event.
+    pub description: Option<String>,
+    // The location name of the event.
+    pub location_name: Option<String>,
+    // The address where the event is taking place.
+    pub location_address: Option<String>,
+    // The context code of the calendar this event belongs to (course, user or
+    // group).
+    pub context_code: String,
+    // Current state of the event ('active', 'locked' or 'deleted') 'locked'
+    // indicates that start_at/end_at cannot be changed (though the event could
+    // be deleted). Normally only reservations or time slots reserved by
+    // appointment groups will be 'locked'.
+    pub workflow_state: String,
+    // URL for a user to view this event.
+    pub html_url: Option<String>,
+    // Boolean indicating whether this is an all-day event (midnight to
+    // midnight).
+    #[serde(default)]
+    pub all_day: bool,
+    // The date of this event.
+    pub all_day_date: Option<chrono::NaiveDate>,
+    // When the calendar event was last updated.
+    pub updated_at: Option<chrono::DateTime<chrono::prelude::Local>>,
+}
+
+// https://canvas.instructure.com/doc/api/enrollments.html#method.enrollments_api.index
+#[derive(ArgEnum, Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum EnrollmentType {
+    #[serde(rename = "StudentEnrollment")]
+    Student,
+    #[serde(rename = "TeacherEnrollment")]
+    Teacher,
+    #[serde(rename = "TaEnrollment")]
+    Ta,
+    #[serde(rename = "DesignerEnrollment")]
+    Designer,
+    #[serde(rename = "ObserverEnrollment")]
+    Observer,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnrollmentState {
+    Active,
+    Invited,
+    CreationPending,
+    Deleted,
+    Rejected,
+    Completed,
+    Inactive,
+    CurrentAndInvited,
+    CurrentAndFuture,
+    CurrentAndConcluded,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnrollmentInclude {
+    AvatarUrl,
+    GroupIds,
+    CurrentPoints,
+    Uuid,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionInclude {
+    SubmissionHistory,
+    SubmissionComments,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionInclude {
+    Students,
+    TotalStudents,
+}
+
+/// Filters for listing enrollments. Everything left empty is left up to
+/// Canvas' defaults, which is what an EnrollmentQuery::default() gives you.
+#[derive(Clone, Debug, Default)]
+pub struct EnrollmentQuery {
+    pub types: Vec<EnrollmentType>,
+    // Roles can be customized per account, so they're free-form names.
+    pub roles: Vec<String>,
+    pub states: Vec<EnrollmentState>,
+    pub user_id: Option<id::IdRef<id::UserId>>,
+    // Only enrollments in one of these sections, or in any section if empty.
+    pub sections: Vec<id::IdRef<id::SectionId>>,
+    pub grading_period_id: Option<id::GradingPeriodId>,
+    pub include: Vec<EnrollmentInclude>,
+}
+
+impl EnrollmentQuery {
+    pub fn to_query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![("per_page", PER_PAGE.to_string())];
+        query.extend(self.types.iter().map(|t| ("type[]", serde_name(t))));
+        query.extend(self.roles.iter().map(|r| ("role[]", r.clone())));
+        query.extend(self.states.iter().map(|s| ("state[]", serde_name(s))));
+        // Canvas filters on SIS user ids itself, but nothing else SIS knows a
+        // user by, so those are left to user_matches.
+        match &self.user_id {
+            Some(id::IdRef::Id(id)) => query.push(("user_id", id.to_string())),
+            Some(id::IdRef::Sis { prefix: "sis_user_id", id }) => {
+                query.push(("sis_user_id[]", id.clone()))
+            },
+            _ => {},
+        }
+        // Likewise for sections, but only when every section is given that
+        // way, since Canvas would otherwise leave the rest out.
+        if let Some(sis_ids) = self.sis_section_ids() {
+            query.extend(
+                sis_ids.into_iter().map(|id| ("sis_section_id[]", id)),
+            );
+        }
+        query.extend(
+            self.grading_period_id
+                .map(|id| ("grading_period_id", id.to_string())),
+        );
+        query.extend(self.include.iter().map(|i| ("include[]", serde_name(i))));
+        query
+    }
+
+    /// Whether an enrollment passes the filters Canvas can't apply itself.
+    pub fn matches(&self, enrollment: &Enrollment) -> bool {
+        let user_matches = match &self.user_id {
+            Some(user_ref @ id::IdRef::Sis { prefix, .. })
+                if *prefix != "sis_user_id" => {
+                sis::enrollment_user_matches(enrollment, user_ref)
+            },
+            _ => true,
+        };
+        let section_matches = self.sections.is_empty()
+            || self.sis_section_ids().is_some()
+            || self.sections
+                .iter()
+                .any(|r| sis::enrollment_section_matches(enrollment, r));
+        user_matches && section_matches
+    }
+
+    fn sis_section_ids(&self) -> Option<Vec<String>> {
+        if self.sections.is_empty() {
+            return None;
+        }
+        self.sections
+            .iter()
+            .map(|r| match r {
+                id::IdRef::Sis { prefix: "sis_section_id", id } => {
+                    Some(id.clone())
+                },
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl EnrollmentType {
+    /// The name Canvas uses for this type on enrollments, such as
+    /// "StudentEnrollment".
+    pub fn canvas_name(&self) -> String {
+        serde_name(self)
+    }
+
+    // The courses endpoint filters on a shorter form of the enrollment type than
+    // the one enrollments themselves use.
+    fn short_name(&self) -> &'static str {
+        match self {
+            EnrollmentType::Student => "student",
+            EnrollmentType::Teacher => "teacher",
+            EnrollmentType::Ta => "ta",
+            EnrollmentType::Designer => "designer",
+            EnrollmentType::Observer => "observer",
+        }
+    }
+}
+
+// The enrollment states the courses endpoint can filter on. These differ from
+// the ones the enrollments endpoint takes.
+#[derive(ArgEnum, Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseEnrollmentState {
+    Active,
+    InvitedOrPending,
+    Completed,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseState {
+    Unpublished,
+    Available,
+    Completed,
+    Deleted,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseInclude {
+    Term,
+    TotalScores,
+    CurrentGradingPeriodScores,
+    Teachers,
+    TotalStudents,
+    SyllabusBody,
+    CourseProgress,
+    // The enrollments of the students an observer observes, alongside the
+    // observer's own.
+    ObservedUsers,
+}
+
+/// Filters for listing the current user's courses. As with EnrollmentQuery,
+/// the default leaves everything up to Canvas.
+#[derive(Clone, Debug, Default)]
+pub struct CourseQuery {
+    pub enrollment_type: Option<EnrollmentType>,
+    pub enrollment_role: Option<String>,
+    pub enrollment_state: Option<CourseEnrollmentState>,
+    pub states: Vec<CourseState>,
+    pub include: Vec<CourseInclude>,
+}
+
+impl CourseQuery {
+    /// Ask for an extra include on top of whatever was already requested, for
+    /// commands which can't work without it.
+    pub fn with_include(mut self, include: CourseInclude) -> Self {
+        if !self.include.contains(&include) {
+            self.include.push(include);
+        }
+        self
+    }
+
+    pub fn to_query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![("per_page", PER_PAGE.to_string())];
+        query.extend(
+            self.enrollment_type
+                .map(|t| ("enrollment_type", t.short_name().to_string())),
+        );
+        query.extend(
+            self.enrollment_role
+                .iter()
+                .map(|r| ("enrollment_role", r.clone())),
+        );
+        query.extend(
+            self.enrollment_state
+                .map(|s| ("enrollment_state", serde_name(&s))),
+        );
+        query.extend(self.states.iter().map(|s| ("state[]", serde_name(s))));
+        query.extend(self.include.iter().map(|i| ("include[]", serde_name(i))));
+        query
+    }
+}
+
+// The largest page Canvas will hand back. Fewer pages means fewer requests
+// against the rate limit.
+const PER_PAGE: u32 = 100;
+
+// Canvas hands back an empty object rather than null for some missing
+// values, such as the author of an anonymous discussion.
+fn empty_object_as_none<'de, D, A>(
+    deserializer: D,
+) -> Result<Option<A>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    A: serde::de::DeserializeOwned,
+{
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(serde_json::Value::Object(o)) if o.is_empty() => Ok(None),
+        Some(value) => serde_json::from_value(value)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+// The enums above already know their Canvas names through serde, so lean on
+// that rather than keeping a second copy of every name for query strings.
+fn serde_name<A: Serialize>(a: &A) -> String {
+    serde_json::to_value(a)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+/// The caller's courses or, running for a student the caller observes, the
+/// student's, each with the enrollments of whoever they were listed for.
+/// Running for any other student, such as one of the caller's own, it's the
+/// caller's courses which the student is in.
 pub async fn courses<'a>(
     config: &'a cli::CliValid,
+    query: &CourseQuery,
 ) -> Result<Vec<Course>, error::AppError> {
-    http::request::<Vec<Course>>(
+    let path = match (config.student, config.observing) {
+        (Some(student_id), true) => format!("users/{}/courses", student_id),
+        _ => "courses".to_string(),
+    };
+    let courses = http::request_paginated::<Course>(
         config,
         reqwest::Method::GET,
-        "https://canvas.instructure.com/api/v1/courses".to_string(),
-    ).await
+        http::api_url_query(config, &path, &query.to_query()),
+    ).await?;
+    match config.student {
+        Some(student_id) if !config.observing => {
+            Ok(courses_with_student(config, courses, student_id).await)
+        },
+        _ => Ok(courses),
+    }
+}
+
+// Courses where the student's enrollments can't be listed are ones the caller
+// doesn't teach, so they go along with those the student isn't in.
+async fn courses_with_student(
+    config: &cli::CliValid,
+    courses: Vec<Course>,
+    student_id: id::UserId,
+) -> Vec<Course> {
+    let query = EnrollmentQuery {
+        user_id: Some(id::IdRef::Id(student_id)),
+        ..Default::default()
+    };
+    let enrollment_futures = courses
+        .iter()
+        .map(|c| enrollments_by_course(config, c.id, &query));
+    let enrollments = join_all(enrollment_futures).await;
+    courses
+        .into_iter()
+        .zip(enrollments)
+        .filter(|(_, enrollments)| {
+            enrollments.as_ref().map(|e| !e.is_empty()).unwrap_or(false)
+        })
+        .map(|(course, _)| course)
+        .collect()
 }
 
 // For whatever reason, the Canvas API call here fails with a 500 from the
@@ -320,14 +1557,744 @@ pub async fn courses<'a>(
 // indicate the call is made in error on my part.
 pub async fn enrollments_by_course<'a>(
     config: &'a cli::CliValid,
-    course_id: u64,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+    query: &EnrollmentQuery,
+) -> Result<Vec<Enrollment>, error::AppError> {
+    let course_id = course_id.into();
+    let enrollments = http::request_paginated::<Enrollment>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/enrollments", course_id),
+            &query.to_query(),
+        ),
+    ).await?;
+    Ok(
+        enrollments
+            .into_iter()
+            .filter(|e| query.matches(e))
+            .collect()
+    )
+}
+
+/// Every enrollment across the given courses, fetched concurrently.
+pub async fn enrollments_by_courses(
+    config: &cli::CliValid,
+    courses: &[Course],
+    query: &EnrollmentQuery,
 ) -> Result<Vec<Enrollment>, error::AppError> {
-    http::request::<Vec<Enrollment>>(
+    let enrollment_futures = courses
+        .iter()
+        .map(|c| { enrollments_by_course(config, c.id, query) });
+    Ok(
+        try_join_all(enrollment_futures)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<Enrollment>>()
+    )
+}
+
+/// A course's sections, with whatever extra data is asked for.
+pub async fn sections(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+    include: &[SectionInclude],
+) -> Result<Vec<Section>, error::AppError> {
+    let course_id = course_id.into();
+    let mut query = vec![("per_page", PER_PAGE.to_string())];
+    query.extend(include.iter().map(|i| ("include[]", serde_name(i))));
+    http::request_paginated::<Section>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/sections", course_id),
+            &query,
+        ),
+    ).await
+}
+
+/// A single section, which may be given by SIS id.
+pub async fn section(
+    config: &cli::CliValid,
+    section_id: impl Into<id::IdRef<id::SectionId>>,
+) -> Result<Section, error::AppError> {
+    let section_id = section_id.into();
+    http::request::<Section>(
+        config,
+        reqwest::Method::GET,
+        http::api_url(config, &format!("sections/{}", section_id)),
+    ).await
+}
+
+/// The groups in a course, across all of its group categories.
+pub async fn course_groups(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Vec<Group>, error::AppError> {
+    let course_id = course_id.into();
+    http::request_paginated::<Group>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/groups", course_id),
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+    ).await
+}
+
+pub async fn group_categories(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Vec<GroupCategory>, error::AppError> {
+    let course_id = course_id.into();
+    http::request_paginated::<GroupCategory>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/group_categories", course_id),
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+    ).await
+}
+
+/// The members of a group.
+pub async fn group_users(
+    config: &cli::CliValid,
+    group_id: impl Into<id::IdRef<id::GroupId>>,
+) -> Result<Vec<User>, error::AppError> {
+    let group_id = group_id.into();
+    http::request_paginated::<User>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("groups/{}/users", group_id),
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+    ).await
+}
+
+/// A course's modules along with their items. Passing a student id gets the
+/// completion state for that student rather than the caller.
+pub async fn modules(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+    student_id: Option<id::UserId>,
+) -> Result<Vec<Module>, error::AppError> {
+    let course_id = course_id.into();
+    let mut query = vec![
+        ("per_page", PER_PAGE.to_string()),
+        ("include[]", "items".to_string()),
+    ];
+    query.extend(student_id.map(|id| ("student_id", id.to_string())));
+    let mut modules = http::request_paginated::<Module>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/modules", course_id),
+            &query,
+        ),
+    ).await?;
+    // Canvas leaves the items off of modules it considers too large, which
+    // then have to be asked for separately.
+    for module in modules.iter_mut().filter(|m| m.items.is_none()) {
+        module.items = Some(
+            module_items(
+                config,
+                course_id.clone(),
+                module.id,
+                student_id,
+            ).await?,
+        );
+    }
+    Ok(modules)
+}
+
+pub async fn module_items(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+    module_id: id::ModuleId,
+    student_id: Option<id::UserId>,
+) -> Result<Vec<ModuleItem>, error::AppError> {
+    let course_id = course_id.into();
+    let mut query = vec![("per_page", PER_PAGE.to_string())];
+    query.extend(student_id.map(|id| ("student_id", id.to_string())));
+    http::request_paginated::<ModuleItem>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/modules/{}/items", course_id, module_id),
+            &query,
+        ),
+    ).await
+}
+
+// The analytics endpoints are only there when the account has analytics
+// turned on, and generally only for teachers and admins.
+pub async fn course_activity(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Vec<CourseActivity>, error::AppError> {
+    let course_id = course_id.into();
+    http::request::<Vec<CourseActivity>>(
+        config,
+        reqwest::Method::GET,
+        http::api_url(
+            config,
+            &format!("courses/{}/analytics/activity", course_id),
+        ),
+    ).await
+}
+
+pub async fn student_activity(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+    student_id: id::UserId,
+) -> Result<StudentActivity, error::AppError> {
+    let course_id = course_id.into();
+    http::request::<StudentActivity>(
+        config,
+        reqwest::Method::GET,
+        http::api_url(
+            config,
+            &format!(
+                "courses/{}/analytics/users/{}/activity",
+                course_id,
+                student_id,
+            ),
+        ),
+    ).await
+}
+
+// Only account admins may list terms, so expect this to fail for everyone
+// else.
+pub async fn enrollment_terms(
+    config: &cli::CliValid,
+    account_id: impl Into<id::IdRef<id::AccountId>>,
+) -> Result<Vec<EnrollmentTerm>, error::AppError> {
+    let account_id = account_id.into();
+    http::request_paginated_by::<EnrollmentTermList, EnrollmentTerm>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("accounts/{}/terms", account_id),
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+        |list| list.enrollment_terms,
+    ).await
+}
+
+/// A single course, along with its term and the caller's own scores in it.
+/// Running for a student the caller observes, the enrollments are the
+/// student's instead.
+pub async fn course(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Course, error::AppError> {
+    let course_id = course_id.into();
+    let mut query = vec![
+        ("include[]", serde_name(&CourseInclude::Term)),
+        ("include[]", serde_name(&CourseInclude::TotalScores)),
+    ];
+    let observed = config.student.filter(|_| config.observing);
+    if observed.is_some() {
+        query.push(("include[]", serde_name(&CourseInclude::ObservedUsers)));
+    }
+    let mut course = http::request::<Course>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(config, &format!("courses/{}", course_id), &query),
+    ).await?;
+    if let Some(student_id) = observed {
+        course.enrollments.retain(|e| e.user_id == student_id);
+    }
+    Ok(course)
+}
+
+/// A course's assignments, each with the caller's own submission and, when
+/// the teacher shares them, the course wide score statistics. Running for a
+/// student, each carries the student's submission instead.
+pub async fn assignments(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Vec<Assignment>, error::AppError> {
+    let course_id = course_id.into();
+    let mut assignments = http::request_paginated::<Assignment>(
         config,
         reqwest::Method::GET,
-        format!(
-            "https://canvas.instructure.com/api/v1/courses/{}/enrollments",
+        http::api_url_query(
+            config,
+            &format!("courses/{}/assignments", course_id),
+            &[
+                ("per_page", PER_PAGE.to_string()),
+                ("include[]", "submission".to_string()),
+                ("include[]", "score_statistics".to_string()),
+            ],
+        ),
+    ).await?;
+    if let Some(student_id) = config.student {
+        let mut submissions = student_submissions(
+            config,
             course_id,
+            student_id,
+            &[],
+        )
+            .await?
+            .into_iter()
+            .map(|s| (s.assignment_id, s))
+            .collect::<HashMap<id::AssignmentId, Submission>>();
+        for assignment in assignments.iter_mut() {
+            assignment.submission = submissions.remove(&assignment.id);
+        }
+    }
+    Ok(assignments)
+}
+
+/// Every assignment across the given courses, fetched concurrently.
+pub async fn assignments_by_courses(
+    config: &cli::CliValid,
+    courses: &[Course],
+) -> Result<Vec<Assignment>, error::AppError> {
+    let assignment_futures = courses
+        .iter()
+        .map(|c| assignments(config, c.id));
+    Ok(
+        try_join_all(assignment_futures)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<Assignment>>()
+    )
+}
+
+pub async fn assignment_groups(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Vec<AssignmentGroup>, error::AppError> {
+    let course_id = course_id.into();
+    http::request_paginated::<AssignmentGroup>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/assignment_groups", course_id),
+            &[("per_page", PER_PAGE.to_string())],
         ),
     ).await
 }
+
+// Students only ever get their own submission back from this, so it's only
+// worth asking when the caller teaches the course.
+pub async fn submissions(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+    assignment_id: id::AssignmentId,
+) -> Result<Vec<Submission>, error::AppError> {
+    let course_id = course_id.into();
+    http::request_paginated::<Submission>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!(
+                "courses/{}/assignments/{}/submissions",
+                course_id,
+                assignment_id,
+            ),
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+    ).await
+}
+
+/// A single student's submissions across a course. Observers can ask this of
+/// the students they observe, and teachers of anyone in the course.
+pub async fn student_submissions(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+    student_id: id::UserId,
+    include: &[SubmissionInclude],
+) -> Result<Vec<Submission>, error::AppError> {
+    let course_id = course_id.into();
+    let mut query = vec![
+        ("per_page", PER_PAGE.to_string()),
+        ("student_ids[]", student_id.to_string()),
+    ];
+    query.extend(include.iter().map(|i| ("include[]", serde_name(i))));
+    http::request_paginated::<Submission>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/students/submissions", course_id),
+            &query,
+        ),
+    ).await
+}
+
+pub async fn quizzes(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Vec<Quiz>, error::AppError> {
+    let course_id = course_id.into();
+    http::request_paginated::<Quiz>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/quizzes", course_id),
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+    ).await
+}
+
+/// Quiz submissions for a classic quiz. Students only get their own back,
+/// while teachers get the latest attempt of everyone in the course.
+pub async fn quiz_submissions(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+    quiz_id: id::QuizId,
+) -> Result<Vec<QuizSubmission>, error::AppError> {
+    let course_id = course_id.into();
+    http::request_paginated_by::<QuizSubmissionList, QuizSubmission>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/quizzes/{}/submissions", course_id, quiz_id),
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+        |list| list.quiz_submissions,
+    ).await
+}
+
+/// How the class did on a classic quiz, across every attempt. Only those who
+/// can grade the quiz may see this.
+pub async fn quiz_statistics(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+    quiz_id: id::QuizId,
+) -> Result<Option<QuizStatistics>, error::AppError> {
+    let course_id = course_id.into();
+    http::request::<QuizStatisticsList>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/quizzes/{}/statistics", course_id, quiz_id),
+            &[("all_versions", "true".to_string())],
+        ),
+    ).await.map(|list| list.quiz_statistics.into_iter().next())
+}
+
+/// A course's New Quizzes, which don't show up among its classic quizzes.
+pub async fn new_quizzes(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Vec<NewQuiz>, error::AppError> {
+    let course_id = course_id.into();
+    http::request_paginated::<NewQuiz>(
+        config,
+        reqwest::Method::GET,
+        http::quiz_api_url_query(
+            config,
+            &format!("courses/{}/quizzes", course_id),
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+    ).await
+}
+
+/// A user's submission for an assignment along with every earlier attempt,
+/// for the caller without a user id.
+pub async fn submission_history(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+    assignment_id: id::AssignmentId,
+    user_id: Option<id::IdRef<id::UserId>>,
+) -> Result<Vec<Submission>, error::AppError> {
+    let course_id = course_id.into();
+    let submission = http::request::<Submission>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!(
+                "courses/{}/assignments/{}/submissions/{}",
+                course_id,
+                assignment_id,
+                user_path(config, user_id),
+            ),
+            &[("include[]", "submission_history".to_string())],
+        ),
+    ).await?;
+    Ok(submission.submission_history.unwrap_or_default())
+}
+
+/// A course's pages, without their bodies.
+pub async fn pages(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Vec<Page>, error::AppError> {
+    let course_id = course_id.into();
+    http::request_paginated::<Page>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/pages", course_id),
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+    ).await
+}
+
+/// A single page, body and all, by its url or id.
+pub async fn page(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+    url: &str,
+) -> Result<Page, error::AppError> {
+    let course_id = course_id.into();
+    http::request::<Page>(
+        config,
+        reqwest::Method::GET,
+        http::api_url(config, &format!("courses/{}/pages/{}", course_id, url)),
+    ).await
+}
+
+/// A course's syllabus as HTML, if it has one.
+pub async fn syllabus(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Option<String>, error::AppError> {
+    let course_id = course_id.into();
+    let course = http::request::<Course>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}", course_id),
+            &[("include[]", serde_name(&CourseInclude::SyllabusBody))],
+        ),
+    ).await?;
+    Ok(course.syllabus_body.filter(|body| !body.trim().is_empty()))
+}
+
+/// Every folder in a course, nested ones included.
+pub async fn course_folders(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Vec<Folder>, error::AppError> {
+    let course_id = course_id.into();
+    http::request_paginated::<Folder>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/folders", course_id),
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+    ).await
+}
+
+/// Every file in a course, from all of its folders.
+pub async fn course_files(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Vec<File>, error::AppError> {
+    let course_id = course_id.into();
+    http::request_paginated::<File>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/files", course_id),
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+    ).await
+}
+
+pub async fn discussion_topics(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Vec<DiscussionTopic>, error::AppError> {
+    let course_id = course_id.into();
+    http::request_paginated::<DiscussionTopic>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!("courses/{}/discussion_topics", course_id),
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+    ).await
+}
+
+/// Every entry in a discussion along with who posted them. Canvas builds the
+/// view in the background, so entries newer than it come back alongside.
+pub async fn discussion_view(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+    topic_id: id::TopicId,
+) -> Result<DiscussionView, error::AppError> {
+    let course_id = course_id.into();
+    http::request::<DiscussionView>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!(
+                "courses/{}/discussion_topics/{}/view",
+                course_id,
+                topic_id,
+            ),
+            &[("include_new_entries", "1".to_string())],
+        ),
+    ).await
+}
+
+// Canvas only takes so many contexts in one request.
+const CONTEXT_CODES_MAX: usize = 10;
+
+/// Every calendar event on the given courses' calendars, rather than just the
+/// upcoming ones Canvas lists by default.
+pub async fn calendar_events(
+    config: &cli::CliValid,
+    course_ids: &[id::CourseId],
+) -> Result<Vec<CalendarEvent>, error::AppError> {
+    let mut events = vec![];
+    for chunk in course_ids.chunks(CONTEXT_CODES_MAX) {
+        let mut query = vec![
+            ("per_page", PER_PAGE.to_string()),
+            ("type", "event".to_string()),
+            ("all_events", "true".to_string()),
+        ];
+        query.extend(
+            chunk.iter().map(|id| ("context_codes[]", format!("course_{}", id))),
+        );
+        events.extend(
+            http::request_paginated::<CalendarEvent>(
+                config,
+                reqwest::Method::GET,
+                http::api_url_query(config, "calendar_events", &query),
+            ).await?,
+        );
+    }
+    Ok(events)
+}
+
+/// Announcements posted to the given courses between two times. Left to
+/// itself Canvas only lists the last two weeks of them.
+// https://canvas.instructure.com/doc/api/announcements.html#method.announcements_api.index
+pub async fn announcements(
+    config: &cli::CliValid,
+    course_ids: &[id::CourseId],
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<DiscussionTopic>, error::AppError> {
+    let mut announcements = vec![];
+    for chunk in course_ids.chunks(CONTEXT_CODES_MAX) {
+        let mut query = vec![
+            ("per_page", PER_PAGE.to_string()),
+            ("start_date", start.to_rfc3339()),
+            ("end_date", end.to_rfc3339()),
+        ];
+        query.extend(
+            chunk.iter().map(|id| ("context_codes[]", format!("course_{}", id))),
+        );
+        announcements.extend(
+            http::request_paginated::<DiscussionTopic>(
+                config,
+                reqwest::Method::GET,
+                http::api_url_query(config, "announcements", &query),
+            ).await?,
+        );
+    }
+    Ok(announcements)
+}
+
+/// The raw iCalendar feed Canvas publishes for a course. Feeds are public
+/// URLs, so they're fetched without the API token.
+pub async fn course_calendar_feed(
+    course: &Course,
+) -> Result<String, error::AppError> {
+    http::request_public_text(course.calendar.ics.clone()).await
+}
+
+/// A user's profile or, without a user id, that of whoever the command is
+/// running for.
+pub async fn profile(
+    config: &cli::CliValid,
+    user_id: Option<id::IdRef<id::UserId>>,
+) -> Result<Profile, error::AppError> {
+    http::request::<Profile>(
+        config,
+        reqwest::Method::GET,
+        http::api_url(
+            config,
+            &format!("users/{}/profile", user_path(config, user_id)),
+        ),
+    ).await
+}
+
+/// Assignments past due which a user hasn't turned in, across the given
+/// courses. Without a user id these are for whoever the command is running
+/// for.
+pub async fn missing_submissions(
+    config: &cli::CliValid,
+    user_id: Option<id::IdRef<id::UserId>>,
+    course_ids: &[id::CourseId],
+) -> Result<Vec<Assignment>, error::AppError> {
+    // Leave out anything which can't be turned in through Canvas, such as
+    // assignments handed in on paper.
+    let mut query = vec![
+        ("per_page", PER_PAGE.to_string()),
+        ("filter[]", "submittable".to_string()),
+    ];
+    query.extend(course_ids.iter().map(|id| ("course_ids[]", id.to_string())));
+    http::request_paginated::<Assignment>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            &format!(
+                "users/{}/missing_submissions",
+                user_path(config, user_id),
+            ),
+            &query,
+        ),
+    ).await
+}
+
+/// The students the caller observes, such as a parent's children.
+pub async fn observees(
+    config: &cli::CliValid,
+) -> Result<Vec<User>, error::AppError> {
+    http::request_paginated::<User>(
+        config,
+        reqwest::Method::GET,
+        http::api_url_query(
+            config,
+            "users/self/observees",
+            &[("per_page", PER_PAGE.to_string())],
+        ),
+    ).await
+}
+
+// Canvas takes "self" in place of a user id to mean the caller, which is who
+// a command is about unless it's running for a student.
+fn user_path(
+    config: &cli::CliValid,
+    user_id: Option<id::IdRef<id::UserId>>,
+) -> String {
+    user_id
+        .or_else(|| config.student.map(id::IdRef::Id))
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| "self".to_string())
+}
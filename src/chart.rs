@@ -0,0 +1,154 @@
+use clap::ArgEnum;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::path::PathBuf;
+
+use crate::error;
+use crate::terminal;
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ChartFormat {
+    Png,
+    Svg,
+    Terminal,
+}
+
+impl ChartFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ChartFormat::Png => "png",
+            ChartFormat::Svg => "svg",
+            ChartFormat::Terminal => "txt",
+        }
+    }
+}
+
+/// What a chart can be split up by, either as colored groups within a single
+/// plot or as one plot per value.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Grouping {
+    Term,
+}
+
+/// Where a chart gets written and how large it is. Images always have a path
+/// and are sized in pixels. Terminal charts are sized in characters, and go to
+/// stdout without a path.
+#[derive(Clone, Debug)]
+pub struct ChartOutput {
+    pub format: ChartFormat,
+    pub path: Option<PathBuf>,
+    pub size: (u32, u32),
+}
+
+/// Anything which can draw itself onto a plotters drawing area. Charts are
+/// written against this rather than a concrete backend so the same chart can
+/// come out as a PNG, an SVG or text.
+pub trait Chart {
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>>;
+}
+
+pub fn render<C: Chart>(
+    output: &ChartOutput,
+    chart: &C,
+) -> Result<(), error::AppError> {
+    if let Some(path) = &output.path {
+        log::info!("Writing chart to {:?}.", path);
+    }
+    match (output.format, &output.path) {
+        (ChartFormat::Png, Some(path)) => {
+            let root = BitMapBackend::new(path, output.size)
+                .into_drawing_area();
+            chart.draw(&root)
+                .and_then(|_| root.present())
+                .map_err(draw_error)
+        },
+        (ChartFormat::Svg, Some(path)) => {
+            let root = SVGBackend::new(path, output.size)
+                .into_drawing_area();
+            chart.draw(&root)
+                .and_then(|_| root.present())
+                .map_err(draw_error)
+        },
+        (ChartFormat::Terminal, path) => {
+            let root = terminal::TextBackend::new(path.as_deref(), output.size)
+                .into_drawing_area();
+            chart.draw(&root)
+                .and_then(|_| root.present())
+                .map_err(draw_error)
+        },
+        (_, None) => Err(error::AppError::ChartDrawError(
+            "Images can only be written to a file.".to_string(),
+        )),
+    }
+}
+
+/// Draw a chart as an SVG document in memory, for embedding in other
+/// documents.
+pub fn render_svg<C: Chart>(
+    chart: &C,
+    size: (u32, u32),
+) -> Result<String, error::AppError> {
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, size).into_drawing_area();
+        chart.draw(&root)
+            .and_then(|_| root.present())
+            .map_err(draw_error)?;
+    }
+    Ok(svg)
+}
+
+/// A stable color for the nth series or group in a chart.
+pub fn series_color(index: usize) -> PaletteColor<Palette99> {
+    Palette99::pick(index)
+}
+
+/// Cut a label down to at most max characters, marking where it was cut.
+pub fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else if max == 0 {
+        String::new()
+    } else {
+        format!("{}…", s.chars().take(max - 1).collect::<String>())
+    }
+}
+
+// Plotters errors are generic over the backend, which isn't something we want
+// leaking into AppError.
+fn draw_error<E: std::error::Error + Send + Sync>(
+    e: DrawingAreaErrorKind<E>,
+) -> error::AppError {
+    error::AppError::ChartDrawError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_leaves_short_labels() {
+        assert_eq!(truncate("Quiz 1", 6), "Quiz 1");
+        assert_eq!(truncate("", 3), "");
+    }
+
+    #[test]
+    fn truncate_marks_the_cut() {
+        assert_eq!(truncate("Midterm Exam", 8), "Midterm…");
+        assert_eq!(truncate("Midterm Exam", 1), "…");
+    }
+
+    #[test]
+    fn truncate_counts_characters_not_bytes() {
+        assert_eq!(truncate("Résumé", 6), "Résumé");
+        assert_eq!(truncate("Résumé draft", 6), "Résum…");
+    }
+
+    #[test]
+    fn truncate_to_nothing() {
+        assert_eq!(truncate("Quiz", 0), "");
+    }
+}
@@ -1,28 +1,500 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::activity;
+use crate::announcements;
+use crate::calendar;
+use crate::canvas;
+use crate::chart;
 use crate::config;
 use crate::error;
+use crate::graph;
+use crate::http;
+use crate::id;
 use crate::logging;
+use crate::missing;
+use crate::modules;
+use crate::network;
+use crate::observer;
+use crate::term;
+use crate::terminal;
+use crate::workload;
 
 /// The various inputs aggregated into one place - not yet validated.
 #[derive(Parser, Debug)]
 #[clap(
     name = "canvas-instructure-graph",
     about = "Create a graph from Instructure's Canvas HTTP API.",
+    after_help = "Courses, sections and users can be given by SIS id wherever \
+                  an id is taken, as in sis_course_id:ABC123, \
+                  sis_section_id:ABC123-01, sis_user_id:A0001 or \
+                  sis_login_id:jdoe.",
 )]
 #[clap()]
 // Without a structopt declaration, the argument is positional.
 pub struct CliInput {
     #[clap(short = 'a', long)]
     pub api_token: Option<String>,
+    /// Seconds a cached response is reused before revalidating it with Canvas.
+    #[clap(default_value = "900", long)]
+    pub cache_ttl: i64,
+    /// Always go to Canvas, neither reading nor writing the response cache.
+    #[clap(long)]
+    pub no_cache: bool,
+    /// Ask Canvas for ids as strings rather than numbers, for servers whose
+    /// ids are too large to survive a trip through JSON numbers.
+    #[clap(long)]
+    pub string_ids: bool,
+    /// Only list courses where the user has this type of enrollment.
+    #[clap(arg_enum, long)]
+    pub course_enrollment_type: Option<canvas::EnrollmentType>,
+    /// Only list courses where the user has an enrollment with this role name.
+    #[clap(long)]
+    pub course_enrollment_role: Option<String>,
+    /// Only list courses where the user's enrollment is in this state.
+    #[clap(arg_enum, long)]
+    pub course_enrollment_state: Option<canvas::CourseEnrollmentState>,
+    /// Only list courses in this state. May be repeated.
+    #[clap(arg_enum, long = "course-state")]
+    pub course_states: Vec<canvas::CourseState>,
+    /// Extra data to include with each course. May be repeated.
+    #[clap(arg_enum, long = "course-include")]
+    pub course_includes: Vec<canvas::CourseInclude>,
+    /// Only fetch enrollments of this type. May be repeated.
+    #[clap(arg_enum, long = "enrollment-type")]
+    pub enrollment_types: Vec<canvas::EnrollmentType>,
+    /// Only fetch enrollments with this role name. May be repeated.
+    #[clap(long = "enrollment-role")]
+    pub enrollment_roles: Vec<String>,
+    /// Only fetch enrollments in this state. May be repeated.
+    #[clap(arg_enum, long = "enrollment-state")]
+    pub enrollment_states: Vec<canvas::EnrollmentState>,
+    /// Only fetch enrollments belonging to this user, by id or SIS id.
+    #[clap(long)]
+    pub enrollment_user_id: Option<id::IdRef<id::UserId>>,
+    /// Return grades for this grading period rather than the current one.
+    #[clap(long)]
+    pub grading_period_id: Option<id::GradingPeriodId>,
+    /// Extra data to include with each enrollment. May be repeated.
+    #[clap(arg_enum, long = "enrollment-include")]
+    pub enrollment_includes: Vec<canvas::EnrollmentInclude>,
     #[clap(default_value = "default", short, long)]
     pub server: String,
+    /// Only use courses from this term, given by name, id, or "current".
+    #[clap(global = true, long)]
+    pub term: Option<term::TermSelector>,
+    /// Only include students in the section with this id or SIS id, in
+    /// commands about the students of a course: enrollments, report,
+    /// discussions, groups, quizzes (the class average), and graph network,
+    /// activity, distribution and discussions. May be repeated.
+    #[clap(global = true, long = "section")]
+    pub sections: Vec<id::IdRef<id::SectionId>>,
+    /// Run for this student in place of yourself, given by id, by SIS id or
+    /// by the name of a student you observe. "all" runs once for each student
+    /// you observe, with their names added to any output file names.
+    #[clap(global = true, long)]
+    pub student: Option<observer::StudentSelector>,
     #[clap(long, short = 'v', parse(from_occurrences))]
     pub verbosity: usize,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Print every enrollment in your courses. This is the default.
+    Enrollments,
+    /// Draw a chart.
+    #[clap(subcommand)]
+    Graph(GraphCommand),
+    /// Write course data out for use in other programs.
+    #[clap(subcommand)]
+    Export(ExportCommand),
+    /// List upcoming due dates, quizzes and events across your courses.
+    Calendar(CalendarArgs),
+    /// Write a standalone HTML report of scores, missing work and charts,
+    /// for you across your courses or for a single course.
+    Report(ReportArgs),
+    /// List missing, late and zero scored work across your active courses,
+    /// the work costing the most of a course grade first.
+    Missing(MissingArgs),
+    /// List quizzes across your courses with their time limits, allowed
+    /// attempts and your scores.
+    Quizzes(QuizzesArgs),
+    /// How much each student has posted and replied in a course's
+    /// discussions.
+    Discussions(DiscussionsArgs),
+    /// List the groups in your courses and who is in each.
+    Groups(GroupsArgs),
+    /// A digest of the announcements posted across your courses.
+    Announcements(AnnouncementsArgs),
+    /// Mirror the files of your courses into a directory, resuming where an
+    /// earlier download left off.
+    Download(DownloadArgs),
+    /// Keep a copy of your courses, your submissions and grades included, to
+    /// read after you've lost access to them.
+    Archive(ArchiveArgs),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ExportCommand {
+    /// An iCalendar file of due dates, quizzes and events across your
+    /// courses.
+    Ical(IcalArgs),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum GraphCommand {
+    /// Your current score in each of your courses.
+    Scores(ScoresArgs),
+    /// The people in your courses and how they are connected through them.
+    Network(NetworkArgs),
+    /// A course's modules and the prerequisites between them.
+    Modules(ModulesArgs),
+    /// A heatmap of when students are active in each course.
+    Activity(ActivityArgs),
+    /// How a course scored on its assignments, as histograms and box plots.
+    Distribution(DistributionArgs),
+    /// What's due over the coming weeks across your active courses.
+    Workload(WorkloadArgs),
+    /// Your score on each attempt at your quizzes, over time.
+    Quizzes(QuizAttemptsArgs),
+    /// Who replies to whom in a course's discussions.
+    Discussions(DiscussionGraphArgs),
+}
+
+/// Options shared by everything which draws a chart.
+#[derive(Args, Debug)]
+pub struct ChartArgs {
+    #[clap(arg_enum, default_value = "png", long)]
+    pub output: chart::ChartFormat,
+    /// Where to write the chart. Defaults to the chart's name in the current
+    /// directory, or stdout for terminal charts.
+    #[clap(long, parse(from_os_str))]
+    pub file: Option<PathBuf>,
+    /// Image width in pixels. Terminal charts fill the terminal instead.
+    #[clap(default_value = "1024", long)]
+    pub width: u32,
+    /// Image height in pixels.
+    #[clap(default_value = "768", long)]
+    pub height: u32,
+}
+
+#[derive(Args, Debug)]
+pub struct ScoresArgs {
+    #[clap(flatten)]
+    pub chart: ChartArgs,
+    /// Color the bars by this, with a legend.
+    #[clap(arg_enum, long)]
+    pub group_by: Option<chart::Grouping>,
+    /// Draw a separate plot for each value of this.
+    #[clap(arg_enum, long)]
+    pub facet_by: Option<chart::Grouping>,
+}
+
+#[derive(Args, Debug)]
+pub struct NetworkArgs {
+    #[clap(arg_enum, default_value = "dot", long)]
+    pub output: graph::GraphFormat,
+    /// Where to write the graph. Defaults to stdout.
+    #[clap(long, parse(from_os_str))]
+    pub file: Option<PathBuf>,
+    /// Box courses up by term, or split each course into its sections.
+    #[clap(arg_enum, long)]
+    pub cluster_by: Option<network::Cluster>,
+    /// Only include enrollments of this type. May be repeated.
+    #[clap(arg_enum, long = "role-type")]
+    pub role_types: Vec<canvas::EnrollmentType>,
+    /// Add each course's groups, connected to their members.
+    #[clap(long)]
+    pub groups: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ModulesArgs {
+    /// The id of the course whose modules to graph.
+    #[clap(long)]
+    pub course: id::IdRef<id::CourseId>,
+    #[clap(arg_enum, default_value = "dot", long)]
+    pub output: modules::ModulesFormat,
+    /// Where to write the graph. Defaults to stdout for graph formats and
+    /// terminal charts, and modules.png or modules.svg for images.
+    #[clap(long, parse(from_os_str))]
+    pub file: Option<PathBuf>,
+    #[clap(default_value = "1024", long)]
+    pub width: u32,
+    #[clap(default_value = "768", long)]
+    pub height: u32,
+}
+
+#[derive(Args, Debug)]
+pub struct ActivityArgs {
+    #[clap(flatten)]
+    pub chart: ChartArgs,
+    #[clap(arg_enum, default_value = "week", long)]
+    pub layout: activity::ActivityLayout,
+    /// Only chart the course with this id.
+    #[clap(long)]
+    pub course: Option<id::IdRef<id::CourseId>>,
+    /// Draw a separate chart for each section of a course.
+    #[clap(long)]
+    pub by_section: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct DistributionArgs {
+    #[clap(flatten)]
+    pub chart: ChartArgs,
+    /// The id of the course whose scores to chart.
+    #[clap(long)]
+    pub course: id::IdRef<id::CourseId>,
+    /// Only chart the assignment with this id.
+    #[clap(long)]
+    pub assignment: Option<id::AssignmentId>,
+    /// Only chart assignments in the assignment group with this id.
+    #[clap(long)]
+    pub assignment_group: Option<id::AssignmentGroupId>,
+    /// How many bins to split 0-100% into for the histogram.
+    #[clap(default_value = "10", long)]
+    pub bins: usize,
+    /// Include scores the teacher has not yet posted to students.
+    #[clap(long)]
+    pub include_unposted: bool,
+    /// Draw a separate chart for each section of the course, side by side.
+    #[clap(long)]
+    pub by_section: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct WorkloadArgs {
+    #[clap(flatten)]
+    pub chart: ChartArgs,
+    #[clap(arg_enum, default_value = "timeline", long)]
+    pub layout: workload::WorkloadLayout,
+    /// How many weeks to chart, starting with this one.
+    #[clap(default_value = "6", long)]
+    pub weeks: u32,
+}
+
+#[derive(Args, Debug)]
+pub struct QuizAttemptsArgs {
+    #[clap(flatten)]
+    pub chart: ChartArgs,
+    /// Only chart the course with this id. May be repeated.
+    #[clap(long = "course")]
+    pub courses: Vec<id::IdRef<id::CourseId>>,
+}
+
+#[derive(Args, Debug)]
+pub struct DiscussionGraphArgs {
+    /// The id of the course whose discussions to graph.
+    #[clap(long)]
+    pub course: id::IdRef<id::CourseId>,
+    /// Only include the discussion topic with this id. May be repeated.
+    #[clap(long = "topic")]
+    pub topics: Vec<id::TopicId>,
+    #[clap(arg_enum, default_value = "dot", long)]
+    pub output: graph::GraphFormat,
+    /// Where to write the graph. Defaults to stdout.
+    #[clap(long, parse(from_os_str))]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct IcalArgs {
+    /// Where to write the calendar. Defaults to stdout.
+    #[clap(long, parse(from_os_str))]
+    pub file: Option<PathBuf>,
+    /// Only include this kind of event. May be repeated.
+    #[clap(arg_enum, long = "kind")]
+    pub kinds: Vec<calendar::EventKind>,
+    /// Only include the course with this id. May be repeated.
+    #[clap(long = "course")]
+    pub courses: Vec<id::IdRef<id::CourseId>>,
+}
+
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    /// Report on the course with this id, rather than on you across your
+    /// courses.
+    #[clap(long)]
+    pub course: Option<id::IdRef<id::CourseId>>,
+    /// Where to write the report. Defaults to stdout.
+    #[clap(long, parse(from_os_str))]
+    pub file: Option<PathBuf>,
+    /// How many weeks of upcoming work to chart, starting with this one.
+    #[clap(default_value = "6", long)]
+    pub weeks: u32,
+}
+
+#[derive(Args, Debug)]
+pub struct MissingArgs {
+    #[clap(arg_enum, default_value = "text", long)]
+    pub output: missing::MissingFormat,
+    /// Where to write the list. Defaults to stdout.
+    #[clap(long, parse(from_os_str))]
+    pub file: Option<PathBuf>,
+    /// Only list work with this status. May be repeated.
+    #[clap(arg_enum, long = "status")]
+    pub statuses: Vec<missing::MissingStatus>,
+}
+
+#[derive(Args, Debug)]
+pub struct GroupsArgs {
+    /// Only list the groups in the course with this id. May be repeated.
+    #[clap(long = "course")]
+    pub courses: Vec<id::IdRef<id::CourseId>>,
+}
+
+#[derive(Args, Debug)]
+pub struct QuizzesArgs {
+    /// Only list the course with this id. May be repeated.
+    #[clap(long = "course")]
+    pub courses: Vec<id::IdRef<id::CourseId>>,
+}
+
+#[derive(Args, Debug)]
+pub struct DiscussionsArgs {
+    /// The id of the course whose discussions to count.
+    #[clap(long)]
+    pub course: id::IdRef<id::CourseId>,
+    /// Only count the discussion topic with this id. May be repeated.
+    #[clap(long = "topic")]
+    pub topics: Vec<id::TopicId>,
+}
+
+#[derive(Args, Debug)]
+pub struct AnnouncementsArgs {
+    #[clap(arg_enum, default_value = "text", long)]
+    pub output: announcements::AnnouncementsFormat,
+    /// Where to write the digest. Defaults to stdout.
+    #[clap(long, parse(from_os_str))]
+    pub file: Option<PathBuf>,
+    /// Only include the course with this id. May be repeated.
+    #[clap(long = "course")]
+    pub courses: Vec<id::IdRef<id::CourseId>>,
+    /// The first day to include announcements from, as YYYY-MM-DD. Defaults
+    /// to two weeks before the last.
+    #[clap(long)]
+    pub from: Option<chrono::NaiveDate>,
+    /// The last day to include announcements from, as YYYY-MM-DD. Defaults
+    /// to now.
+    #[clap(long)]
+    pub to: Option<chrono::NaiveDate>,
+    /// Start from the last time this was run with --since-last-run, and
+    /// remember this run for next time. The first run starts as --from does.
+    #[clap(long)]
+    pub since_last_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct DownloadArgs {
+    /// The directory to mirror into. Each course gets a directory of its own
+    /// inside it.
+    #[clap(default_value = "canvas-files", long, parse(from_os_str))]
+    pub dir: PathBuf,
+    /// Only download the files of the course with this id. May be repeated.
+    #[clap(long = "course")]
+    pub courses: Vec<id::IdRef<id::CourseId>>,
+}
+
+#[derive(Args, Debug)]
+pub struct ArchiveArgs {
+    /// The directory to archive into. Each course gets a directory of its
+    /// own inside it.
+    #[clap(default_value = "canvas-archive", long, parse(from_os_str))]
+    pub dir: PathBuf,
+    /// Only archive the course with this id. May be repeated.
+    #[clap(long = "course")]
+    pub courses: Vec<id::IdRef<id::CourseId>>,
+}
+
+#[derive(Args, Debug)]
+pub struct CalendarArgs {
+    /// The first day to list events for, as YYYY-MM-DD. Defaults to today.
+    #[clap(long)]
+    pub from: Option<chrono::NaiveDate>,
+    /// The last day to list events for, as YYYY-MM-DD. Defaults to two weeks
+    /// after the first.
+    #[clap(long)]
+    pub to: Option<chrono::NaiveDate>,
+    /// Only include this kind of event. May be repeated.
+    #[clap(arg_enum, long = "kind")]
+    pub kinds: Vec<calendar::EventKind>,
+    /// Only include the course with this id. May be repeated.
+    #[clap(long = "course")]
+    pub courses: Vec<id::IdRef<id::CourseId>>,
+    /// Whether to read events from the API, the courses' calendar feeds, or
+    /// both.
+    #[clap(arg_enum, default_value = "both", long)]
+    pub source: calendar::CalendarSource,
+}
+
+impl Command {
+    // The commands about the students of a course, which --section narrows.
+    fn takes_sections(&self) -> bool {
+        matches!(
+            self,
+            Command::Enrollments
+                | Command::Report(_)
+                | Command::Discussions(_)
+                | Command::Groups(_)
+                | Command::Quizzes(_)
+                | Command::Graph(
+                    GraphCommand::Network(_)
+                        | GraphCommand::Activity(_)
+                        | GraphCommand::Distribution(_)
+                        | GraphCommand::Discussions(_)
+                ),
+        )
+    }
 }
 
 pub struct CliValid {
+    pub cache: Option<http::CacheConfig>,
+    pub command: Command,
+    pub state_dir: PathBuf,
+    pub course_query: canvas::CourseQuery,
+    pub enrollment_query: canvas::EnrollmentQuery,
     pub verbosity: usize,
     pub server: config::ConfigServerParsed,
+    pub string_ids: bool,
+    pub term: Option<term::TermSelector>,
+    pub student_selector: Option<observer::StudentSelector>,
+    // The student a command is running for in place of the caller, once the
+    // selector has been looked up.
+    pub student: Option<id::UserId>,
+    // Whether the caller observes that student. Only observers (and the
+    // student themselves) can see the student's own course list.
+    pub observing: bool,
+    // Added to output file names when running for several students in turn.
+    pub output_label: Option<String>,
+}
+
+pub fn chart_output(
+    config: &CliValid,
+    args: &ChartArgs,
+    name: &str,
+) -> chart::ChartOutput {
+    match args.output {
+        chart::ChartFormat::Terminal => chart::ChartOutput {
+            format: args.output,
+            path: args.file
+                .as_deref()
+                .map(|p| observer::output_path(config, p)),
+            size: terminal::size(),
+        },
+        format => chart::ChartOutput {
+            format,
+            path: Some(observer::output_path(
+                config,
+                &args.file.clone().unwrap_or_else(|| {
+                    PathBuf::from(format!("{}.{}", name, format.extension()))
+                }),
+            )),
+            size: (args.width, args.height),
+        },
+    }
 }
 
 pub fn cli_validate(
@@ -35,9 +507,51 @@ pub fn cli_validate(
     } else {
         cli.server
     };
+    let cache = if cli.no_cache {
+        None
+    } else {
+        Some(http::CacheConfig {
+            dir: config.cache_dir.clone(),
+            ttl: chrono::Duration::seconds(cli.cache_ttl),
+        })
+    };
+    let command = cli.command.unwrap_or(Command::Enrollments);
+    if !cli.sections.is_empty() && !command.takes_sections() {
+        return Err(error::AppError::CliSectionSelectError(
+            "--section only applies to enrollments, report, discussions, \
+             groups, quizzes, and graph network, activity, distribution \
+             and discussions."
+                .to_string(),
+        ));
+    }
     match config.servers.get(&server_name) {
         Some(server) => Ok(CliValid {
+            cache,
+            command,
+            state_dir: config.state_dir.clone(),
+            course_query: canvas::CourseQuery {
+                enrollment_type: cli.course_enrollment_type,
+                enrollment_role: cli.course_enrollment_role,
+                enrollment_state: cli.course_enrollment_state,
+                states: cli.course_states,
+                include: cli.course_includes,
+            },
+            enrollment_query: canvas::EnrollmentQuery {
+                types: cli.enrollment_types,
+                roles: cli.enrollment_roles,
+                states: cli.enrollment_states,
+                user_id: cli.enrollment_user_id,
+                sections: cli.sections,
+                grading_period_id: cli.grading_period_id,
+                include: cli.enrollment_includes,
+            },
             server: server.clone(),
+            string_ids: cli.string_ids,
+            term: cli.term,
+            student_selector: cli.student,
+            student: None,
+            observing: false,
+            output_label: None,
             verbosity: cli.verbosity,
         }),
         None => Err(error::AppError::CliConfigServerMissingError(
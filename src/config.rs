@@ -26,6 +26,12 @@ pub struct ConfigServerFromFile {
 
 #[derive(Clone)]
 pub struct ConfigParsed {
+    // Where HTTP responses are cached between runs. Not configurable from the
+    // file yet.
+    pub cache_dir: std::path::PathBuf,
+    // Where commands remember things between runs, such as when they last
+    // ran. Unlike the cache, losing it changes what a command does.
+    pub state_dir: std::path::PathBuf,
     pub default_server: String,
     pub servers: HashMap<String, ConfigServerParsed>,
 }
@@ -59,17 +65,44 @@ fn config_from_file(app_name: &str) -> Result<ConfigFromFile, error::AppError> {
     ).map_err(error::AppError::ConfigDeserializationError)
 }
 
+// The cache directory itself is created lazily, when something is first
+// written to it.
+fn cache_dir(app_name: &str) -> Result<std::path::PathBuf, error::AppError> {
+    Ok(path(&[
+        &env::var("HOME").map_err(error::AppError::ConfigVarError)?,
+        ".cache",
+        app_name,
+        "http",
+    ]))
+}
+
+// Created lazily like the cache directory.
+fn state_dir(app_name: &str) -> Result<std::path::PathBuf, error::AppError> {
+    Ok(path(&[
+        &env::var("HOME").map_err(error::AppError::ConfigVarError)?,
+        ".local",
+        "state",
+        app_name,
+    ]))
+}
+
 pub fn config_load(app_name: &str) -> Result<ConfigParsed, error::AppError> {
     config_dir_ensure(app_name)?;
+    let cache_dir = cache_dir(app_name)?;
+    let state_dir = state_dir(app_name)?;
     config_from_file(app_name)
-        .and_then(config_validate)
+        .and_then(|c| config_validate(c, cache_dir, state_dir))
 }
 
 // defaultServer should exist among servers, or something is wrong.
 fn config_validate(
     config_from_file: ConfigFromFile,
+    cache_dir: std::path::PathBuf,
+    state_dir: std::path::PathBuf,
 ) -> Result<ConfigParsed, error::AppError> {
     Ok(ConfigParsed {
+        cache_dir,
+        state_dir,
         default_server: config_from_file.default_server,
         servers: config_from_file.servers.into_iter().map(|(k, v)| {
             Ok((k.clone(), ConfigServerParsed {
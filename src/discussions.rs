@@ -0,0 +1,427 @@
+use futures::future::join_all;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::canvas;
+use crate::cli;
+use crate::error;
+use crate::graph;
+use crate::id;
+use crate::output;
+use crate::section;
+
+struct Discussion {
+    topic: canvas::DiscussionTopic,
+    view: canvas::DiscussionView,
+}
+
+// How much one person has taken part in a course's discussions.
+struct Participation {
+    name: String,
+    // Entries answering a topic directly.
+    posts: u64,
+    // Entries answering someone else's entry.
+    replies: u64,
+    // Replies from other people to this person's topics and entries.
+    received: u64,
+    topics: BTreeSet<id::TopicId>,
+}
+
+pub async fn graph(
+    config: &cli::CliValid,
+    args: &cli::DiscussionGraphArgs,
+) -> Result<(), error::AppError> {
+    let discussions = course_discussions(
+        config,
+        &args.course,
+        &args.topics,
+    ).await?;
+    let students = section::students_selected(
+        config,
+        args.course.clone(),
+    ).await?;
+    output::write(
+        config,
+        args.file.as_deref(),
+        &graph::render(
+            &reply_graph(&discussions, students.as_ref()),
+            args.output,
+        ),
+    )
+}
+
+/// List how many posts and replies each student has made across a course's
+/// discussions, and how many replies they've drawn, busiest first.
+pub async fn list(
+    config: &cli::CliValid,
+    args: &cli::DiscussionsArgs,
+) -> Result<(), error::AppError> {
+    let discussions = course_discussions(
+        config,
+        &args.course,
+        &args.topics,
+    ).await?;
+    // The roster brings in the students who haven't posted at all. Without
+    // it, everyone who has posted is listed instead.
+    let query = canvas::EnrollmentQuery {
+        types: vec![canvas::EnrollmentType::Student],
+        sections: config.enrollment_query.sections.clone(),
+        ..Default::default()
+    };
+    let roster = canvas::enrollments_by_course(
+        config,
+        args.course.clone(),
+        &query,
+    ).await.map_err(|e| {
+        log::info!(
+            "Could not list the students in course {}, so listing everyone \
+             who has posted instead: {:?}",
+            args.course,
+            e,
+        )
+    }).ok();
+    let students = roster.as_deref().unwrap_or_default();
+    let mut people = participation(&discussions, students)
+        .into_iter()
+        .filter(|(id, _)| {
+            roster.is_none() || students.iter().any(|e| e.user_id == *id)
+        })
+        .map(|(_, p)| p)
+        .collect::<Vec<Participation>>();
+    people.sort_by(|a, b| {
+        (b.posts + b.replies)
+            .cmp(&(a.posts + a.replies))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    println!(
+        "{:<30}  {:>6}  {:>5}  {:>7}  {:>8}",
+        "Student",
+        "Topics",
+        "Posts",
+        "Replies",
+        "Received",
+    );
+    for p in people {
+        println!(
+            "{:<30}  {:>6}  {:>5}  {:>7}  {:>8}",
+            p.name,
+            p.topics.len(),
+            p.posts,
+            p.replies,
+            p.received,
+        );
+    }
+    Ok(())
+}
+
+// Every discussion in the course, or only the given topics. Canvas can be slow
+// to build a discussion's view, so one which can't be had is left out rather
+// than costing the rest.
+async fn course_discussions(
+    config: &cli::CliValid,
+    course_id: &id::IdRef<id::CourseId>,
+    topic_ids: &[id::TopicId],
+) -> Result<Vec<Discussion>, error::AppError> {
+    let topics = canvas::discussion_topics(config, course_id.clone())
+        .await?
+        .into_iter()
+        .filter(|t| topic_ids.is_empty() || topic_ids.contains(&t.id))
+        .collect::<Vec<canvas::DiscussionTopic>>();
+    let view_futures = topics
+        .iter()
+        .map(|t| canvas::discussion_view(config, course_id.clone(), t.id));
+    let views = join_all(view_futures).await;
+    Ok(
+        topics
+            .into_iter()
+            .zip(views)
+            .filter_map(|(topic, view)| match view {
+                Ok(view) => Some(Discussion { topic, view }),
+                Err(e) => {
+                    log::warn!(
+                        "Could not get the entries of discussion {}: {:?}",
+                        topic.id,
+                        e,
+                    );
+                    None
+                },
+            })
+            .collect()
+    )
+}
+
+/// The directed graph of who replied to whom across the given discussions,
+/// each edge weighted by how many times. Everyone's participation counts go
+/// on their node. Given students, only they and the replies between them are
+/// kept.
+fn reply_graph(
+    discussions: &[Discussion],
+    students: Option<&HashSet<id::UserId>>,
+) -> graph::Graph {
+    let kept = |id: &id::UserId| {
+        students.map(|s| s.contains(id)).unwrap_or(true)
+    };
+    let mut counts = BTreeMap::new();
+    for (from, to) in discussions.iter().flat_map(replies) {
+        if kept(&from) && kept(&to) {
+            *counts.entry((from, to)).or_insert(0u64) += 1;
+        }
+    }
+    let mut replies_graph = graph::Graph::new("discussions", true);
+    for (id, p) in participation(discussions, &[]) {
+        if !kept(&id) {
+            continue;
+        }
+        let mut attributes = graph::Attributes::new();
+        for (name, count) in [
+            ("topics", p.topics.len() as u64),
+            ("posts", p.posts),
+            ("replies", p.replies),
+            ("received", p.received),
+        ] {
+            attributes.insert(
+                name.to_string(),
+                graph::Attribute::Number(count as f64),
+            );
+        }
+        replies_graph.add_node(graph::Node {
+            id: user_node_id(id),
+            kind: graph::NodeKind::User,
+            label: p.name,
+            cluster: None,
+            attributes,
+        });
+    }
+    for ((from, to), count) in counts {
+        let mut attributes = graph::Attributes::new();
+        attributes.insert(
+            "weight".to_string(),
+            graph::Attribute::Number(count as f64),
+        );
+        replies_graph.edges.push(graph::Edge {
+            source: user_node_id(from),
+            target: user_node_id(to),
+            label: Some(count.to_string()),
+            attributes,
+        });
+    }
+    replies_graph
+}
+
+// Everyone who has taken part in the discussions, along with the given
+// students even if they haven't, keyed by user id.
+fn participation(
+    discussions: &[Discussion],
+    students: &[canvas::Enrollment],
+) -> BTreeMap<id::UserId, Participation> {
+    let mut names = students
+        .iter()
+        .map(|e| (e.user_id, e.user.name.clone()))
+        .collect::<HashMap<id::UserId, String>>();
+    for discussion in discussions {
+        let authors = discussion.topic.author.iter();
+        for user in discussion.view.participants.iter().chain(authors) {
+            names
+                .entry(user.id)
+                .or_insert_with(|| user.display_name.clone());
+        }
+    }
+    let mut people = BTreeMap::new();
+    for enrollment in students {
+        person(&mut people, &names, enrollment.user_id);
+    }
+    for discussion in discussions {
+        let posted = entries(&discussion.view)
+            .into_iter()
+            .filter(|(e, _)| e.deleted != Some(true));
+        for (entry, parent_id) in posted {
+            if let Some(user_id) = entry.user_id {
+                let p = person(&mut people, &names, user_id);
+                match parent_id {
+                    Some(_) => p.replies += 1,
+                    None => p.posts += 1,
+                }
+                p.topics.insert(discussion.topic.id);
+            }
+        }
+        for (_, to) in replies(discussion) {
+            person(&mut people, &names, to).received += 1;
+        }
+    }
+    people
+}
+
+// Who replied to whom in a discussion, once per reply. Entries answering the
+// topic itself count as replies to whoever posted it. Nobody is counted as
+// replying to themselves, and deleted entries don't count.
+fn replies(discussion: &Discussion) -> Vec<(id::UserId, id::UserId)> {
+    let entries = entries(&discussion.view);
+    let authors = entries
+        .iter()
+        .filter_map(|(e, _)| Some((e.id, e.user_id?)))
+        .collect::<HashMap<id::EntryId, id::UserId>>();
+    let topic_author = discussion.topic.author.as_ref().map(|a| a.id);
+    entries
+        .iter()
+        .filter(|(e, _)| e.deleted != Some(true))
+        .filter_map(|(entry, parent_id)| {
+            let from = entry.user_id?;
+            let to = match parent_id {
+                Some(parent_id) => authors.get(parent_id).copied(),
+                None => topic_author,
+            }?;
+            if from == to {
+                None
+            } else {
+                Some((from, to))
+            }
+        })
+        .collect()
+}
+
+// Every entry in a discussion's view, along with the id of the entry it
+// replies to. The nesting says as much even where an entry leaves its
+// parent_id out.
+fn entries(
+    view: &canvas::DiscussionView,
+) -> Vec<(&canvas::DiscussionEntry, Option<id::EntryId>)> {
+    fn walk<'a>(
+        entry: &'a canvas::DiscussionEntry,
+        parent_id: Option<id::EntryId>,
+        found: &mut Vec<(&'a canvas::DiscussionEntry, Option<id::EntryId>)>,
+    ) {
+        found.push((entry, parent_id.or(entry.parent_id)));
+        for reply in &entry.replies {
+            walk(reply, Some(entry.id), found);
+        }
+    }
+    let mut found = vec![];
+    for entry in &view.view {
+        walk(entry, None, &mut found);
+    }
+    for entry in &view.new_entries {
+        if !found.iter().any(|(e, _)| e.id == entry.id) {
+            walk(entry, None, &mut found);
+        }
+    }
+    found
+}
+
+fn person<'a>(
+    people: &'a mut BTreeMap<id::UserId, Participation>,
+    names: &HashMap<id::UserId, String>,
+    id: id::UserId,
+) -> &'a mut Participation {
+    people.entry(id).or_insert_with(|| Participation {
+        name: names
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| format!("User {}", id)),
+        posts: 0,
+        replies: 0,
+        received: 0,
+        topics: BTreeSet::new(),
+    })
+}
+
+fn user_node_id(user_id: id::UserId) -> String {
+    format!("user_{}", user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A topic posted by user 1, with entries nested as Canvas sends them and
+    // one newer entry only in new_entries.
+    fn discussion() -> Discussion {
+        let topic = serde_json::json!({
+            "id": 1,
+            "title": "Introductions",
+            "html_url": "https://c.test/courses/1/discussion_topics/1",
+            "author": { "id": 1, "display_name": "Teacher" },
+        });
+        let view = serde_json::json!({
+            "view": [
+                {
+                    "id": 10,
+                    "user_id": 2,
+                    "replies": [
+                        { "id": 11, "user_id": 3 },
+                        {
+                            "id": 12,
+                            "user_id": 2,
+                            "replies": [{ "id": 13, "user_id": 1 }],
+                        },
+                    ],
+                },
+                { "id": 20, "user_id": 1 },
+                { "id": 30, "deleted": true, "replies": [
+                    { "id": 31, "user_id": 3 },
+                ] },
+            ],
+            "new_entries": [
+                { "id": 11, "user_id": 3, "parent_id": 10 },
+                { "id": 40, "user_id": 4, "parent_id": 20 },
+            ],
+        });
+        Discussion {
+            topic: serde_json::from_value(topic).unwrap(),
+            view: serde_json::from_value(view).unwrap(),
+        }
+    }
+
+    #[test]
+    fn entries_takes_parents_from_nesting_and_new_entries() {
+        let discussion = discussion();
+        let parents = entries(&discussion.view)
+            .into_iter()
+            .map(|(e, parent_id)| (e.id.0, parent_id.map(|p| p.0)))
+            .collect::<Vec<(u64, Option<u64>)>>();
+        assert_eq!(
+            parents,
+            vec![
+                (10, None),
+                (11, Some(10)),
+                (12, Some(10)),
+                (13, Some(12)),
+                (20, None),
+                (30, None),
+                (31, Some(30)),
+                (40, Some(20)),
+            ],
+        );
+    }
+
+    #[test]
+    fn replies_between_people() {
+        let replies = replies(&discussion())
+            .into_iter()
+            .map(|(from, to)| (from.0, to.0))
+            .collect::<Vec<(u64, u64)>>();
+        // 10 answers the topic, 11 and 13 answer 10 and 12, 40 answers 20.
+        // 12 is 2 answering themselves, 20 is the topic's author answering
+        // their own topic, and 31 answers the deleted 30, which has no
+        // author.
+        assert_eq!(replies, vec![(2, 1), (3, 2), (1, 2), (4, 1)]);
+    }
+
+    #[test]
+    fn reply_graph_narrowed_to_students() {
+        let students = [id::UserId(2), id::UserId(3)]
+            .iter()
+            .copied()
+            .collect::<HashSet<id::UserId>>();
+        let graph = reply_graph(&[discussion()], Some(&students));
+        let nodes = graph.nodes
+            .iter()
+            .map(|n| n.id.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(nodes, vec!["user_2", "user_3"]);
+        let edges = graph.edges
+            .iter()
+            .map(|e| (e.source.as_str(), e.target.as_str()))
+            .collect::<Vec<(&str, &str)>>();
+        assert_eq!(edges, vec![("user_3", "user_2")]);
+        assert_eq!(reply_graph(&[discussion()], None).nodes.len(), 4);
+    }
+}
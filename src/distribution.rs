@@ -0,0 +1,544 @@
+use futures::future::try_join_all;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::canvas;
+use crate::chart;
+use crate::cli;
+use crate::error;
+use crate::id;
+use crate::section;
+
+// The five numbers a box plot is drawn from, as percentages of the points
+// possible so assignments worth different amounts share an axis.
+struct Summary {
+    min: f64,
+    lower_q: f64,
+    median: f64,
+    upper_q: f64,
+    max: f64,
+}
+
+struct AssignmentBox {
+    name: String,
+    summary: Summary,
+    highlight: Option<f64>,
+}
+
+/// Whose score stands out from the rest of the class.
+#[derive(Clone, Copy)]
+pub enum Highlight {
+    Nobody,
+    // The caller's own, taken from the submission which comes with each
+    // assignment.
+    Own,
+    Student(id::UserId),
+}
+
+/// How a course did on some of its assignments: a histogram of each student's
+/// overall percentage across them, and a box plot per assignment.
+pub struct DistributionChart {
+    title: String,
+    boxes: Vec<AssignmentBox>,
+    // Each student's percentage across every assignment they were graded on.
+    totals: Vec<f64>,
+    highlight_total: Option<f64>,
+    highlight_label: String,
+    bins: usize,
+}
+
+/// A distribution chart for each section of a course, side by side, so
+/// cohorts can be compared.
+pub struct SectionsChart {
+    title: String,
+    sections: Vec<DistributionChart>,
+}
+
+pub async fn graph(
+    config: &cli::CliValid,
+    args: &cli::DistributionArgs,
+) -> Result<(), error::AppError> {
+    let course = canvas::course(config, args.course.clone()).await?;
+    // A student gets their own submission alongside each assignment, but only
+    // a teacher may see everyone else's. Anyone else, such as an observer or
+    // a designer, gets what a student would.
+    let student = !course.teaching();
+    // Without points possible there's nothing to take a percentage of.
+    let assignments = canvas::assignments(config, course.id)
+        .await?
+        .into_iter()
+        .filter(|a| args.assignment.map(|id| id == a.id).unwrap_or(true))
+        .filter(|a| {
+            args.assignment_group
+                .map(|id| a.assignment_group_id == Some(id))
+                .unwrap_or(true)
+        })
+        .filter(|a| a.points_possible.map(|p| p > 0.0).unwrap_or(false))
+        .collect::<Vec<canvas::Assignment>>();
+    if assignments.is_empty() {
+        log::warn!(
+            "No assignments worth any points matched in course {}.",
+            course.id,
+        );
+    }
+    let title = match (args.assignment, args.assignment_group) {
+        (Some(_), _) if assignments.len() == 1 => format!(
+            "{}: {}",
+            course.name,
+            assignments[0].name,
+        ),
+        (_, Some(group_id)) => {
+            let group_name = canvas::assignment_groups(config, course.id)
+                .await?
+                .into_iter()
+                .find(|g| g.id == group_id)
+                .map(|g| g.name)
+                .unwrap_or_else(|| format!("Group {}", group_id));
+            format!("{}: {}", course.name, group_name)
+        },
+        _ => course.name.clone(),
+    };
+    let submissions = if student {
+        HashMap::new()
+    } else {
+        let submission_futures = assignments
+            .iter()
+            .map(|a| canvas::submissions(config, course.id, a.id));
+        assignments
+            .iter()
+            .map(|a| a.id)
+            .zip(try_join_all(submission_futures).await?)
+            .collect::<HashMap<id::AssignmentId, Vec<canvas::Submission>>>()
+    };
+    let highlight = if student {
+        Highlight::Own
+    } else {
+        config.student.map(Highlight::Student).unwrap_or(Highlight::Nobody)
+    };
+    let output = cli::chart_output(config, &args.chart, "distribution");
+    let sectioned = args.by_section
+        || !config.enrollment_query.sections.is_empty();
+    if student || !sectioned {
+        if sectioned {
+            log::warn!(
+                "Only teachers can see scores by section, so charting the \
+                 whole course.",
+            );
+        }
+        return chart::render(
+            &output,
+            &distribution_chart(
+                title,
+                &assignments,
+                &submissions,
+                highlight,
+                args.include_unposted,
+                args.bins,
+            ),
+        );
+    }
+    let sections = section::sections_selected(config, course.id).await?;
+    if !args.by_section {
+        let students = section::student_ids(&sections);
+        return chart::render(
+            &output,
+            &distribution_chart(
+                title,
+                &assignments,
+                &submissions_of(&submissions, &students),
+                highlight,
+                args.include_unposted,
+                args.bins,
+            ),
+        );
+    }
+    let charts = sections
+        .iter()
+        .map(|s| {
+            let students = section::student_ids(std::slice::from_ref(s));
+            distribution_chart(
+                section::section_label(s),
+                &assignments,
+                &submissions_of(&submissions, &students),
+                highlight,
+                args.include_unposted,
+                args.bins,
+            )
+        })
+        .collect();
+    chart::render(&output, &SectionsChart { title, sections: charts })
+}
+
+// Only the submissions belonging to some of the students.
+fn submissions_of(
+    submissions: &HashMap<id::AssignmentId, Vec<canvas::Submission>>,
+    students: &HashSet<id::UserId>,
+) -> HashMap<id::AssignmentId, Vec<canvas::Submission>> {
+    submissions
+        .iter()
+        .map(|(assignment_id, submissions)| {
+            let kept = submissions
+                .iter()
+                .filter(|s| students.contains(&s.user_id))
+                .cloned()
+                .collect();
+            (*assignment_id, kept)
+        })
+        .collect()
+}
+
+pub fn distribution_chart(
+    title: String,
+    assignments: &[canvas::Assignment],
+    submissions: &HashMap<id::AssignmentId, Vec<canvas::Submission>>,
+    highlight: Highlight,
+    include_unposted: bool,
+    bins: usize,
+) -> DistributionChart {
+    let mut boxes = vec![];
+    // User id to the points they scored and the points they could have.
+    let mut totals: HashMap<id::UserId, (f64, f64)> = HashMap::new();
+    let mut own_total = (0.0, 0.0);
+    let mut hidden = 0;
+    for assignment in assignments {
+        // Without points possible there's nothing to take a percentage of.
+        let points = match assignment.points_possible.filter(|p| *p > 0.0) {
+            Some(points) => points,
+            None => continue,
+        };
+        let own = match highlight {
+            Highlight::Own => assignment.submission
+                .as_ref()
+                .filter(|s| score_visible(assignment, s, include_unposted))
+                .and_then(graded_score),
+            _ => None,
+        };
+        if let Some(score) = own {
+            own_total.0 += score;
+            own_total.1 += points;
+        }
+        let mut scores = vec![];
+        let mut highlighted = None;
+        for submission in submissions
+            .get(&assignment.id)
+            .into_iter()
+            .flatten() {
+            let score = match graded_score(submission) {
+                Some(score) => score,
+                None => continue,
+            };
+            if !score_visible(assignment, submission, include_unposted) {
+                hidden += 1;
+                continue;
+            }
+            let total = totals.entry(submission.user_id).or_insert((0.0, 0.0));
+            total.0 += score;
+            total.1 += points;
+            scores.push(percent(score, points));
+            if let Highlight::Student(id) = highlight {
+                if id == submission.user_id {
+                    highlighted = Some(percent(score, points));
+                }
+            }
+        }
+        // Students can't see the class's submissions, only whatever summary
+        // of them the teacher chose to share.
+        let summary = summary(&scores).or_else(|| {
+            assignment.score_statistics
+                .as_ref()
+                .map(|s| statistics_summary(s, points))
+        });
+        if let Some(summary) = summary {
+            boxes.push(AssignmentBox {
+                name: assignment.name.clone(),
+                summary,
+                highlight: own.map(|s| percent(s, points)).or(highlighted),
+            });
+        }
+    }
+    if hidden > 0 {
+        log::warn!(
+            "Left out {} scores which have not been posted to students. Pass \
+             --include-unposted to show them anyway.",
+            hidden,
+        );
+    }
+    let highlight_total = match highlight {
+        Highlight::Nobody => None,
+        Highlight::Own => Some(own_total),
+        Highlight::Student(id) => totals.get(&id).cloned(),
+    }.filter(|(_, points)| *points > 0.0)
+        .map(|(score, points)| percent(score, points));
+    DistributionChart {
+        title,
+        boxes,
+        totals: totals
+            .values()
+            .map(|(score, points)| percent(*score, *points))
+            .collect(),
+        highlight_total,
+        highlight_label: match highlight {
+            Highlight::Student(id) => format!("Student {}", id),
+            _ => "Your score".to_string(),
+        },
+        bins: bins.max(1),
+    }
+}
+
+// Canvas holds back grades on assignments posted by hand until the teacher
+// posts them. Older instances only say whether the whole assignment is muted.
+fn score_visible(
+    assignment: &canvas::Assignment,
+    submission: &canvas::Submission,
+    include_unposted: bool,
+) -> bool {
+    include_unposted
+        || submission.posted_at.is_some()
+        || !(assignment.muted.unwrap_or(false)
+            || assignment.post_manually.unwrap_or(false))
+}
+
+// Excused submissions keep whatever score they had, but don't count for it.
+fn graded_score(submission: &canvas::Submission) -> Option<f64> {
+    if submission.excused.unwrap_or(false) {
+        None
+    } else {
+        submission.score
+    }
+}
+
+fn percent(score: f64, points: f64) -> f64 {
+    100.0 * score / points
+}
+
+fn summary(scores: &[f64]) -> Option<Summary> {
+    if scores.is_empty() {
+        return None;
+    }
+    let mut sorted = scores.to_vec();
+    sorted.sort_by(|a, b| {
+        a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    // Interpolate between the two closest scores.
+    let quantile = |q: f64| {
+        let rank = q * (sorted.len() - 1) as f64;
+        let lower = sorted[rank.floor() as usize];
+        let upper = sorted[rank.ceil() as usize];
+        lower + (upper - lower) * rank.fract()
+    };
+    Some(Summary {
+        min: sorted[0],
+        lower_q: quantile(0.25),
+        median: quantile(0.5),
+        upper_q: quantile(0.75),
+        max: sorted[sorted.len() - 1],
+    })
+}
+
+// Canvas leaves the quartiles off when there are too few scores to keep them
+// anonymous, so fall back to what it does give.
+fn statistics_summary(
+    statistics: &canvas::ScoreStatistic,
+    points: f64,
+) -> Summary {
+    Summary {
+        min: percent(statistics.min, points),
+        lower_q: percent(statistics.lower_q.unwrap_or(statistics.min), points),
+        median: percent(statistics.median.unwrap_or(statistics.mean), points),
+        upper_q: percent(statistics.upper_q.unwrap_or(statistics.max), points),
+        max: percent(statistics.max, points),
+    }
+}
+
+// The palette starts on red, which is kept for whoever is highlighted.
+const FILL: usize = 3;
+const HIGHLIGHT: RGBColor = RED;
+
+impl chart::Chart for DistributionChart {
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        root.fill(&WHITE)?;
+        let root = root.titled(&self.title, ("sans-serif", 24))?;
+        // Students only ever have the box plots to go on.
+        if self.totals.is_empty() {
+            return self.draw_boxes(&root);
+        }
+        let (upper, lower) = root.split_vertically(
+            root.relative_to_height(0.45),
+        );
+        self.draw_histogram(&upper)?;
+        self.draw_boxes(&lower)
+    }
+}
+
+impl chart::Chart for SectionsChart {
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        root.fill(&WHITE)?;
+        let root = root.titled(&self.title, ("sans-serif", 28))?;
+        let panels = root.split_evenly((1, self.sections.len().max(1)));
+        for (panel, section) in panels.iter().zip(self.sections.iter()) {
+            section.draw(panel)?;
+        }
+        Ok(())
+    }
+}
+
+impl DistributionChart {
+    fn draw_histogram<DB: DrawingBackend>(
+        &self,
+        area: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        // Extra credit can push a percentage past 100.
+        let x_max = self.totals.iter().cloned().fold(100.0, f64::max);
+        let width = 100.0 / self.bins as f64;
+        let bin_count = (x_max / width).ceil() as usize;
+        let bin_of = |v: f64| ((v / width) as usize).min(bin_count - 1);
+        let mut counts = vec![0u32; bin_count];
+        for total in &self.totals {
+            counts[bin_of(*total)] += 1;
+        }
+        let y_max = counts.iter().cloned().max().unwrap_or(0) + 1;
+        let mut chart = ChartBuilder::on(area)
+            .margin(10)
+            .margin_right(30)
+            .x_label_area_size(40)
+            .y_label_area_size(50)
+            .build_cartesian_2d(0.0..bin_count as f64 * width, 0u32..y_max)?;
+        chart.configure_mesh()
+            .disable_x_mesh()
+            .x_desc("Overall score (%)")
+            .y_desc("Students")
+            .draw()?;
+        let highlighted = self.highlight_total.map(bin_of);
+        chart.draw_series(
+            counts
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| Some(*i) != highlighted)
+                .map(|(i, count)| {
+                    bin(i, *count, width, chart::series_color(FILL))
+                }),
+        )?;
+        if let Some(i) = highlighted {
+            chart.draw_series(std::iter::once(
+                bin(i, counts[i], width, HIGHLIGHT),
+            ))?
+                .label(self.highlight_label.clone())
+                .legend(|(x, y)| {
+                    Rectangle::new(
+                        [(x, y - 5), (x + 10, y + 5)],
+                        HIGHLIGHT.filled(),
+                    )
+                });
+            chart.configure_series_labels()
+                .position(SeriesLabelPosition::UpperLeft)
+                .background_style(WHITE.mix(0.8))
+                .border_style(BLACK)
+                .draw()?;
+        }
+        Ok(())
+    }
+
+    fn draw_boxes<DB: DrawingBackend>(
+        &self,
+        area: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        let y_max = self.boxes
+            .iter()
+            .map(|b| b.summary.max)
+            .fold(100.0, f64::max);
+        // Box i spans 2i to 2i + 2 so its center lands on a whole number to
+        // label. Plotters can't map a segmented range with only one segment,
+        // which rules those out.
+        let count = self.boxes.len().max(1) as u32;
+        let centers = (0..count).map(|i| 2 * i + 1).collect::<Vec<u32>>();
+        let mut chart = ChartBuilder::on(area)
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(50)
+            .build_cartesian_2d(
+                (0..2 * count).with_key_points(centers),
+                0.0..y_max,
+            )?;
+        let label = |x: &u32| self.boxes
+            .get(*x as usize / 2)
+            .map(|b| b.name.clone())
+            .unwrap_or_default();
+        chart.configure_mesh()
+            .disable_x_mesh()
+            .x_label_formatter(&label)
+            .y_desc("Score (%)")
+            .draw()?;
+        // Leave a gap either side of each box, and a wider one for the
+        // whisker caps.
+        let segment = area.dim_in_pixel().0 / count;
+        let box_margin = segment / 5;
+        let cap_margin = segment * 2 / 5;
+        for (i, b) in self.boxes.iter().enumerate() {
+            let s = &b.summary;
+            let center = 2 * i as u32 + 1;
+            chart.draw_series(vec![
+                PathElement::new(
+                    vec![(center, s.min), (center, s.lower_q)],
+                    BLACK,
+                ),
+                PathElement::new(
+                    vec![(center, s.upper_q), (center, s.max)],
+                    BLACK,
+                ),
+            ])?;
+            let fill = chart::series_color(FILL).filled();
+            chart.draw_series(vec![
+                inset(i, s.lower_q, s.upper_q, box_margin, fill),
+                inset(i, s.lower_q, s.upper_q, box_margin, BLACK.into()),
+                inset(i, s.median, s.median, box_margin, BLACK.stroke_width(2)),
+                inset(i, s.min, s.min, cap_margin, BLACK.into()),
+                inset(i, s.max, s.max, cap_margin, BLACK.into()),
+            ])?;
+            if let Some(score) = b.highlight {
+                chart.draw_series(std::iter::once(
+                    Circle::new((center, score), 5, HIGHLIGHT.filled()),
+                ))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn bin<C: Color>(
+    i: usize,
+    count: u32,
+    width: f64,
+    color: C,
+) -> Rectangle<(f64, u32)> {
+    let mut rectangle = Rectangle::new(
+        [(i as f64 * width, 0), ((i + 1) as f64 * width, count)],
+        color.filled(),
+    );
+    rectangle.set_margin(0, 0, 1, 1);
+    rectangle
+}
+
+// A rectangle across the ith box's slot from one score to another, pulled in
+// from either side. A zero height makes a horizontal line.
+fn inset(
+    i: usize,
+    from: f64,
+    to: f64,
+    margin: u32,
+    style: ShapeStyle,
+) -> Rectangle<(u32, f64)> {
+    let i = i as u32;
+    let mut rectangle = Rectangle::new(
+        [(2 * i, from), (2 * i + 2, to)],
+        style,
+    );
+    rectangle.set_margin(0, 0, margin, margin);
+    rectangle
+}
@@ -0,0 +1,399 @@
+use futures::stream::{self, StreamExt};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::canvas;
+use crate::cli;
+use crate::error;
+use crate::http;
+use crate::id;
+use crate::observer;
+use crate::sis;
+use crate::term;
+
+// Partial downloads sit next to where they'll end up, under this extension,
+// until they're complete.
+const PART_EXTENSION: &str = "part";
+
+// Next to each partial download, the version of the file it was started from,
+// so a file changed since is downloaded afresh rather than spliced onto.
+const VERSION_EXTENSION: &str = "version";
+
+pub enum Outcome {
+    Downloaded,
+    // Picked up from where an earlier run left off.
+    Resumed,
+    Unchanged,
+}
+
+/// Mirror the files of your courses into a directory, one directory per
+/// course laid out as its folders are in Canvas. Files already there with the
+/// same size and update time are skipped, and partial downloads left by an
+/// earlier run are resumed.
+pub async fn download(
+    config: &cli::CliValid,
+    args: &cli::DownloadArgs,
+) -> Result<(), error::AppError> {
+    let (courses, _terms) = term::courses_selected(
+        config,
+        &config.course_query,
+    ).await?;
+    let courses = sis::courses_selected(
+        config,
+        courses,
+        &args.courses,
+    ).await?;
+    let dir = observer::output_path(config, &args.dir);
+    let mut files = vec![];
+    for course in &courses {
+        // Teachers can hide the files of a course from students, which
+        // shouldn't stop the others from downloading.
+        let course_dir = dir.join(course_dir_name(course));
+        match course_files(config, course, &course_dir).await {
+            Ok(course_files) => files.extend(course_files),
+            Err(e) => log::warn!(
+                "Could not list the files in {}: {:?}",
+                course.name,
+                e,
+            ),
+        }
+    }
+    let outcomes = download_files(config, &files).await;
+    let count = |wanted: fn(&Outcome) -> bool| {
+        outcomes
+            .iter()
+            .filter(|o| o.as_ref().map(wanted).unwrap_or(false))
+            .count()
+    };
+    println!(
+        "Downloaded {} files ({} resumed), {} unchanged, {} failed.",
+        count(|o| !matches!(o, Outcome::Unchanged)),
+        count(|o| matches!(o, Outcome::Resumed)),
+        count(|o| matches!(o, Outcome::Unchanged)),
+        outcomes.iter().filter(|o| o.is_err()).count(),
+    );
+    Ok(())
+}
+
+/// A course's files, each with the path it's mirrored to under the given
+/// directory. Files locked for the caller come without a download URL, so
+/// they're left out.
+pub async fn course_files(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+    course_dir: &Path,
+) -> Result<Vec<(canvas::File, PathBuf)>, error::AppError> {
+    let (folders, files) = futures::try_join!(
+        canvas::course_folders(config, course.id),
+        canvas::course_files(config, course.id),
+    )?;
+    // Folders' full names start with the course's root folder, which is the
+    // course directory itself here.
+    let folder_dirs = folders
+        .iter()
+        .map(|f| {
+            let dir = f.full_name
+                .split('/')
+                .skip(1)
+                .fold(course_dir.to_path_buf(), |dir, name| {
+                    dir.join(path_component(name))
+                });
+            (f.id, dir)
+        })
+        .collect::<HashMap<id::FolderId, PathBuf>>();
+    Ok(
+        files
+            .into_iter()
+            .filter(|f| {
+                if f.url.is_empty() {
+                    log::info!(
+                        "Skipping {} in {}, which is locked.",
+                        f.display_name,
+                        course.name,
+                    );
+                }
+                !f.url.is_empty()
+            })
+            .map(|f| {
+                let path = folder_dirs
+                    .get(&f.folder_id)
+                    .map(|dir| dir.as_path())
+                    .unwrap_or(course_dir)
+                    .join(path_component(&f.display_name));
+                (f, path)
+            })
+            .collect()
+    )
+}
+
+/// Download each file to its path, a few at a time. Failures are logged and
+/// left out of the way of the rest.
+pub async fn download_files(
+    config: &cli::CliValid,
+    files: &[(canvas::File, PathBuf)],
+) -> Vec<Result<Outcome, ()>> {
+    stream::iter(files.iter())
+        .map(|(file, path)| async move {
+            download_file(config, file, path)
+                .await
+                .map_err(|e| {
+                    log::warn!("Could not download {:?}: {:?}", path, e);
+                })
+        })
+        // Each request waits its turn in the http module, but the transfers
+        // themselves carry on after, so they're held to the same number.
+        .buffer_unordered(http::REQUESTS_CONCURRENT)
+        .collect()
+        .await
+}
+
+/// Download a file to the given path unless it's already there, resuming a
+/// partial download of it if there is one.
+pub async fn download_file(
+    config: &cli::CliValid,
+    file: &canvas::File,
+    path: &Path,
+) -> Result<Outcome, error::AppError> {
+    if unchanged(file, path) {
+        log::debug!("Skipping {:?}, which is unchanged.", path);
+        return Ok(Outcome::Unchanged);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(error::AppError::DownloadIoError)?;
+    }
+    let part = with_added_extension(path, PART_EXTENSION);
+    let version_path = with_added_extension(&part, VERSION_EXTENSION);
+    let version = file_version(file);
+    // Only a partial file of this very version can be resumed, and one as big
+    // as the whole file is of no use to resume from.
+    let validator = part_validator(&version_path, &version);
+    let offset = match validator {
+        Some(_) => fs::metadata(&part).map(|m| m.len()).unwrap_or(0),
+        None => 0,
+    };
+    let offset = if offset < file.size { offset } else { 0 };
+    let mut headers = reqwest::header::HeaderMap::new();
+    if offset > 0 {
+        if let Ok(range) = reqwest::header::HeaderValue::from_str(
+            &format!("bytes={}-", offset),
+        ) {
+            headers.insert(reqwest::header::RANGE, range);
+        }
+        // Lets the server send the whole file instead if what it has isn't
+        // what the partial file was started from.
+        if let Some(if_range) = validator
+            .filter(|v| !v.is_empty())
+            .and_then(|v| reqwest::header::HeaderValue::from_str(&v).ok()) {
+            headers.insert(reqwest::header::IF_RANGE, if_range);
+        }
+    }
+    let mut response = http::request_raw(
+        config,
+        reqwest::Method::GET,
+        file.url.clone(),
+        headers,
+    )
+        .await
+        .map_err(error::AppError::CanvasRequestError)?;
+    if !response.status().is_success() {
+        return Err(error::AppError::CanvasStatusError(response.status()));
+    }
+    // Servers are free to ignore the range and send the whole file again.
+    let resumed = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    log::info!(
+        "{} {:?}.",
+        if resumed { "Resuming" } else { "Downloading" },
+        path,
+    );
+    if !resumed {
+        fs::write(
+            &version_path,
+            format!("{}\n{}\n", version, response_validator(&response)),
+        ).map_err(error::AppError::DownloadIoError)?;
+    }
+    let mut out = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&part)
+        .map_err(error::AppError::DownloadIoError)?;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(error::AppError::CanvasRequestError)? {
+        out.write_all(&chunk).map_err(error::AppError::DownloadIoError)?;
+    }
+    drop(out);
+    let size = fs::metadata(&part)
+        .map_err(error::AppError::DownloadIoError)?
+        .len();
+    if size != file.size {
+        return Err(error::AppError::DownloadSizeError(format!(
+            "Downloaded {} bytes of {:?} where Canvas lists {}.",
+            size,
+            path,
+            file.size,
+        )));
+    }
+    fs::rename(&part, path).map_err(error::AppError::DownloadIoError)?;
+    if let Err(e) = fs::remove_file(&version_path) {
+        log::debug!("Could not remove {:?}: {}", version_path, e);
+    }
+    // The update time is how the next run tells the file is unchanged.
+    if let Some(updated_at) = file.updated_at {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .and_then(|f| f.set_modified(SystemTime::from(updated_at)))
+            .map_err(error::AppError::DownloadIoError)?;
+    }
+    Ok(if resumed { Outcome::Resumed } else { Outcome::Downloaded })
+}
+
+// The path with another extension added after any it already has.
+fn with_added_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .map(|n| n.to_os_string())
+        .unwrap_or_default();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+// What tells one version of a file in Canvas from another.
+fn file_version(file: &canvas::File) -> String {
+    let time = |t: Option<chrono::DateTime<chrono::Local>>| {
+        t.map(|t| t.to_rfc3339()).unwrap_or_default()
+    };
+    format!(
+        "{} {} {}",
+        file.size,
+        time(file.updated_at),
+        time(file.modified_at),
+    )
+}
+
+// The validator the server sent when a partial download of this version was
+// started, which is empty if it sent none. Nothing at all means there's no
+// partial download of this version to resume.
+fn part_validator(version_path: &Path, version: &str) -> Option<String> {
+    let recorded = fs::read_to_string(version_path).ok()?;
+    let mut lines = recorded.lines();
+    if lines.next()? != version {
+        return None;
+    }
+    Some(lines.next().unwrap_or_default().to_string())
+}
+
+// A strong ETag if there is one, or else the modification time, for If-Range
+// when resuming.
+fn response_validator(response: &reqwest::Response) -> String {
+    let header = |name| {
+        response.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    header(reqwest::header::ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(reqwest::header::LAST_MODIFIED))
+        .unwrap_or_default()
+}
+
+// Whether the file on disk is already the one in Canvas, going by its size
+// and, to the second, the time Canvas last updated it.
+fn unchanged(file: &canvas::File, path: &Path) -> bool {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return false,
+    };
+    let seconds = |t: SystemTime| {
+        t.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).ok()
+    };
+    let modified = metadata.modified().ok().and_then(seconds);
+    metadata.len() == file.size
+        && file.updated_at
+            .map(|t| seconds(SystemTime::from(t)) == modified)
+            .unwrap_or(true)
+}
+
+/// The directory a course is mirrored into. Courses can share a name, such
+/// as the same course in two terms, so the id goes on the end.
+pub fn course_dir_name(course: &canvas::Course) -> String {
+    path_component(&format!("{} ({})", course.name, course.id))
+}
+
+/// A name from Canvas made safe to use as a single file or directory name.
+/// Names can hold anything, including characters which would climb out of the
+/// directory or aren't allowed in file names.
+pub fn path_component(name: &str) -> String {
+    let component = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || "/\\:*?\"<>|".contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect::<String>();
+    match component.trim() {
+        "" | "." | ".." => "_".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_component_keeps_ordinary_names() {
+        assert_eq!(path_component("Lecture 1.pdf"), "Lecture 1.pdf");
+        assert_eq!(path_component("Résumé.docx"), "Résumé.docx");
+    }
+
+    #[test]
+    fn path_component_replaces_separators() {
+        assert_eq!(path_component("../../etc/passwd"), ".._.._etc_passwd");
+        assert_eq!(path_component("..\\boot.ini"), ".._boot.ini");
+        assert_eq!(path_component("/etc"), "_etc");
+    }
+
+    #[test]
+    fn path_component_never_climbs_or_vanishes() {
+        assert_eq!(path_component(".."), "_");
+        assert_eq!(path_component(" . "), "_");
+        assert_eq!(path_component(""), "_");
+        assert_eq!(path_component("   "), "_");
+    }
+
+    #[test]
+    fn path_component_replaces_reserved_and_control_characters() {
+        assert_eq!(path_component("a:b*c?\"d<e>f|g"), "a_b_c__d_e_f_g");
+        assert_eq!(path_component("line\nbreak\0"), "line_break_");
+    }
+
+    #[test]
+    fn path_component_stays_one_component() {
+        for name in &["..", "../x", "a/../../b", "C:\\x", "."] {
+            let component = path_component(name);
+            let path = Path::new("course").join(&component);
+            assert_eq!(path.parent(), Some(Path::new("course")));
+            assert_eq!(path.components().count(), 2);
+        }
+    }
+
+    #[test]
+    fn with_added_extension_keeps_existing_extension() {
+        assert_eq!(
+            with_added_extension(Path::new("dir/notes.pdf"), PART_EXTENSION),
+            PathBuf::from("dir/notes.pdf.part"),
+        );
+    }
+}
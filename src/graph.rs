@@ -0,0 +1,455 @@
+use clap::ArgEnum;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// The formats a relationship graph can be exported as.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum GraphFormat {
+    Dot,
+    // For Gephi, yEd, and friends.
+    Graphml,
+    // The node-link layout NetworkX reads with json_graph.node_link_graph.
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Course,
+    Group,
+    Module,
+    Section,
+    User,
+}
+
+/// A value hung off of a node or edge, for tools which can do more with
+/// properties than show them as a label.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Attribute {
+    Number(f64),
+    Text(String),
+}
+
+pub type Attributes = BTreeMap<String, Attribute>;
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    // Must be unique across the whole graph, not just among nodes of the same
+    // kind - hence ids like "course_1" rather than "1".
+    pub id: String,
+    pub kind: NodeKind,
+    pub label: String,
+    // The id of the cluster the node is drawn inside of, if any.
+    pub cluster: Option<String>,
+    pub attributes: Attributes,
+}
+
+#[derive(Clone, Debug)]
+pub struct Edge {
+    pub source: String,
+    pub target: String,
+    pub label: Option<String>,
+    pub attributes: Attributes,
+}
+
+/// A general purpose graph of Canvas entities, kept independent of any one
+/// export format.
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    pub name: String,
+    pub directed: bool,
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    // Cluster ids to their labels.
+    pub clusters: BTreeMap<String, String>,
+    // The ids in nodes, so add_node needn't search them all each time.
+    node_ids: HashSet<String>,
+}
+
+impl Graph {
+    pub fn new(name: &str, directed: bool) -> Self {
+        Graph {
+            name: name.to_string(),
+            directed,
+            ..Default::default()
+        }
+    }
+
+    /// Add a node unless one with the same id is already present. The same
+    /// user tends to show up once per course they are in.
+    pub fn add_node(&mut self, node: Node) {
+        if self.node_ids.insert(node.id.clone()) {
+            self.nodes.push(node);
+        }
+    }
+
+    pub fn has_node(&self, id: &str) -> bool {
+        self.node_ids.contains(id)
+    }
+}
+
+pub fn render(graph: &Graph, format: GraphFormat) -> String {
+    match format {
+        GraphFormat::Dot => to_dot(graph),
+        GraphFormat::Graphml => to_graphml(graph),
+        GraphFormat::Json => to_node_link_json(graph),
+    }
+}
+
+pub fn to_dot(graph: &Graph) -> String {
+    let (keyword, connector) = if graph.directed {
+        ("digraph", "->")
+    } else {
+        ("graph", "--")
+    };
+    let mut lines = vec![format!("{} {} {{", keyword, dot_quote(&graph.name))];
+    lines.push("  rankdir=LR;".to_string());
+    for (cluster_id, label) in &graph.clusters {
+        // Graphviz only draws a box around subgraphs named "cluster*".
+        lines.push(format!(
+            "  subgraph {} {{",
+            dot_quote(&format!("cluster_{}", cluster_id)),
+        ));
+        lines.push(format!("    label={};", dot_quote(label)));
+        for node in graph.nodes
+            .iter()
+            .filter(|n| n.cluster.as_ref() == Some(cluster_id)) {
+            lines.push(format!("    {}", dot_node(node)));
+        }
+        lines.push("  }".to_string());
+    }
+    for node in graph.nodes.iter().filter(|n| n.cluster.is_none()) {
+        lines.push(format!("  {}", dot_node(node)));
+    }
+    for edge in &graph.edges {
+        let mut properties = edge.label
+            .iter()
+            .map(|l| format!("label={}", dot_quote(l)))
+            .collect::<Vec<String>>();
+        // Graphviz pulls heavier edges shorter and straighter.
+        if let Some(Attribute::Number(weight)) = edge.attributes.get("weight") {
+            properties.push(format!("weight={}", weight));
+        }
+        let label = if properties.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", properties.join(", "))
+        };
+        lines.push(format!(
+            "  {} {} {}{};",
+            dot_quote(&edge.source),
+            connector,
+            dot_quote(&edge.target),
+            label,
+        ));
+    }
+    lines.push("}".to_string());
+    lines.join("\n") + "\n"
+}
+
+fn dot_node(node: &Node) -> String {
+    format!(
+        "{} [label={}, shape={}];",
+        dot_quote(&node.id),
+        dot_quote(&node.label),
+        dot_shape(node.kind),
+    )
+}
+
+fn dot_shape(kind: NodeKind) -> &'static str {
+    match kind {
+        NodeKind::Course => "box",
+        NodeKind::Group => "hexagon",
+        NodeKind::Module => "component",
+        NodeKind::Section => "folder",
+        NodeKind::User => "ellipse",
+    }
+}
+
+fn dot_quote(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n"),
+    )
+}
+
+pub fn to_graphml(graph: &Graph) -> String {
+    // GraphML wants every property declared up front along with its type.
+    let mut node_keys = graphml_keys(graph.nodes.iter().map(|n| &n.attributes));
+    node_keys.insert("kind".to_string(), "string");
+    node_keys.insert("label".to_string(), "string");
+    let mut edge_keys = graphml_keys(graph.edges.iter().map(|e| &e.attributes));
+    edge_keys.insert("label".to_string(), "string");
+    let mut lines = vec![
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string(),
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+            .to_string(),
+    ];
+    for (domain, keys) in [("node", &node_keys), ("edge", &edge_keys)] {
+        for (name, type_) in keys.iter() {
+            lines.push(format!(
+                "  <key id=\"{}_{}\" for=\"{}\" attr.name=\"{}\" \
+                 attr.type=\"{}\"/>",
+                domain,
+                xml_escape(name),
+                domain,
+                xml_escape(name),
+                type_,
+            ));
+        }
+    }
+    lines.push(format!(
+        "  <graph id=\"{}\" edgedefault=\"{}\">",
+        xml_escape(&graph.name),
+        if graph.directed { "directed" } else { "undirected" },
+    ));
+    for node in &graph.nodes {
+        lines.push(format!("    <node id=\"{}\">", xml_escape(&node.id)));
+        lines.push(graphml_data(
+            "node_kind",
+            &Attribute::Text(kind_name(node.kind)),
+        ));
+        lines.push(graphml_data(
+            "node_label",
+            &Attribute::Text(node.label.clone()),
+        ));
+        for (k, v) in &node.attributes {
+            lines.push(graphml_data(&format!("node_{}", k), v));
+        }
+        lines.push("    </node>".to_string());
+    }
+    for edge in &graph.edges {
+        lines.push(format!(
+            "    <edge source=\"{}\" target=\"{}\">",
+            xml_escape(&edge.source),
+            xml_escape(&edge.target),
+        ));
+        if let Some(label) = &edge.label {
+            lines.push(graphml_data(
+                "edge_label",
+                &Attribute::Text(label.clone()),
+            ));
+        }
+        for (k, v) in &edge.attributes {
+            lines.push(graphml_data(&format!("edge_{}", k), v));
+        }
+        lines.push("    </edge>".to_string());
+    }
+    lines.push("  </graph>".to_string());
+    lines.push("</graphml>".to_string());
+    lines.join("\n") + "\n"
+}
+
+pub fn to_node_link_json(graph: &Graph) -> String {
+    let nodes = graph.nodes
+        .iter()
+        .map(|n| {
+            let mut object = serde_json::Map::new();
+            object.insert("id".to_string(), n.id.clone().into());
+            object.insert("kind".to_string(), kind_name(n.kind).into());
+            object.insert("label".to_string(), n.label.clone().into());
+            if let Some(cluster) = &n.cluster {
+                object.insert("cluster".to_string(), cluster.clone().into());
+            }
+            object.extend(attributes_json(&n.attributes));
+            serde_json::Value::Object(object)
+        })
+        .collect::<Vec<serde_json::Value>>();
+    let links = graph.edges
+        .iter()
+        .map(|e| {
+            let mut object = serde_json::Map::new();
+            object.insert("source".to_string(), e.source.clone().into());
+            object.insert("target".to_string(), e.target.clone().into());
+            if let Some(label) = &e.label {
+                object.insert("label".to_string(), label.clone().into());
+            }
+            object.extend(attributes_json(&e.attributes));
+            serde_json::Value::Object(object)
+        })
+        .collect::<Vec<serde_json::Value>>();
+    let document = serde_json::json!({
+        "directed": graph.directed,
+        // A user enrolled twice in one course, under two roles or in two
+        // sections, gets an edge for each enrollment.
+        "multigraph": has_parallel_edges(graph),
+        "graph": { "name": graph.name, "clusters": graph.clusters },
+        "nodes": nodes,
+        "links": links,
+    });
+    // A Value built from strings, numbers and maps always serializes.
+    serde_json::to_string_pretty(&document).unwrap_or_default() + "\n"
+}
+
+fn has_parallel_edges(graph: &Graph) -> bool {
+    let mut pairs = HashSet::new();
+    graph.edges.iter().any(|e| {
+        let pair = if graph.directed || e.source <= e.target {
+            (&e.source, &e.target)
+        } else {
+            (&e.target, &e.source)
+        };
+        !pairs.insert(pair)
+    })
+}
+
+fn attributes_json(
+    attributes: &Attributes,
+) -> impl Iterator<Item = (String, serde_json::Value)> + '_ {
+    attributes
+        .iter()
+        .map(|(k, v)| {
+            (k.clone(), serde_json::to_value(v).unwrap_or_default())
+        })
+}
+
+fn kind_name(kind: NodeKind) -> String {
+    serde_json::to_value(kind)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+// Every value of an attribute has to fit the one type declared for it, so an
+// attribute which is a number in some places and text in others is text.
+fn graphml_keys<'a>(
+    attributes: impl Iterator<Item = &'a Attributes>,
+) -> BTreeMap<String, &'static str> {
+    let mut keys = BTreeMap::new();
+    for (k, v) in attributes.flatten() {
+        let type_ = graphml_type(v);
+        keys.entry(k.clone())
+            .and_modify(|t| if *t != type_ { *t = "string" })
+            .or_insert(type_);
+    }
+    keys
+}
+
+fn graphml_type(attribute: &Attribute) -> &'static str {
+    match attribute {
+        Attribute::Number(_) => "double",
+        Attribute::Text(_) => "string",
+    }
+}
+
+fn graphml_data(key: &str, attribute: &Attribute) -> String {
+    let value = match attribute {
+        Attribute::Number(n) => n.to_string(),
+        Attribute::Text(t) => xml_escape(t),
+    };
+    format!("      <data key=\"{}\">{}</data>", xml_escape(key), value)
+}
+
+pub fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, attributes: Attributes) -> Node {
+        Node {
+            id: id.to_string(),
+            kind: NodeKind::User,
+            label: id.to_string(),
+            cluster: None,
+            attributes,
+        }
+    }
+
+    fn edge(source: &str, target: &str) -> Edge {
+        Edge {
+            source: source.to_string(),
+            target: target.to_string(),
+            label: None,
+            attributes: Attributes::new(),
+        }
+    }
+
+    fn multigraph(graph: &Graph) -> serde_json::Value {
+        let document: serde_json::Value =
+            serde_json::from_str(&to_node_link_json(graph)).unwrap();
+        document["multigraph"].clone()
+    }
+
+    #[test]
+    fn add_node_skips_repeated_ids() {
+        let mut graph = Graph::new("g", false);
+        graph.add_node(node("a", Attributes::new()));
+        graph.add_node(node("a", Attributes::new()));
+        assert_eq!(graph.nodes.len(), 1);
+        assert!(graph.has_node("a"));
+        assert!(!graph.has_node("b"));
+    }
+
+    #[test]
+    fn not_multigraph_without_parallel_edges() {
+        let mut graph = Graph::new("g", false);
+        graph.edges = vec![edge("a", "b"), edge("b", "c")];
+        assert_eq!(multigraph(&graph), serde_json::json!(false));
+    }
+
+    #[test]
+    fn multigraph_with_repeated_edge() {
+        let mut graph = Graph::new("g", true);
+        graph.edges = vec![edge("a", "b"), edge("a", "b")];
+        assert_eq!(multigraph(&graph), serde_json::json!(true));
+    }
+
+    #[test]
+    fn reversed_edge_parallel_only_when_undirected() {
+        let mut graph = Graph::new("g", false);
+        graph.edges = vec![edge("a", "b"), edge("b", "a")];
+        assert_eq!(multigraph(&graph), serde_json::json!(true));
+        graph.directed = true;
+        assert_eq!(multigraph(&graph), serde_json::json!(false));
+    }
+
+    #[test]
+    fn graphml_keys_typed_by_values() {
+        let numbers = Attributes::from([
+            ("score".to_string(), Attribute::Number(1.0)),
+            ("role".to_string(), Attribute::Text("student".to_string())),
+        ]);
+        let keys = graphml_keys(vec![&numbers, &numbers].into_iter());
+        assert_eq!(keys.get("score"), Some(&"double"));
+        assert_eq!(keys.get("role"), Some(&"string"));
+    }
+
+    #[test]
+    fn graphml_keys_conflicting_types_are_strings() {
+        let number = Attributes::from([
+            ("grade".to_string(), Attribute::Number(90.0)),
+        ]);
+        let text = Attributes::from([
+            ("grade".to_string(), Attribute::Text("A".to_string())),
+        ]);
+        let keys = graphml_keys(vec![&number, &text].into_iter());
+        assert_eq!(keys.get("grade"), Some(&"string"));
+        let keys = graphml_keys(vec![&text, &number].into_iter());
+        assert_eq!(keys.get("grade"), Some(&"string"));
+    }
+
+    #[test]
+    fn graphml_declares_conflicting_key_as_string() {
+        let mut graph = Graph::new("g", false);
+        graph.add_node(node("a", Attributes::from([
+            ("grade".to_string(), Attribute::Number(90.0)),
+        ])));
+        graph.add_node(node("b", Attributes::from([
+            ("grade".to_string(), Attribute::Text("A".to_string())),
+        ])));
+        assert!(to_graphml(&graph).contains(
+            "<key id=\"node_grade\" for=\"node\" attr.name=\"grade\" \
+             attr.type=\"string\"/>",
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+use futures::future::join_all;
+use std::collections::HashMap;
+
+use crate::canvas;
+use crate::chart;
+use crate::cli;
+use crate::error;
+use crate::id;
+use crate::section;
+use crate::sis;
+use crate::term;
+
+/// A group in a course, along with the name of the category it was made in
+/// and its members.
+pub struct CourseGroup {
+    pub course_id: id::CourseId,
+    pub category: Option<String>,
+    pub group: canvas::Group,
+    pub members: Vec<canvas::User>,
+}
+
+/// List the groups in your courses, category by category, with their
+/// members.
+pub async fn list(
+    config: &cli::CliValid,
+    args: &cli::GroupsArgs,
+) -> Result<(), error::AppError> {
+    let (courses, _terms) = term::courses_selected(
+        config,
+        &config.course_query,
+    ).await?;
+    let courses = sis::courses_selected(
+        config,
+        courses,
+        &args.courses,
+    ).await?;
+    println!(
+        "{:<20}  {:<20}  {:<24}  {:>7}  Members",
+        "Course",
+        "Category",
+        "Group",
+        "Count",
+    );
+    for course in &courses {
+        let students = section::students_selected(config, course.id).await?;
+        let mut groups = course_groups(config, course.id).await;
+        // Narrowed to sections, groups show only their members in those
+        // sections, and groups with none there are left out.
+        if let Some(students) = &students {
+            for g in &mut groups {
+                g.members.retain(|u| students.contains(&u.id));
+            }
+            groups.retain(|g| !g.members.is_empty());
+        }
+        groups.sort_by(|a, b| {
+            a.category
+                .cmp(&b.category)
+                .then_with(|| a.group.name.cmp(&b.group.name))
+        });
+        for g in groups {
+            println!(
+                "{:<20}  {:<20}  {:<24}  {:>7}  {}",
+                chart::truncate(&course.course_code, 20),
+                chart::truncate(g.category.as_deref().unwrap_or("-"), 20),
+                chart::truncate(&g.group.name, 24),
+                match &students {
+                    Some(_) => g.members.len() as u64,
+                    None => g.group.members_count,
+                },
+                g.members
+                    .iter()
+                    .map(|u| u.name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Every group in a course with its members. Groups, categories and members
+/// which can't be had are left out with a warning rather than costing the
+/// rest, and the rest of the courses.
+pub async fn course_groups(
+    config: &cli::CliValid,
+    course_id: id::CourseId,
+) -> Vec<CourseGroup> {
+    let groups = canvas::course_groups(config, course_id)
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!(
+                "Could not get the groups of course {}: {:?}",
+                course_id,
+                e,
+            );
+            vec![]
+        });
+    let categories = canvas::group_categories(config, course_id)
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!(
+                "Could not get the group categories of course {}: {:?}",
+                course_id,
+                e,
+            );
+            vec![]
+        })
+        .into_iter()
+        .map(|c| (c.id, c.name))
+        .collect::<HashMap<id::GroupCategoryId, String>>();
+    let member_futures = groups
+        .iter()
+        .map(|g| canvas::group_users(config, g.id));
+    let members = join_all(member_futures).await;
+    groups
+        .into_iter()
+        .zip(members)
+        .map(|(group, members)| CourseGroup {
+            course_id,
+            category: categories.get(&group.group_category_id).cloned(),
+            members: members.unwrap_or_else(|e| {
+                log::warn!(
+                    "Could not get the members of group {}: {:?}",
+                    group.id,
+                    e,
+                );
+                vec![]
+            }),
+            group,
+        })
+        .collect()
+}
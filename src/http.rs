@@ -1,7 +1,13 @@
 // Needed to make future magic work, I guess.
 use futures::TryFutureExt;
+use lazy_static::lazy_static;
 use reqwest;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::sync::Semaphore;
 
 use crate::cli;
 use crate::error;
@@ -29,6 +35,93 @@ pub struct CanvasError {
     pub error_code: String,
 }
 
+/// How many requests are out to Canvas at once. Commands fire off requests
+/// for every course or assignment together, and they all wait their turn here.
+pub const REQUESTS_CONCURRENT: usize = 8;
+
+// Canvas gives each token a bucket of request cost, and refuses with a 403
+// once it runs dry. Below this much we slow down so it can refill.
+const RATE_LIMIT_LOW: f64 = 100.0;
+
+// How many times a request refused for the rate limit is tried again, waiting
+// twice as long each time.
+const RATE_LIMIT_RETRIES: u32 = 5;
+
+lazy_static! {
+    static ref REQUEST_SLOTS: Semaphore = Semaphore::new(REQUESTS_CONCURRENT);
+}
+
+/// Where and for how long GET responses are kept on disk. Caching is disabled
+/// entirely when this is absent from the CLI configuration.
+#[derive(Clone, Debug)]
+pub struct CacheConfig {
+    pub dir: PathBuf,
+    // Within this window a cached response is used without asking Canvas at
+    // all. Past it we revalidate using the stored ETag/Last-Modified values.
+    pub ttl: chrono::Duration,
+}
+
+// What we keep on disk for a single response. Headers are stored as plain
+// pairs since HeaderMap doesn't serialize.
+#[derive(Debug, Deserialize, Serialize)]
+struct CacheEntry {
+    url: String,
+    fetched_at: chrono::DateTime<chrono::Utc>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    status: u16,
+    headers: Vec<(String, String)>,
+    text: String,
+}
+
+/// Build a full Canvas API URL for the configured server from a path such as
+/// "courses" or "courses/123/enrollments".
+pub fn api_url(config: &cli::CliValid, path: &str) -> String {
+    format!(
+        "{}/api/v1/{}",
+        config.server.host_url.trim_end_matches('/'),
+        path,
+    )
+}
+
+/// Like api_url, but with query parameters appended. Canvas takes repeated
+/// keys for array parameters (e.g. "type[]"), so the same key may show up more
+/// than once.
+pub fn api_url_query(
+    config: &cli::CliValid,
+    path: &str,
+    query: &[(&str, String)],
+) -> String {
+    url_query(api_url(config, path), query)
+}
+
+/// Like api_url_query, but for the New Quizzes API, which Canvas serves apart
+/// from everything else.
+pub fn quiz_api_url_query(
+    config: &cli::CliValid,
+    path: &str,
+    query: &[(&str, String)],
+) -> String {
+    url_query(
+        format!(
+            "{}/api/quiz/v1/{}",
+            config.server.host_url.trim_end_matches('/'),
+            path,
+        ),
+        query,
+    )
+}
+
+fn url_query(url: String, query: &[(&str, String)]) -> String {
+    if query.is_empty() {
+        url
+    } else {
+        reqwest::Url::parse_with_params(&url, query)
+            .map(|u| u.to_string())
+            .unwrap_or(url)
+    }
+}
+
 /// Make a generic request and deserialize the response.
 pub async fn request<'a, A: serde::de::DeserializeOwned>(
     config: &'a cli::CliValid,
@@ -40,6 +133,66 @@ pub async fn request<'a, A: serde::de::DeserializeOwned>(
         method,
         url,
     ).await?;
+    response_deserialize(&buffered_response)
+}
+
+/// Fetch a URL Canvas hands out for use outside of the API, such as a
+/// calendar feed, without the API token. Such URLs carry their own token
+/// instead, and needn't point at the Canvas server at all.
+pub async fn request_public_text(
+    url: String,
+) -> Result<String, error::AppError> {
+    // The semaphore is never closed, so there's always a slot to wait for.
+    let _slot = REQUEST_SLOTS.acquire().await.ok();
+    let response = reqwest::Client::new()
+        .get(url)
+        .send()
+        .await
+        .map_err(error::AppError::CanvasRequestError)?;
+    if !response.status().is_success() {
+        return Err(error::AppError::CanvasStatusError(response.status()));
+    }
+    response.text().await.map_err(error::AppError::CanvasRequestError)
+}
+
+/// Request a collection Canvas paginates, following the "next" links it hands
+/// back in the Link header until every page has been gathered.
+pub async fn request_paginated<A: serde::de::DeserializeOwned>(
+    config: &cli::CliValid,
+    method: reqwest::Method,
+    url: String,
+) -> Result<Vec<A>, error::AppError> {
+    request_paginated_by::<Vec<A>, A>(config, method, url, |page| page).await
+}
+
+/// Like request_paginated, but for endpoints which wrap each page in an
+/// object. The unwrap function pulls the items back out of each page.
+pub async fn request_paginated_by<W, A>(
+    config: &cli::CliValid,
+    method: reqwest::Method,
+    url: String,
+    unwrap: fn(W) -> Vec<A>,
+) -> Result<Vec<A>, error::AppError>
+where
+    W: serde::de::DeserializeOwned,
+{
+    let mut items = vec![];
+    let mut next = Some(url);
+    while let Some(url) = next {
+        let buffered_response = request_buffered(
+            config,
+            method.clone(),
+            url,
+        ).await?;
+        next = link_next(&buffered_response.headers)?;
+        items.extend(unwrap(response_deserialize::<W>(&buffered_response)?));
+    }
+    Ok(items)
+}
+
+fn response_deserialize<A: serde::de::DeserializeOwned>(
+    buffered_response: &BufferedResponse,
+) -> Result<A, error::AppError> {
     // If there is a server error, there should be an accompanying payload we
     // can inspect.
     if buffered_response.status.as_u16() < 400 {
@@ -54,17 +207,87 @@ pub async fn request<'a, A: serde::de::DeserializeOwned>(
     }
 }
 
+// Canvas' Link header looks like:
+// <https://.../courses?page=2&per_page=10>; rel="next",<...>; rel="last"
+fn link_next(
+    headers: &reqwest::header::HeaderMap,
+) -> Result<Option<String>, error::AppError> {
+    match headers.get(reqwest::header::LINK) {
+        Some(link) => Ok(
+            link.to_str()
+                .map_err(error::AppError::CanvasHeaderError)?
+                .split(',')
+                .find_map(|part| {
+                    let mut pieces = part.split(';');
+                    let url = pieces.next()?.trim();
+                    pieces
+                        .any(|p| p.trim() == "rel=\"next\"")
+                        .then(|| {
+                            url.trim_start_matches('<')
+                               .trim_end_matches('>')
+                               .to_string()
+                        })
+                })
+        ),
+        None => Ok(None),
+    }
+}
+
 /// Make a generic request to the Canvas API using the auth token.
 pub async fn request_raw<'a>(
     config: &'a cli::CliValid,
     method: reqwest::Method,
     url: String,
+    mut headers: reqwest::header::HeaderMap,
 ) -> Result<reqwest::Response, reqwest::Error> {
-    reqwest::Client::new()
-        .request(method, url)
-        .bearer_auth(config.server.api_token.clone())
-        .send()
-        .await
+    // The ids come back as strings, but otherwise the JSON is the same.
+    if config.string_ids {
+        headers.insert(
+            reqwest::header::ACCEPT,
+            reqwest::header::HeaderValue::from_static(
+                "application/json+canvas-string-ids",
+            ),
+        );
+    }
+    // The semaphore is never closed, so there's always a slot to wait for.
+    let _slot = REQUEST_SLOTS.acquire().await.ok();
+    let mut retries = 0;
+    loop {
+        let response = reqwest::Client::new()
+            .request(method.clone(), url.clone())
+            .bearer_auth(config.server.api_token.clone())
+            .headers(headers.clone())
+            .send()
+            .await?;
+        let remaining = rate_limit_remaining(&response);
+        let limited = response.status() == reqwest::StatusCode::FORBIDDEN
+            && remaining.map(|r| r <= 0.0).unwrap_or(false);
+        if limited && retries < RATE_LIMIT_RETRIES {
+            let wait = std::time::Duration::from_secs(1 << retries);
+            log::info!(
+                "Canvas' rate limit was hit, trying {} again in {:?}.",
+                url,
+                wait,
+            );
+            tokio::time::sleep(wait).await;
+            retries += 1;
+            continue;
+        }
+        if remaining.map(|r| r < RATE_LIMIT_LOW).unwrap_or(false) {
+            log::debug!("Close to Canvas' rate limit, slowing down.");
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+        return Ok(response);
+    }
+}
+
+// What's left of the rate limit bucket, which Canvas reports with every
+// response.
+fn rate_limit_remaining(response: &reqwest::Response) -> Option<f64> {
+    response.headers()
+        .get("X-Rate-Limit-Remaining")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<f64>().ok())
 }
 
 pub async fn request_buffered<'a>(
@@ -72,16 +295,58 @@ pub async fn request_buffered<'a>(
     method: reqwest::Method,
     url: String,
 ) -> Result<BufferedResponse, error::AppError> {
-    let response = request_raw(config, method.clone(), url.clone())
+    // Only GETs are safe to replay from disk.
+    let cache = match method {
+        reqwest::Method::GET => config.cache.as_ref(),
+        _ => None,
+    };
+    let cache_file = cache.map(|c| cache_path(config, c, &url));
+    let cached = cache_file.as_ref().and_then(cache_read);
+    if let (Some(c), Some(entry)) = (cache, &cached) {
+        if chrono::Utc::now() - entry.fetched_at < c.ttl {
+            log::debug!("Using cached response for {} {}.", method, url);
+            return Ok(cache_entry_to_response(entry));
+        }
+    }
+    let conditional_headers = cached
+        .as_ref()
+        .map(cache_conditional_headers)
+        .unwrap_or_default();
+    let response = request_raw(
+        config,
+        method.clone(),
+        url.clone(),
+        conditional_headers,
+    )
         .map_err(error::AppError::CanvasRequestError)
         .await?;
-    let buffered_response = to_buffered_response(response).await?;
+    let mut buffered_response = to_buffered_response(response).await?;
     log::debug!(
         "Response from {} {}: {:#?}",
         method,
         url,
         buffered_response,
     );
+    if let Some(path) = cache_file {
+        match (buffered_response.status, cached) {
+            (reqwest::StatusCode::NOT_MODIFIED, Some(entry)) => {
+                log::debug!("Cached response for {} is still valid.", url);
+                let entry = CacheEntry {
+                    fetched_at: chrono::Utc::now(),
+                    ..entry
+                };
+                buffered_response = cache_entry_to_response(&entry);
+                cache_write(&path, &entry);
+            },
+            (status, _) if status.is_success() => {
+                cache_write(&path, &response_to_cache_entry(
+                    &url,
+                    &buffered_response,
+                ));
+            },
+            _ => (),
+        }
+    }
     Ok(buffered_response)
 }
 
@@ -95,3 +360,201 @@ pub async fn to_buffered_response(
         text: r.text().await.map_err(error::AppError::CanvasRequestError)?,
     })
 }
+
+// Responses differ per server and per token (a teacher sees more than a
+// student), so both take part in the key. The token is hashed on its own first
+// so it can't be recovered from anything we write to disk.
+fn cache_path(
+    config: &cli::CliValid,
+    cache: &CacheConfig,
+    url: &str,
+) -> PathBuf {
+    let token_hash = Sha256::digest(config.server.api_token.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(config.server.name.as_bytes());
+    hasher.update([0]);
+    hasher.update(url.as_bytes());
+    hasher.update([0]);
+    hasher.update(token_hash);
+    cache.dir.join(format!("{:x}.json", hasher.finalize()))
+}
+
+// A missing or unreadable cache entry is just a cache miss - the request can
+// still be made normally.
+fn cache_read(path: &PathBuf) -> Option<CacheEntry> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| {
+            serde_json::from_str::<CacheEntry>(&text)
+                .map_err(|e| {
+                    log::warn!(
+                        "Ignoring unreadable cache entry {:?}: {}",
+                        path,
+                        e,
+                    );
+                })
+                .ok()
+        })
+}
+
+// Failing to write the cache shouldn't fail the run, so we only warn. Entries
+// are private to the user running us, since they hold whatever the token could
+// see.
+fn cache_write(path: &PathBuf, entry: &CacheEntry) {
+    let result = path.parent()
+        .map(fs::create_dir_all)
+        .unwrap_or(Ok(()))
+        .and_then(|_| {
+            serde_json::to_string(entry)
+                .map_err(std::io::Error::from)
+        })
+        .and_then(|text| {
+            let mut file = cache_open(path)?;
+            file.write_all(text.as_bytes())
+        });
+    if let Err(e) = result {
+        log::warn!("Could not write cache entry {:?}: {}", path, e);
+    }
+}
+
+#[cfg(unix)]
+fn cache_open(path: &PathBuf) -> std::io::Result<fs::File> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // The mode only applies to new files, and older entries may predate it.
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn cache_open(path: &PathBuf) -> std::io::Result<fs::File> {
+    fs::File::create(path)
+}
+
+fn cache_conditional_headers(
+    entry: &CacheEntry,
+) -> reqwest::header::HeaderMap {
+    let mut headers = reqwest::header::HeaderMap::new();
+    let pairs = [
+        (reqwest::header::IF_NONE_MATCH, &entry.etag),
+        (reqwest::header::IF_MODIFIED_SINCE, &entry.last_modified),
+    ];
+    for (name, value) in pairs.iter() {
+        if let Some(v) = value.as_ref()
+            .and_then(|v| reqwest::header::HeaderValue::from_str(v).ok()) {
+            headers.insert(name.clone(), v);
+        }
+    }
+    headers
+}
+
+fn cache_entry_to_response(entry: &CacheEntry) -> BufferedResponse {
+    let headers = entry.headers
+        .iter()
+        .filter_map(|(k, v)| {
+            Some((
+                reqwest::header::HeaderName::from_bytes(k.as_bytes()).ok()?,
+                reqwest::header::HeaderValue::from_str(v).ok()?,
+            ))
+        })
+        .collect();
+    BufferedResponse {
+        headers,
+        status: reqwest::StatusCode::from_u16(entry.status)
+            .unwrap_or(reqwest::StatusCode::OK),
+        text: entry.text.clone(),
+    }
+}
+
+// Only what's needed to replay a response goes to disk. Anything else, such as
+// a session cookie, has no business sitting in the cache.
+const CACHED_HEADERS: [reqwest::header::HeaderName; 4] = [
+    reqwest::header::CONTENT_TYPE,
+    reqwest::header::ETAG,
+    reqwest::header::LAST_MODIFIED,
+    reqwest::header::LINK,
+];
+
+fn response_to_cache_entry(
+    url: &str,
+    response: &BufferedResponse,
+) -> CacheEntry {
+    let header = |name: reqwest::header::HeaderName| {
+        response.headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    CacheEntry {
+        url: url.to_string(),
+        fetched_at: chrono::Utc::now(),
+        etag: header(reqwest::header::ETAG),
+        last_modified: header(reqwest::header::LAST_MODIFIED),
+        status: response.status.as_u16(),
+        headers: CACHED_HEADERS
+            .iter()
+            .filter_map(|name| {
+                header(name.clone()).map(|v| (name.to_string(), v))
+            })
+            .collect(),
+        text: response.text.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(value: &str) -> reqwest::header::HeaderMap {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::LINK,
+            reqwest::header::HeaderValue::from_str(value).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn link_next_finds_next_among_others() {
+        let headers = link(
+            "<https://c.test/api/v1/courses?page=1>; rel=\"current\",\
+             <https://c.test/api/v1/courses?page=2>; rel=\"next\",\
+             <https://c.test/api/v1/courses?page=5>; rel=\"last\"",
+        );
+        assert_eq!(
+            link_next(&headers).unwrap(),
+            Some("https://c.test/api/v1/courses?page=2".to_string()),
+        );
+    }
+
+    #[test]
+    fn link_next_none_on_last_page() {
+        let headers = link(
+            "<https://c.test/api/v1/courses?page=1>; rel=\"first\", \
+             <https://c.test/api/v1/courses?page=5>; rel=\"last\"",
+        );
+        assert_eq!(link_next(&headers).unwrap(), None);
+    }
+
+    #[test]
+    fn link_next_none_without_link() {
+        let headers = reqwest::header::HeaderMap::new();
+        assert_eq!(link_next(&headers).unwrap(), None);
+    }
+
+    #[test]
+    fn link_next_errors_on_unreadable_header() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::LINK,
+            reqwest::header::HeaderValue::from_bytes(b"<\xff>; rel=\"next\"")
+                .unwrap(),
+        );
+        assert!(link_next(&headers).is_err());
+    }
+}
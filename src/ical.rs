@@ -0,0 +1,445 @@
+use chrono::TimeZone;
+
+/// When an event happens: either at an instant, or across a whole day in
+/// whatever time zone the calendar is viewed in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventTime {
+    At(chrono::DateTime<chrono::Utc>),
+    Day(chrono::NaiveDate),
+}
+
+impl EventTime {
+    /// The day this falls on where we are.
+    pub fn local_date(&self) -> chrono::NaiveDate {
+        match self {
+            EventTime::At(at) => {
+                at.with_timezone(&chrono::Local).naive_local().date()
+            },
+            EventTime::Day(day) => *day,
+        }
+    }
+
+    /// Orders times by day, with all day events ahead of the rest of that
+    /// day.
+    pub fn sort_key(&self) -> (chrono::NaiveDate, Option<chrono::NaiveTime>) {
+        match self {
+            EventTime::At(at) => (
+                self.local_date(),
+                Some(at.with_timezone(&chrono::Local).naive_local().time()),
+            ),
+            EventTime::Day(day) => (*day, None),
+        }
+    }
+}
+
+/// A single VEVENT, as much of one as we have use for.
+#[derive(Clone, Debug)]
+pub struct Event {
+    // Calendar clients match events across refreshes by this, so it must come
+    // out the same every time for the same Canvas object.
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub url: Option<String>,
+    pub start: EventTime,
+    pub end: Option<EventTime>,
+    pub categories: Vec<String>,
+    pub last_modified: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// An iCalendar (RFC 5545) document holding the given events.
+pub fn to_ics(
+    name: &str,
+    events: &[Event],
+    now: chrono::DateTime<chrono::Utc>,
+) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//canvas-instructure-graph//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+    for event in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", escape_text(&event.uid)));
+        lines.push(format!("DTSTAMP:{}", format_utc(&now)));
+        lines.push(time_property("DTSTART", &event.start));
+        if let Some(end) = &event.end {
+            lines.push(time_property("DTEND", end));
+        }
+        lines.push(format!("SUMMARY:{}", escape_text(&event.summary)));
+        if let Some(description) = &event.description {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+        if let Some(location) = &event.location {
+            lines.push(format!("LOCATION:{}", escape_text(location)));
+        }
+        if let Some(url) = &event.url {
+            lines.push(format!("URL:{}", url));
+        }
+        if !event.categories.is_empty() {
+            lines.push(format!(
+                "CATEGORIES:{}",
+                event.categories
+                    .iter()
+                    .map(|c| escape_text(c))
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ));
+        }
+        if let Some(last_modified) = &event.last_modified {
+            lines.push(format!("LAST-MODIFIED:{}", format_utc(last_modified)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines
+        .iter()
+        .map(|l| fold(l))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn time_property(name: &str, time: &EventTime) -> String {
+    match time {
+        EventTime::At(at) => format!("{}:{}", name, format_utc(at)),
+        EventTime::Day(day) => {
+            format!("{};VALUE=DATE:{}", name, day.format("%Y%m%d"))
+        },
+    }
+}
+
+fn format_utc(at: &chrono::DateTime<chrono::Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape_text(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// Content lines are limited to 75 octets, with longer ones continued on the
+// next line after a single space. Never split a character in two.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Pull the VEVENTs out of an iCalendar document. Parsing is forgiving:
+/// events without a UID or a start are skipped, as are properties we don't
+/// understand.
+pub fn parse(text: &str) -> Vec<Event> {
+    let mut events = vec![];
+    let mut current: Option<Vec<Property>> = None;
+    for line in unfold(text) {
+        let property = match parse_property(&line) {
+            Some(p) => p,
+            None => continue,
+        };
+        match (property.name.as_str(), property.value.as_str()) {
+            ("BEGIN", "VEVENT") => current = Some(vec![]),
+            ("END", "VEVENT") => {
+                events.extend(current.take().and_then(|p| to_event(&p)));
+            },
+            _ => {
+                if let Some(properties) = current.as_mut() {
+                    properties.push(property);
+                }
+            },
+        }
+    }
+    events
+}
+
+// A content line taken apart, as in "DTSTART;TZID=America/Chicago:2026...".
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+// Undo the line folding to_ics does, accepting bare newlines and tabs too
+// since not every feed sticks to the letter of the spec.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in text.lines() {
+        let continued = line
+            .strip_prefix(' ')
+            .or_else(|| line.strip_prefix('\t'));
+        match (continued, lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn parse_property(line: &str) -> Option<Property> {
+    // Parameter values may be quoted and hold colons of their own, so the
+    // value starts at the first colon outside of quotes.
+    let mut quoted = false;
+    let split = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        },
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+    let mut parts = line[..split].split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    let params = parts
+        .filter_map(|p| {
+            let (n, v) = p.split_once('=')?;
+            Some((n.to_string(), v.trim_matches('"').to_string()))
+        })
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value: line[split + 1..].to_string(),
+    })
+}
+
+fn to_event(properties: &[Property]) -> Option<Event> {
+    let get = |name: &str| properties.iter().find(|p| p.name == name);
+    let text = |name: &str| get(name).map(|p| unescape_text(&p.value));
+    let uid = text("UID")?;
+    let start = get("DTSTART").and_then(parse_time);
+    if start.is_none() {
+        log::debug!("Skipping calendar event {} without a start.", uid);
+    }
+    Some(Event {
+        uid,
+        summary: text("SUMMARY").unwrap_or_default(),
+        description: text("DESCRIPTION").filter(|d| !d.is_empty()),
+        location: text("LOCATION").filter(|l| !l.is_empty()),
+        url: get("URL").map(|p| p.value.clone()),
+        start: start?,
+        end: get("DTEND").and_then(parse_time),
+        categories: properties
+            .iter()
+            .filter(|p| p.name == "CATEGORIES")
+            .flat_map(|p| split_escaped(&p.value))
+            .collect(),
+        last_modified: get("LAST-MODIFIED")
+            .and_then(parse_time)
+            .and_then(|t| match t {
+                EventTime::At(at) => Some(at),
+                EventTime::Day(_) => None,
+            }),
+    })
+}
+
+// Times come as a UTC instant, a date, a wall clock time in a named zone, or
+// a "floating" wall clock time which we read as local.
+fn parse_time(property: &Property) -> Option<EventTime> {
+    let value = property.value.trim();
+    if property.param("VALUE") == Some("DATE") || value.len() == 8 {
+        return chrono::NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()
+            .map(EventTime::Day);
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        return chrono::NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .ok()
+            .map(|at| {
+                EventTime::At(chrono::DateTime::from_utc(at, chrono::Utc))
+            });
+    }
+    let at = chrono::NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .ok()?;
+    let local = match property.param("TZID") {
+        Some(zone) => match zone.parse::<chrono_tz::Tz>() {
+            Ok(zone) => zone
+                .from_local_datetime(&at)
+                .earliest()
+                .map(|at| at.with_timezone(&chrono::Utc)),
+            Err(_) => {
+                log::debug!("Unknown time zone '{}', reading as local.", zone);
+                None
+            },
+        },
+        None => None,
+    };
+    local
+        .or_else(|| {
+            chrono::Local
+                .from_local_datetime(&at)
+                .earliest()
+                .map(|at| at.with_timezone(&chrono::Utc))
+        })
+        .map(EventTime::At)
+}
+
+fn unescape_text(s: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => unescaped.push('\n'),
+                Some(other) => unescaped.push(other),
+                None => (),
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+// Split a list value on its unescaped commas.
+fn split_escaped(s: &str) -> Vec<String> {
+    let mut items = vec![];
+    let mut item = String::new();
+    let mut escaped = false;
+    for c in s.chars() {
+        match c {
+            ',' if !escaped => {
+                items.push(unescape_text(&std::mem::take(&mut item)));
+            },
+            _ => {
+                escaped = c == '\\' && !escaped;
+                item.push(c);
+            },
+        }
+    }
+    items.push(unescape_text(&item));
+    items.into_iter().filter(|i| !i.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_leaves_short_lines() {
+        assert_eq!(fold("SUMMARY:Quiz 1"), "SUMMARY:Quiz 1\r\n");
+    }
+
+    #[test]
+    fn fold_limits_lines_to_75_octets() {
+        let line = format!("DESCRIPTION:{}", "x".repeat(200));
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|l| l.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), format!("{}\r\n", line));
+    }
+
+    #[test]
+    fn fold_never_splits_a_character() {
+        let line = format!("SUMMARY:{}", "é".repeat(100));
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|l| l.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), format!("{}\r\n", line));
+    }
+
+    #[test]
+    fn escape_text_escapes_separators() {
+        assert_eq!(escape_text("a,b;c\\d\ne"), r"a\,b\;c\\d\ne");
+    }
+
+    fn event(uid: &str, start: EventTime) -> Event {
+        Event {
+            uid: uid.to_string(),
+            summary: "Essay, draft; final".to_string(),
+            description: Some("Line one\nLine two".to_string()),
+            location: None,
+            url: Some("https://c.test/courses/1/assignments/2".to_string()),
+            start,
+            end: None,
+            categories: vec!["C1".to_string(), "a,b".to_string()],
+            last_modified: None,
+        }
+    }
+
+    #[test]
+    fn parse_reads_back_what_to_ics_writes() {
+        let at = chrono::Utc.ymd(2021, 10, 4).and_hms(15, 30, 0);
+        let day = chrono::NaiveDate::from_ymd(2021, 10, 5);
+        let mut long = event("assignment-2@c.test", EventTime::At(at));
+        long.description = Some("word ".repeat(40));
+        let events = vec![long, event("event-3@c.test", EventTime::Day(day))];
+        let parsed = parse(&to_ics("C1", &events, at));
+        assert_eq!(parsed.len(), 2);
+        for (parsed, event) in parsed.iter().zip(&events) {
+            assert_eq!(parsed.uid, event.uid);
+            assert_eq!(parsed.summary, event.summary);
+            assert_eq!(parsed.description, event.description);
+            assert_eq!(parsed.url, event.url);
+            assert_eq!(parsed.start, event.start);
+            assert_eq!(parsed.categories, event.categories);
+        }
+    }
+
+    #[test]
+    fn parse_unfolds_bare_newlines_and_tabs() {
+        let parsed = parse(
+            "BEGIN:VEVENT\nUID:1\nDTSTART:20211004T153000Z\n\
+             SUMMARY:Mid\n\tterm\n Exam\nEND:VEVENT\n",
+        );
+        assert_eq!(parsed[0].summary, "MidtermExam");
+    }
+
+    #[test]
+    fn parse_skips_events_without_uid_or_start() {
+        let parsed = parse(
+            "BEGIN:VCALENDAR\r\n\
+             BEGIN:VEVENT\r\nDTSTART:20211004T153000Z\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:2\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:3\r\nDTSTART:nonsense\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:4\r\nDTSTART:20211004\r\nEND:VEVENT\r\n\
+             END:VCALENDAR\r\n",
+        );
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].uid, "4");
+    }
+
+    #[test]
+    fn parse_time_in_named_zone() {
+        let parsed = parse(
+            "BEGIN:VEVENT\r\nUID:1\r\n\
+             DTSTART;TZID=\"America/Chicago\":20211004T103000\r\n\
+             END:VEVENT\r\n",
+        );
+        assert_eq!(
+            parsed[0].start,
+            EventTime::At(chrono::Utc.ymd(2021, 10, 4).and_hms(15, 30, 0)),
+        );
+    }
+
+    #[test]
+    fn parse_property_value_after_quoted_colon() {
+        let property = parse_property(
+            "ATTACH;FMTTYPE=\"text/html\";X-A=\"a:b\":https://c.test/x",
+        ).unwrap();
+        assert_eq!(property.name, "ATTACH");
+        assert_eq!(property.param("x-a"), Some("a:b"));
+        assert_eq!(property.value, "https://c.test/x");
+    }
+}
@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+/// What every kind of Canvas id has in common. Ids of different kinds are
+/// different types, so a user id can't be handed to something wanting a
+/// course id.
+pub trait CanvasId: Copy + fmt::Debug + fmt::Display + FromStr {
+    // The prefixes Canvas takes in place of an id to look the thing up by
+    // one of its SIS ids instead, such as "sis_course_id". Empty for things
+    // SIS doesn't know about.
+    const SIS_PREFIXES: &'static [&'static str];
+}
+
+// Canvas hands ids back as numbers, except in its string id mode and in some
+// newer APIs, where they're strings. Either is read the same.
+macro_rules! canvas_id {
+    ($(#[$doc:meta])* $name:ident, [$($sis:expr),*]) => {
+        $(#[$doc])*
+        #[derive(
+            Clone,
+            Copy,
+            Debug,
+            Eq,
+            Hash,
+            Ord,
+            PartialEq,
+            PartialOrd,
+            Serialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(pub u64);
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                number_or_string(deserializer).map($name)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = std::num::ParseIntError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.trim().parse().map($name)
+            }
+        }
+
+        impl CanvasId for $name {
+            const SIS_PREFIXES: &'static [&'static str] = &[$($sis),*];
+        }
+    };
+}
+
+canvas_id!(AccountId, ["sis_account_id"]);
+canvas_id!(AssignmentGroupId, []);
+canvas_id!(
+    /// Also the id of a New Quiz, which is the assignment behind it.
+    AssignmentId,
+    []
+);
+canvas_id!(CalendarEventId, []);
+canvas_id!(
+    /// The id of whatever a module item points at, which depends on the
+    /// item's type.
+    ContentId,
+    []
+);
+canvas_id!(CourseId, ["sis_course_id"]);
+canvas_id!(EnrollmentId, []);
+canvas_id!(EntryId, []);
+canvas_id!(FileId, []);
+canvas_id!(FolderId, []);
+canvas_id!(GradingPeriodId, []);
+canvas_id!(GradingStandardId, []);
+canvas_id!(GroupCategoryId, []);
+canvas_id!(GroupId, ["sis_group_id"]);
+canvas_id!(ModuleId, []);
+canvas_id!(ModuleItemId, []);
+canvas_id!(QuizId, []);
+canvas_id!(QuizStatisticsId, []);
+canvas_id!(QuizSubmissionId, []);
+canvas_id!(RoleId, []);
+canvas_id!(SectionId, ["sis_section_id"]);
+canvas_id!(SisImportId, []);
+canvas_id!(SubmissionCommentId, []);
+canvas_id!(SubmissionId, []);
+canvas_id!(TermId, ["sis_term_id"]);
+canvas_id!(TopicId, []);
+canvas_id!(
+    UserId,
+    ["sis_user_id", "sis_login_id", "sis_integration_id"]
+);
+
+/// A way of naming something to an endpoint: by its Canvas id, or by one of
+/// its SIS ids for Canvas to look it up by, written as "sis_course_id:ABC123".
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IdRef<I> {
+    Id(I),
+    Sis {
+        prefix: &'static str,
+        id: String,
+    },
+}
+
+impl<I: CanvasId> From<I> for IdRef<I> {
+    fn from(id: I) -> Self {
+        IdRef::Id(id)
+    }
+}
+
+impl<I: CanvasId> From<&I> for IdRef<I> {
+    fn from(id: &I) -> Self {
+        IdRef::Id(*id)
+    }
+}
+
+// As it goes into a URL path. SIS ids can hold anything, so everything but
+// the plainest characters is escaped.
+impl<I: CanvasId> fmt::Display for IdRef<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdRef::Id(id) => write!(f, "{}", id),
+            IdRef::Sis { prefix, id } => {
+                write!(f, "{}:", prefix)?;
+                for byte in id.bytes() {
+                    match byte {
+                        b'A'..=b'Z'
+                        | b'a'..=b'z'
+                        | b'0'..=b'9'
+                        | b'-'
+                        | b'_'
+                        | b'.'
+                        | b'~' => write!(f, "{}", byte as char)?,
+                        _ => write!(f, "%{:02X}", byte)?,
+                    }
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+/// Why a string couldn't be read as an id of some kind.
+#[derive(Debug)]
+pub struct IdRefParseError<I> {
+    given: String,
+    kind: PhantomData<I>,
+}
+
+impl<I: CanvasId> fmt::Display for IdRefParseError<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if I::SIS_PREFIXES.is_empty() {
+            write!(f, "'{}' is not a numeric id", self.given)
+        } else {
+            write!(
+                f,
+                "'{}' is not a numeric id, or an SIS id written as {}",
+                self.given,
+                I::SIS_PREFIXES
+                    .iter()
+                    .map(|p| format!("{}:<id>", p))
+                    .collect::<Vec<String>>()
+                    .join(" or "),
+            )
+        }
+    }
+}
+
+impl<I: CanvasId> std::error::Error for IdRefParseError<I> {}
+
+impl<I: CanvasId> FromStr for IdRef<I> {
+    type Err = IdRefParseError<I>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || IdRefParseError {
+            given: s.to_string(),
+            kind: PhantomData,
+        };
+        match s.split_once(':') {
+            Some((prefix, id)) if !id.is_empty() => I::SIS_PREFIXES
+                .iter()
+                .find(|p| **p == prefix)
+                .map(|&prefix| IdRef::Sis {
+                    prefix,
+                    id: id.to_string(),
+                })
+                .ok_or_else(error),
+            Some(_) => Err(error()),
+            None => s.parse().map(IdRef::Id).map_err(|_| error()),
+        }
+    }
+}
+
+fn number_or_string<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Number(u64),
+        String(String),
+    }
+    match Id::deserialize(deserializer)? {
+        Id::Number(id) => Ok(id),
+        Id::String(id) => id.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_ref_from_number() {
+        assert_eq!(
+            "42".parse::<IdRef<CourseId>>().unwrap(),
+            IdRef::Id(CourseId(42)),
+        );
+    }
+
+    #[test]
+    fn id_ref_from_sis_id() {
+        assert_eq!(
+            "sis_course_id:BIO-101:2021".parse::<IdRef<CourseId>>().unwrap(),
+            IdRef::Sis {
+                prefix: "sis_course_id",
+                id: "BIO-101:2021".to_string(),
+            },
+        );
+        assert_eq!(
+            "sis_login_id:ann".parse::<IdRef<UserId>>().unwrap(),
+            IdRef::Sis { prefix: "sis_login_id", id: "ann".to_string() },
+        );
+    }
+
+    #[test]
+    fn id_ref_rejects_other_kinds_prefix() {
+        assert!("sis_user_id:1".parse::<IdRef<CourseId>>().is_err());
+        assert!("sis_course_id:1".parse::<IdRef<ModuleId>>().is_err());
+    }
+
+    #[test]
+    fn id_ref_rejects_empty_and_unnumbered() {
+        assert!("sis_course_id:".parse::<IdRef<CourseId>>().is_err());
+        assert!("".parse::<IdRef<CourseId>>().is_err());
+        assert!("BIO-101".parse::<IdRef<CourseId>>().is_err());
+    }
+
+    #[test]
+    fn id_ref_parse_error_names_prefixes() {
+        let error = "x".parse::<IdRef<TermId>>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "'x' is not a numeric id, or an SIS id written as \
+             sis_term_id:<id>",
+        );
+    }
+
+    #[test]
+    fn id_ref_display_escapes_sis_ids() {
+        assert_eq!(IdRef::Id(CourseId(42)).to_string(), "42");
+        let sis: IdRef<CourseId> = IdRef::Sis {
+            prefix: "sis_course_id",
+            id: "BIO 101/A:é~_.-".to_string(),
+        };
+        assert_eq!(
+            sis.to_string(),
+            "sis_course_id:BIO%20101%2FA%3A%C3%A9~_.-",
+        );
+    }
+
+    #[test]
+    fn ids_read_as_numbers_or_strings() {
+        let ids: Vec<UserId> = serde_json::from_str("[7, \"8\"]").unwrap();
+        assert_eq!(ids, vec![UserId(7), UserId(8)]);
+        assert!(serde_json::from_str::<UserId>("\"x\"").is_err());
+    }
+}
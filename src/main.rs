@@ -1,27 +1,122 @@
+mod activity;
+mod announcements;
+mod archive;
+mod calendar;
 mod canvas;
+mod chart;
 mod cli;
 mod config;
+mod discussions;
+mod distribution;
+mod download;
 mod error;
+mod graph;
+mod groups;
 mod http;
+mod id;
+mod ical;
 mod logging;
+mod missing;
+mod modules;
+mod network;
+mod observer;
+mod quizzes;
+mod output;
+mod report;
+mod scores;
+mod section;
+mod sis;
+mod state;
+mod term;
+mod terminal;
+mod workload;
 
-use futures::future::try_join_all;
 use partial_application::partial;
 
 #[tokio::main]
 async fn main() -> Result<(), error::AppError> {
-    let config = config::config_load("canvas-instructure-graph")
+    let mut config = config::config_load("canvas-instructure-graph")
         .and_then(cli::cli_validate)?;
-    let courses = canvas::courses(&config).await?;
-    let enrollment_futures = courses
-        .into_iter()
-        .map(|c| { canvas::enrollments_by_course(&config, c.id) });
-    let enrollments = try_join_all(enrollment_futures)
-        .await?
-        .into_iter()
-        .flatten()
-        .collect::<Vec<canvas::Enrollment>>()
-    ;
+    config.enrollment_query.sections =
+        section::sections_resolved(&config).await;
+    let selector = match config.student_selector.clone() {
+        Some(selector) => selector,
+        None => return run(&config).await,
+    };
+    let students = observer::students(&config, &selector).await?;
+    let several = students.len() > 1;
+    for student in students {
+        log::info!("Running for {} ({}).", student.name, student.id);
+        config.student = Some(student.id);
+        config.observing = student.observed;
+        config.output_label = if several {
+            Some(observer::file_label(&student))
+        } else {
+            None
+        };
+        run(&config).await?;
+    }
+    Ok(())
+}
+
+async fn run(config: &cli::CliValid) -> Result<(), error::AppError> {
+    match &config.command {
+        cli::Command::Enrollments => enrollments_print(config).await,
+        cli::Command::Graph(cli::GraphCommand::Scores(args)) => {
+            scores::graph(config, args).await
+        },
+        cli::Command::Graph(cli::GraphCommand::Network(args)) => {
+            network::graph(config, args).await
+        },
+        cli::Command::Graph(cli::GraphCommand::Modules(args)) => {
+            modules::graph(config, args).await
+        },
+        cli::Command::Graph(cli::GraphCommand::Activity(args)) => {
+            activity::graph(config, args).await
+        },
+        cli::Command::Graph(cli::GraphCommand::Distribution(args)) => {
+            distribution::graph(config, args).await
+        },
+        cli::Command::Graph(cli::GraphCommand::Workload(args)) => {
+            workload::graph(config, args).await
+        },
+        cli::Command::Graph(cli::GraphCommand::Quizzes(args)) => {
+            quizzes::graph(config, args).await
+        },
+        cli::Command::Graph(cli::GraphCommand::Discussions(args)) => {
+            discussions::graph(config, args).await
+        },
+        cli::Command::Export(cli::ExportCommand::Ical(args)) => {
+            calendar::export(config, args).await
+        },
+        cli::Command::Calendar(args) => calendar::list(config, args).await,
+        cli::Command::Report(args) => report::report(config, args).await,
+        cli::Command::Missing(args) => missing::list(config, args).await,
+        cli::Command::Quizzes(args) => quizzes::list(config, args).await,
+        cli::Command::Discussions(args) => {
+            discussions::list(config, args).await
+        },
+        cli::Command::Groups(args) => groups::list(config, args).await,
+        cli::Command::Announcements(args) => {
+            announcements::digest(config, args).await
+        },
+        cli::Command::Download(args) => download::download(config, args).await,
+        cli::Command::Archive(args) => archive::archive(config, args).await,
+    }
+}
+
+async fn enrollments_print(
+    config: &cli::CliValid,
+) -> Result<(), error::AppError> {
+    let (courses, _terms) = term::courses_selected(
+        config,
+        &config.course_query,
+    ).await?;
+    let enrollments = canvas::enrollments_by_courses(
+        config,
+        &courses,
+        &config.enrollment_query,
+    ).await?;
     println!("{:#?}", enrollments);
     Ok(())
 }
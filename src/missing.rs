@@ -0,0 +1,370 @@
+use clap::ArgEnum;
+use futures::future::try_join_all;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::canvas;
+use crate::cli;
+use crate::error;
+use crate::id;
+use crate::output;
+use crate::report;
+use crate::term;
+
+/// The formats the list of missing and late work can be written as.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum MissingFormat {
+    // Aligned columns for reading in a terminal.
+    Text,
+    Csv,
+    Json,
+    // A standalone page, styled like the reports.
+    Html,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingStatus {
+    // Past due and never turned in.
+    Missing,
+    // Turned in after it was due.
+    Late,
+    // Graded, but given no points.
+    Zero,
+}
+
+impl MissingStatus {
+    fn label(&self) -> &'static str {
+        match self {
+            MissingStatus::Missing => "missing",
+            MissingStatus::Late => "late",
+            MissingStatus::Zero => "zero",
+        }
+    }
+}
+
+/// A piece of work which is costing the caller points.
+#[derive(Debug, Serialize)]
+pub struct MissingItem {
+    pub course: String,
+    pub assignment: String,
+    pub url: String,
+    pub due_at: Option<chrono::DateTime<chrono::Local>>,
+    pub points_possible: Option<f64>,
+    pub score: Option<f64>,
+    pub status: MissingStatus,
+    // Roughly how many percentage points of the course grade the work is
+    // costing, going by the points not earned on it.
+    pub impact: f64,
+}
+
+/// List the missing, late and zero scored work across your active courses,
+/// the work costing the most of a course grade first.
+pub async fn list(
+    config: &cli::CliValid,
+    args: &cli::MissingArgs,
+) -> Result<(), error::AppError> {
+    let mut query = config.course_query.clone();
+    query.enrollment_state = query.enrollment_state
+        .or(Some(canvas::CourseEnrollmentState::Active));
+    let (courses, _terms) = term::courses_selected(config, &query).await?;
+    let assignments = canvas::assignments_by_courses(
+        config,
+        &courses,
+    ).await?;
+    // Group weights only matter to courses which use them.
+    let group_futures = courses
+        .iter()
+        .filter(|c| c.apply_assignment_group_weights)
+        .map(|c| canvas::assignment_groups(config, c.id));
+    let groups = try_join_all(group_futures)
+        .await?
+        .into_iter()
+        .flatten()
+        .map(|g| (g.id, g))
+        .collect::<HashMap<id::AssignmentGroupId, canvas::AssignmentGroup>>();
+    let mut items = courses
+        .iter()
+        .flat_map(|course| {
+            let course_assignments = assignments
+                .iter()
+                .filter(|a| a.course_id == course.id)
+                .collect::<Vec<&canvas::Assignment>>();
+            course_items(course, &course_assignments, &groups)
+        })
+        .filter(|i| {
+            args.statuses.is_empty() || args.statuses.contains(&i.status)
+        })
+        .collect::<Vec<MissingItem>>();
+    items.sort_by(|a, b| {
+        b.impact
+            .partial_cmp(&a.impact)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.due_at.cmp(&b.due_at))
+    });
+    let text = match args.output {
+        MissingFormat::Text => to_text(&items),
+        MissingFormat::Csv => to_csv(&items),
+        MissingFormat::Json => {
+            serde_json::to_string_pretty(&items).unwrap_or_default() + "\n"
+        },
+        MissingFormat::Html => to_html(&items, chrono::Local::now()),
+    };
+    output::write(config, args.file.as_deref(), &text)
+}
+
+fn course_items(
+    course: &canvas::Course,
+    assignments: &[&canvas::Assignment],
+    groups: &HashMap<id::AssignmentGroupId, canvas::AssignmentGroup>,
+) -> Vec<MissingItem> {
+    let counted = assignments
+        .iter()
+        .filter(|a| !a.omit_from_final_grade.unwrap_or(false))
+        .filter(|a| a.points_possible.unwrap_or(0.0) > 0.0)
+        .collect::<Vec<&&canvas::Assignment>>();
+    let points_in = |group_id: Option<id::AssignmentGroupId>| {
+        counted
+            .iter()
+            .filter(|a| group_id.is_none() || a.assignment_group_id == group_id)
+            .filter_map(|a| a.points_possible)
+            .sum::<f64>()
+    };
+    // The share of the course grade each point of an assignment is worth.
+    // Weighted courses split the grade between groups, and each group
+    // between its points; everything else is just points over total points.
+    let point_value = |assignment: &canvas::Assignment| {
+        if assignment.omit_from_final_grade.unwrap_or(false) {
+            return 0.0;
+        }
+        let (share, points) = if course.apply_assignment_group_weights {
+            let weight = assignment.assignment_group_id
+                .and_then(|id| groups.get(&id))
+                .and_then(|g| g.group_weight)
+                .unwrap_or(0.0);
+            (weight, points_in(assignment.assignment_group_id))
+        } else {
+            (100.0, points_in(None))
+        };
+        if points > 0.0 {
+            share / points
+        } else {
+            0.0
+        }
+    };
+    assignments
+        .iter()
+        .filter_map(|assignment| {
+            let submission = assignment.submission.as_ref()?;
+            let points = assignment.points_possible.unwrap_or(0.0);
+            let status = submission_status(submission, points)?;
+            let lost = match submission.score {
+                Some(score) => (points - score).max(0.0),
+                None if status == MissingStatus::Missing => points,
+                // Late work which isn't graded yet hasn't cost anything.
+                None => 0.0,
+            };
+            Some(MissingItem {
+                course: course.name.clone(),
+                assignment: assignment.name.clone(),
+                url: assignment.html_url.clone(),
+                due_at: assignment.due_at,
+                points_possible: assignment.points_possible,
+                score: submission.score,
+                status,
+                impact: lost * point_value(assignment),
+            })
+        })
+        .collect()
+}
+
+// Canvas flags missing and late work itself, going by the due date and the
+// course's late policy. Excused work never counts against anyone.
+fn submission_status(
+    submission: &canvas::Submission,
+    points_possible: f64,
+) -> Option<MissingStatus> {
+    if submission.excused.unwrap_or(false) {
+        None
+    } else if submission.missing.unwrap_or(false) {
+        Some(MissingStatus::Missing)
+    } else if submission.score == Some(0.0) && points_possible > 0.0 {
+        Some(MissingStatus::Zero)
+    } else if submission.late.unwrap_or(false) {
+        Some(MissingStatus::Late)
+    } else {
+        None
+    }
+}
+
+fn to_text(items: &[MissingItem]) -> String {
+    if items.is_empty() {
+        return "No missing, late or zero scored work.\n".to_string();
+    }
+    let course_width = items
+        .iter()
+        .map(|i| i.course.chars().count())
+        .chain(std::iter::once("Course".len()))
+        .max()
+        .unwrap_or(0);
+    let assignment_width = items
+        .iter()
+        .map(|i| i.assignment.chars().count())
+        .chain(std::iter::once("Assignment".len()))
+        .max()
+        .unwrap_or(0);
+    let line = |columns: [&str; 7]| {
+        format!(
+            "{:>7}  {:<7}  {:<20}  {:>6}  {:<cw$}  {:<aw$}  {}",
+            columns[0],
+            columns[1],
+            columns[2],
+            columns[3],
+            columns[4],
+            columns[5],
+            columns[6],
+            cw = course_width,
+            aw = assignment_width,
+        )
+        .trim_end()
+        .to_string()
+            + "\n"
+    };
+    let mut text = line([
+        "Impact",
+        "Status",
+        "Due",
+        "Points",
+        "Course",
+        "Assignment",
+        "Link",
+    ]);
+    for item in items {
+        text.push_str(&line([
+            &format!("{:.2}%", item.impact),
+            item.status.label(),
+            &due_label(item),
+            &points_label(item),
+            &item.course,
+            &item.assignment,
+            &item.url,
+        ]));
+    }
+    text
+}
+
+fn to_csv(items: &[MissingItem]) -> String {
+    let mut text = "course,assignment,status,due_at,points_possible,score,\
+                    impact,url\n"
+        .to_string();
+    for item in items {
+        let fields = [
+            item.course.clone(),
+            item.assignment.clone(),
+            item.status.label().to_string(),
+            item.due_at.map(|at| at.to_rfc3339()).unwrap_or_default(),
+            item.points_possible.map(|p| p.to_string()).unwrap_or_default(),
+            item.score.map(|s| s.to_string()).unwrap_or_default(),
+            format!("{:.4}", item.impact),
+            item.url.clone(),
+        ];
+        text.push_str(
+            &fields
+                .iter()
+                .map(|f| csv_field(f))
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+        text.push('\n');
+    }
+    text
+}
+
+// Quoted only when it has to be, as RFC 4180 describes.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn to_html(
+    items: &[MissingItem],
+    now: chrono::DateTime<chrono::Local>,
+) -> String {
+    let rows = items
+        .iter()
+        .map(|item| {
+            vec![
+                format!("{:.2}%", item.impact),
+                item.status.label().to_string(),
+                report::escape(&due_label(item)),
+                points_label(item),
+                report::escape(&item.course),
+                format!(
+                    "<a href=\"{}\">{}</a>",
+                    report::escape(&item.url),
+                    report::escape(&item.assignment),
+                ),
+            ]
+        })
+        .collect::<Vec<Vec<String>>>();
+    report::page(
+        "Missing and late work",
+        &format!(
+            "Missing, late and zero scored work as of {}, costing the most \
+             first.",
+            now.format("%Y-%m-%d %H:%M"),
+        ),
+        &[report::section(
+            "Work",
+            &report::table(
+                &["Impact", "Status", "Due", "Points", "Course", "Assignment"],
+                &rows,
+                "No missing, late or zero scored work.",
+            ),
+        )],
+    )
+}
+
+fn due_label(item: &MissingItem) -> String {
+    item.due_at
+        .map(|at| at.format("%a %Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "No due date".to_string())
+}
+
+fn points_label(item: &MissingItem) -> String {
+    match (item.score, item.points_possible) {
+        (Some(score), Some(points)) => format!("{}/{}", score, points),
+        (None, Some(points)) => format!("-/{}", points),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_field_plain_as_is() {
+        assert_eq!(csv_field("Lab report 2"), "Lab report 2");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn csv_field_quotes_commas() {
+        assert_eq!(csv_field("Essay, draft"), "\"Essay, draft\"");
+    }
+
+    #[test]
+    fn csv_field_doubles_quotes() {
+        assert_eq!(csv_field("The \"big\" one"), "\"The \"\"big\"\" one\"");
+    }
+
+    #[test]
+    fn csv_field_quotes_line_breaks() {
+        assert_eq!(csv_field("Part 1\nPart 2"), "\"Part 1\nPart 2\"");
+        assert_eq!(csv_field("Part 1\r\nPart 2"), "\"Part 1\r\nPart 2\"");
+    }
+}
@@ -0,0 +1,410 @@
+use clap::ArgEnum;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::canvas;
+use crate::chart;
+use crate::cli;
+use crate::error;
+use crate::graph;
+use crate::id;
+use crate::output;
+
+/// The module graph can go out as a graph for other tools, or be drawn
+/// directly.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ModulesFormat {
+    Dot,
+    Graphml,
+    Json,
+    Png,
+    Svg,
+    Terminal,
+}
+
+/// A module as it appears in the prerequisite graph, with the completion
+/// state of whoever it was fetched for.
+struct ModuleNode {
+    id: id::ModuleId,
+    name: String,
+    state: Option<String>,
+    requirements: usize,
+    requirements_completed: usize,
+    // How many prerequisite steps the module is from a module with none. Used
+    // to lay the graph out left to right.
+    layer: usize,
+}
+
+pub struct ModulesChart {
+    modules: Vec<ModuleNode>,
+    // Prerequisite module id to the module it unlocks.
+    edges: Vec<(id::ModuleId, id::ModuleId)>,
+}
+
+pub async fn graph(
+    config: &cli::CliValid,
+    args: &cli::ModulesArgs,
+) -> Result<(), error::AppError> {
+    let modules = canvas::modules(
+        config,
+        args.course.clone(),
+        config.student,
+    ).await?;
+    modules_warn(&modules);
+    let modules_chart = modules_chart(&modules);
+    match args.output {
+        ModulesFormat::Dot => output::write(
+            config,
+            args.file.as_deref(),
+            &graph::to_dot(&modules_graph(&modules_chart)),
+        ),
+        ModulesFormat::Graphml => output::write(
+            config,
+            args.file.as_deref(),
+            &graph::to_graphml(&modules_graph(&modules_chart)),
+        ),
+        ModulesFormat::Json => output::write(
+            config,
+            args.file.as_deref(),
+            &graph::to_node_link_json(&modules_graph(&modules_chart)),
+        ),
+        ModulesFormat::Png | ModulesFormat::Svg | ModulesFormat::Terminal => {
+            let format = match args.output {
+                ModulesFormat::Png => chart::ChartFormat::Png,
+                ModulesFormat::Svg => chart::ChartFormat::Svg,
+                _ => chart::ChartFormat::Terminal,
+            };
+            chart::render(
+                &cli::chart_output(
+                    config,
+                    &cli::ChartArgs {
+                        output: format,
+                        file: args.file.clone(),
+                        width: args.width,
+                        height: args.height,
+                    },
+                    "modules",
+                ),
+                &modules_chart,
+            )
+        },
+    }
+}
+
+/// Warn about modules no student could ever get to: ones which require a
+/// module that doesn't exist, and ones caught up in (or stuck behind) a
+/// prerequisite cycle.
+pub fn modules_warn(modules: &[canvas::Module]) {
+    let ids = modules.iter().map(|m| m.id).collect::<Vec<id::ModuleId>>();
+    for module in modules {
+        for missing in module.prerequisite_module_ids
+            .iter()
+            .filter(|id| !ids.contains(id)) {
+            log::warn!(
+                "Module '{}' requires module {}, which is not in the course.",
+                module.name,
+                missing,
+            );
+        }
+    }
+    let layers = module_layers(modules);
+    for module in modules.iter().filter(|m| !layers.contains_key(&m.id)) {
+        log::warn!(
+            "Module '{}' can never be unlocked: it is part of or depends upon \
+             a prerequisite cycle.",
+            module.name,
+        );
+    }
+}
+
+fn modules_chart(modules: &[canvas::Module]) -> ModulesChart {
+    let layers = module_layers(modules);
+    // Modules stuck in a cycle go after everything else so they still show.
+    let last_layer = layers.values().max().map(|l| l + 1).unwrap_or(0);
+    let ids = modules.iter().map(|m| m.id).collect::<Vec<id::ModuleId>>();
+    ModulesChart {
+        modules: modules
+            .iter()
+            .map(|m| {
+                let requirements = m.items
+                    .iter()
+                    .flatten()
+                    .filter_map(|i| i.completion_requirement.as_ref())
+                    .collect::<Vec<&canvas::CompletionRequirement>>();
+                ModuleNode {
+                    id: m.id,
+                    name: m.name.clone(),
+                    state: m.state.clone(),
+                    requirements: requirements.len(),
+                    requirements_completed: requirements
+                        .iter()
+                        .filter(|r| r.completed == Some(true))
+                        .count(),
+                    layer: *layers.get(&m.id).unwrap_or(&last_layer),
+                }
+            })
+            .collect(),
+        edges: modules
+            .iter()
+            .flat_map(|m| {
+                m.prerequisite_module_ids
+                    .iter()
+                    .filter(|p| ids.contains(p))
+                    .map(move |p| (*p, m.id))
+            })
+            .collect(),
+    }
+}
+
+fn modules_graph(chart: &ModulesChart) -> graph::Graph {
+    let mut modules_graph = graph::Graph::new("modules", true);
+    for module in &chart.modules {
+        let mut attributes = graph::Attributes::new();
+        if let Some(state) = &module.state {
+            attributes.insert(
+                "state".to_string(),
+                graph::Attribute::Text(state.clone()),
+            );
+        }
+        attributes.insert(
+            "requirements".to_string(),
+            graph::Attribute::Number(module.requirements as f64),
+        );
+        attributes.insert(
+            "requirements_completed".to_string(),
+            graph::Attribute::Number(module.requirements_completed as f64),
+        );
+        modules_graph.add_node(graph::Node {
+            id: format!("module_{}", module.id),
+            kind: graph::NodeKind::Module,
+            label: module_label(module).join("\n"),
+            cluster: None,
+            attributes,
+        });
+    }
+    for (from, to) in &chart.edges {
+        modules_graph.edges.push(graph::Edge {
+            source: format!("module_{}", from),
+            target: format!("module_{}", to),
+            label: None,
+            attributes: graph::Attributes::new(),
+        });
+    }
+    modules_graph
+}
+
+fn module_label(module: &ModuleNode) -> Vec<String> {
+    let mut lines = vec![module.name.clone()];
+    let mut status = vec![];
+    if let Some(state) = &module.state {
+        status.push(state.clone());
+    }
+    if module.requirements > 0 {
+        status.push(format!(
+            "{}/{} requirements",
+            module.requirements_completed,
+            module.requirements,
+        ));
+    }
+    if !status.is_empty() {
+        lines.push(format!("({})", status.join(", ")));
+    }
+    lines
+}
+
+// Kahn's algorithm, tracking how deep each module sits. Modules that never
+// make it out are in or behind a cycle, and are left out of the result. A
+// prerequisite listed twice is still only waited on once.
+fn module_layers(modules: &[canvas::Module]) -> HashMap<id::ModuleId, usize> {
+    let ids = modules.iter().map(|m| m.id).collect::<Vec<id::ModuleId>>();
+    let mut remaining = modules
+        .iter()
+        .map(|m| {
+            (
+                m.id,
+                m.prerequisite_module_ids
+                    .iter()
+                    .filter(|p| ids.contains(p))
+                    .collect::<BTreeSet<&id::ModuleId>>()
+                    .len(),
+            )
+        })
+        .collect::<BTreeMap<id::ModuleId, usize>>();
+    let mut layers = HashMap::new();
+    let mut ready = remaining
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(id, _)| (*id, 0))
+        .collect::<Vec<(id::ModuleId, usize)>>();
+    while let Some((id, layer)) = ready.pop() {
+        layers.insert(id, layer);
+        for module in modules
+            .iter()
+            .filter(|m| m.prerequisite_module_ids.contains(&id)) {
+            if let Some(count) = remaining.get_mut(&module.id) {
+                *count -= 1;
+                if *count == 0 {
+                    // A module sits one past the deepest of its prerequisites.
+                    let deepest = module.prerequisite_module_ids
+                        .iter()
+                        .filter_map(|p| layers.get(p))
+                        .max()
+                        .copied()
+                        .unwrap_or(layer);
+                    ready.push((module.id, deepest + 1));
+                }
+            }
+        }
+    }
+    layers
+}
+
+fn state_color(state: Option<&str>) -> RGBColor {
+    match state {
+        Some("completed") => RGBColor(0x8f, 0xd1, 0x8f),
+        Some("started") => RGBColor(0xff, 0xd9, 0x80),
+        Some("unlocked") => RGBColor(0xa8, 0xcf, 0xf0),
+        Some("locked") => RGBColor(0xcc, 0xcc, 0xcc),
+        _ => WHITE,
+    }
+}
+
+impl chart::Chart for ModulesChart {
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        root.fill(&WHITE)?;
+        let (width, height) = root.dim_in_pixel();
+        let layer_count = self.modules
+            .iter()
+            .map(|m| m.layer + 1)
+            .max()
+            .unwrap_or(1);
+        let column_width = width as i32 / layer_count as i32;
+        let box_width = column_width * 3 / 4;
+        let box_height = 44;
+        // Lay each layer out top to bottom, in course order.
+        let mut positions = HashMap::new();
+        for layer in 0..layer_count {
+            let column = self.modules
+                .iter()
+                .filter(|m| m.layer == layer)
+                .collect::<Vec<&ModuleNode>>();
+            let row_height = height as i32 / (column.len() as i32 + 1);
+            for (row, module) in column.iter().enumerate() {
+                let x = layer as i32 * column_width
+                    + (column_width - box_width) / 2;
+                let y = row_height * (row as i32 + 1) - box_height / 2;
+                positions.insert(module.id, (x, y));
+            }
+        }
+        for (from, to) in &self.edges {
+            if let (Some((fx, fy)), Some((tx, ty))) =
+                (positions.get(from), positions.get(to)) {
+                let start = (fx + box_width, fy + box_height / 2);
+                let end = (*tx, ty + box_height / 2);
+                root.draw(&PathElement::new(vec![start, end], BLACK))?;
+                root.draw(&Polygon::new(
+                    vec![end, (end.0 - 8, end.1 - 4), (end.0 - 8, end.1 + 4)],
+                    BLACK.filled(),
+                ))?;
+            }
+        }
+        for module in &self.modules {
+            if let Some((x, y)) = positions.get(&module.id) {
+                root.draw(&Rectangle::new(
+                    [(*x, *y), (x + box_width, y + box_height)],
+                    state_color(module.state.as_deref()).filled(),
+                ))?;
+                root.draw(&Rectangle::new(
+                    [(*x, *y), (x + box_width, y + box_height)],
+                    BLACK,
+                ))?;
+                for (i, line) in module_label(module).iter().enumerate() {
+                    root.draw(&Text::new(
+                        line.clone(),
+                        (x + 6, y + 6 + i as i32 * 16),
+                        ("sans-serif", 14),
+                    ))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(id: u64, prerequisites: &[u64]) -> canvas::Module {
+        canvas::Module {
+            id: id::ModuleId(id),
+            workflow_state: None,
+            position: id,
+            name: format!("Module {}", id),
+            unlock_at: None,
+            require_sequential_progress: None,
+            prerequisite_module_ids: prerequisites
+                .iter()
+                .map(|p| id::ModuleId(*p))
+                .collect(),
+            items_count: 0,
+            items_url: String::new(),
+            items: None,
+            state: None,
+            completed_at: None,
+            publish_final_grade: None,
+            published: None,
+        }
+    }
+
+    fn layer(
+        layers: &HashMap<id::ModuleId, usize>,
+        id: u64,
+    ) -> Option<usize> {
+        layers.get(&id::ModuleId(id)).copied()
+    }
+
+    #[test]
+    fn layers_follow_deepest_prerequisite() {
+        let layers = module_layers(&[
+            module(1, &[]),
+            module(2, &[1]),
+            module(3, &[1, 2]),
+            module(4, &[]),
+        ]);
+        assert_eq!(layer(&layers, 1), Some(0));
+        assert_eq!(layer(&layers, 2), Some(1));
+        assert_eq!(layer(&layers, 3), Some(2));
+        assert_eq!(layer(&layers, 4), Some(0));
+    }
+
+    #[test]
+    fn duplicate_prerequisite_waited_on_once() {
+        let layers = module_layers(&[module(1, &[]), module(2, &[1, 1])]);
+        assert_eq!(layer(&layers, 2), Some(1));
+    }
+
+    #[test]
+    fn missing_prerequisite_ignored() {
+        let layers = module_layers(&[module(2, &[9])]);
+        assert_eq!(layer(&layers, 2), Some(0));
+    }
+
+    #[test]
+    fn cycle_and_what_follows_left_out() {
+        let layers = module_layers(&[
+            module(1, &[]),
+            module(2, &[1, 3]),
+            module(3, &[2]),
+            module(4, &[3]),
+        ]);
+        assert_eq!(layer(&layers, 1), Some(0));
+        assert_eq!(layer(&layers, 2), None);
+        assert_eq!(layer(&layers, 3), None);
+        assert_eq!(layer(&layers, 4), None);
+    }
+}
@@ -0,0 +1,314 @@
+use clap::ArgEnum;
+use futures::future::join_all;
+use std::collections::HashMap;
+
+use crate::canvas;
+use crate::cli;
+use crate::error;
+use crate::graph;
+use crate::groups;
+use crate::id;
+use crate::output;
+use crate::term;
+
+/// What course nodes can be boxed up by in the network graph.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Cluster {
+    Term,
+    Section,
+}
+
+pub async fn graph(
+    config: &cli::CliValid,
+    args: &cli::NetworkArgs,
+) -> Result<(), error::AppError> {
+    let (courses, terms) = term::courses_selected(
+        config,
+        &config.course_query,
+    ).await?;
+    // Let Canvas do the role type filtering where it can.
+    let mut query = config.enrollment_query.clone();
+    for t in &args.role_types {
+        if !query.types.contains(t) {
+            query.types.push(*t);
+        }
+    }
+    let enrollments = canvas::enrollments_by_courses(
+        config,
+        &courses,
+        &query,
+    ).await?;
+    let sections = match args.cluster_by {
+        Some(Cluster::Section) => section_names(config, &courses).await,
+        _ => HashMap::new(),
+    };
+    let mut course_groups = vec![];
+    if args.groups {
+        for course in &courses {
+            course_groups.extend(
+                groups::course_groups(config, course.id).await,
+            );
+        }
+    }
+    let mut network = network_graph(
+        &courses,
+        &terms,
+        &sections,
+        &enrollments,
+        args.cluster_by,
+        &args.role_types,
+    );
+    add_groups(&mut network, &course_groups);
+    output::write(
+        config,
+        args.file.as_deref(),
+        &graph::render(&network, args.output),
+    )
+}
+
+// Sections are only named when Canvas will list them, which it won't always
+// for students, so the rest go by their ids.
+async fn section_names(
+    config: &cli::CliValid,
+    courses: &[canvas::Course],
+) -> HashMap<id::SectionId, String> {
+    let section_futures = courses
+        .iter()
+        .map(|c| canvas::sections(config, c.id, &[]));
+    let course_sections = join_all(section_futures).await;
+    let mut names = HashMap::new();
+    for (course, sections) in courses.iter().zip(course_sections) {
+        match sections {
+            Ok(sections) => {
+                names.extend(sections.into_iter().map(|s| (s.id, s.name)));
+            },
+            Err(e) => log::info!(
+                "Could not list the sections of course {}: {:?}",
+                course.id,
+                e,
+            ),
+        }
+    }
+    names
+}
+
+/// The bipartite graph of people and the courses they're enrolled in. Edges
+/// carry the enrollment role. When clustering by section, users connect to
+/// their section rather than directly to the course.
+pub fn network_graph(
+    courses: &[canvas::Course],
+    terms: &HashMap<id::TermId, canvas::EnrollmentTerm>,
+    sections: &HashMap<id::SectionId, String>,
+    enrollments: &[canvas::Enrollment],
+    cluster_by: Option<Cluster>,
+    role_types: &[canvas::EnrollmentType],
+) -> graph::Graph {
+    let mut network = graph::Graph::new("canvas", false);
+    for course in courses {
+        let cluster = match cluster_by {
+            Some(Cluster::Term) => {
+                let id = format!("term_{}", course.enrollment_term_id);
+                network.clusters.insert(
+                    id.clone(),
+                    term::term_label(terms, course.enrollment_term_id),
+                );
+                Some(id)
+            },
+            Some(Cluster::Section) => {
+                let id = course_node_id(course.id);
+                network.clusters.insert(id.clone(), course.name.clone());
+                Some(id)
+            },
+            None => None,
+        };
+        network.add_node(graph::Node {
+            id: course_node_id(course.id),
+            kind: graph::NodeKind::Course,
+            label: course.name.clone(),
+            cluster,
+            attributes: course_attributes(course, terms),
+        });
+    }
+    let role_names = role_types
+        .iter()
+        .map(|t| t.canvas_name())
+        .collect::<Vec<String>>();
+    for enrollment in enrollments
+        .iter()
+        .filter(|e| role_names.is_empty() || role_names.contains(&e.type_)) {
+        let user_id = format!("user_{}", enrollment.user_id);
+        network.add_node(graph::Node {
+            id: user_id.clone(),
+            kind: graph::NodeKind::User,
+            label: enrollment.user.name.clone(),
+            cluster: None,
+            attributes: user_attributes(&enrollment.user),
+        });
+        let target = match cluster_by {
+            Some(Cluster::Section) => {
+                let section_id = format!(
+                    "section_{}",
+                    enrollment.course_section_id,
+                );
+                if !network.has_node(&section_id) {
+                    network.add_node(graph::Node {
+                        id: section_id.clone(),
+                        kind: graph::NodeKind::Section,
+                        label: sections
+                            .get(&enrollment.course_section_id)
+                            .cloned()
+                            .unwrap_or_else(|| format!(
+                                "Section {}",
+                                enrollment.course_section_id,
+                            )),
+                        cluster: Some(course_node_id(enrollment.course_id)),
+                        attributes: graph::Attributes::new(),
+                    });
+                    network.edges.push(graph::Edge {
+                        source: course_node_id(enrollment.course_id),
+                        target: section_id.clone(),
+                        label: None,
+                        attributes: graph::Attributes::new(),
+                    });
+                }
+                section_id
+            },
+            _ => course_node_id(enrollment.course_id),
+        };
+        network.edges.push(graph::Edge {
+            source: user_id,
+            target,
+            label: Some(enrollment.role.clone()),
+            attributes: enrollment_attributes(enrollment),
+        });
+    }
+    network
+}
+
+/// Add groups to a network graph, each hanging off of its course and
+/// connected to its members. Members not already in the graph, because their
+/// enrollments were filtered out, are added too.
+pub fn add_groups(
+    network: &mut graph::Graph,
+    groups: &[groups::CourseGroup],
+) {
+    for g in groups {
+        let group_id = format!("group_{}", g.group.id);
+        let course_id = course_node_id(g.course_id);
+        // Groups go in whatever cluster their course is drawn in.
+        let cluster = network.nodes
+            .iter()
+            .find(|n| n.id == course_id)
+            .and_then(|n| n.cluster.clone());
+        network.add_node(graph::Node {
+            id: group_id.clone(),
+            kind: graph::NodeKind::Group,
+            label: g.group.name.clone(),
+            cluster,
+            attributes: group_attributes(g),
+        });
+        network.edges.push(graph::Edge {
+            source: course_id,
+            target: group_id.clone(),
+            label: g.category.clone(),
+            attributes: graph::Attributes::new(),
+        });
+        for user in &g.members {
+            let user_id = format!("user_{}", user.id);
+            network.add_node(graph::Node {
+                id: user_id.clone(),
+                kind: graph::NodeKind::User,
+                label: user.name.clone(),
+                cluster: None,
+                attributes: user_attributes(user),
+            });
+            network.edges.push(graph::Edge {
+                source: user_id,
+                target: group_id.clone(),
+                label: Some("member".to_string()),
+                attributes: graph::Attributes::new(),
+            });
+        }
+    }
+}
+
+fn group_attributes(group: &groups::CourseGroup) -> graph::Attributes {
+    let mut attributes = graph::Attributes::new();
+    if let Some(category) = &group.category {
+        attributes.insert("category".to_string(), text(category));
+    }
+    attributes.insert(
+        "members_count".to_string(),
+        graph::Attribute::Number(group.group.members_count as f64),
+    );
+    if let Some(join_level) = &group.group.join_level {
+        attributes.insert("join_level".to_string(), text(join_level));
+    }
+    attributes
+}
+
+fn course_attributes(
+    course: &canvas::Course,
+    terms: &HashMap<id::TermId, canvas::EnrollmentTerm>,
+) -> graph::Attributes {
+    let mut attributes = graph::Attributes::new();
+    attributes.insert("course_code".to_string(), text(&course.course_code));
+    attributes.insert(
+        "term".to_string(),
+        text(&term::term_label(terms, course.enrollment_term_id)),
+    );
+    attributes.insert(
+        "workflow_state".to_string(),
+        text(&course.workflow_state),
+    );
+    attributes
+}
+
+fn user_attributes(user: &canvas::User) -> graph::Attributes {
+    let mut attributes = graph::Attributes::new();
+    attributes.insert("sortable_name".to_string(), text(&user.sortable_name));
+    if let Some(login_id) = &user.login_id {
+        attributes.insert("login_id".to_string(), text(login_id));
+    }
+    attributes
+}
+
+// Most of what's interesting about a person in a course lives on their
+// enrollment, so it goes on the edge rather than either node.
+fn enrollment_attributes(
+    enrollment: &canvas::Enrollment,
+) -> graph::Attributes {
+    let mut attributes = graph::Attributes::new();
+    attributes.insert("type".to_string(), text(&enrollment.type_));
+    attributes.insert(
+        "enrollment_state".to_string(),
+        text(&enrollment.enrollment_state),
+    );
+    attributes.insert(
+        "total_activity_time".to_string(),
+        graph::Attribute::Number(enrollment.total_activity_time as f64),
+    );
+    if let Some(last_activity_at) = enrollment.last_activity_at {
+        attributes.insert(
+            "last_activity_at".to_string(),
+            text(&last_activity_at.to_rfc3339()),
+        );
+    }
+    if let Some(score) = enrollment.grades
+        .as_ref()
+        .and_then(|g| g.current_score) {
+        attributes.insert(
+            "current_score".to_string(),
+            graph::Attribute::Number(score),
+        );
+    }
+    attributes
+}
+
+fn text(s: &str) -> graph::Attribute {
+    graph::Attribute::Text(s.to_string())
+}
+
+fn course_node_id(course_id: id::CourseId) -> String {
+    format!("course_{}", course_id)
+}
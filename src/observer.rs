@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::canvas;
+use crate::cli;
+use crate::error;
+use crate::id;
+use crate::output;
+use crate::sis;
+
+/// How a student is picked out on the command line: by id or SIS id, by
+/// name, or every student the caller observes.
+#[derive(Clone, Debug)]
+pub enum StudentSelector {
+    All,
+    Id(id::IdRef<id::UserId>),
+    Name(String),
+}
+
+impl FromStr for StudentSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("all") {
+            Ok(StudentSelector::All)
+        } else {
+            Ok(
+                s.parse::<id::IdRef<id::UserId>>()
+                    .map(StudentSelector::Id)
+                    .unwrap_or_else(|_| StudentSelector::Name(s.to_string()))
+            )
+        }
+    }
+}
+
+/// A student to run a command for, in place of the caller.
+#[derive(Clone, Debug)]
+pub struct Student {
+    pub id: id::UserId,
+    pub name: String,
+    // Whether the caller is one of the student's observers, rather than, say,
+    // their teacher.
+    pub observed: bool,
+}
+
+/// The students a selector picks out. Names and "all" are looked up among
+/// the students the caller observes.
+pub async fn students(
+    config: &cli::CliValid,
+    selector: &StudentSelector,
+) -> Result<Vec<Student>, error::AppError> {
+    let name = match selector {
+        // Teachers can pick out anyone in their courses by id, so an id is
+        // taken as it is rather than checked against the observees.
+        StudentSelector::Id(id::IdRef::Id(id)) => {
+            return Ok(vec![Student {
+                id: *id,
+                name: id.to_string(),
+                observed: observee_ids(config).await.contains(id),
+            }]);
+        },
+        StudentSelector::Id(user_ref) => {
+            return student_by_sis_id(config, user_ref).await.map(|s| vec![s]);
+        },
+        StudentSelector::All => None,
+        StudentSelector::Name(name) => Some(name),
+    };
+    let observees = canvas::observees(config).await?;
+    let selected = observees
+        .iter()
+        .filter(|u| name.map(|n| user_named(u, n)).unwrap_or(true))
+        .map(|u| Student {
+            id: u.id,
+            name: u.short_name.clone().unwrap_or_else(|| u.name.clone()),
+            observed: true,
+        })
+        .collect::<Vec<Student>>();
+    match (name, selected.len()) {
+        (None, 0) => Err(error::AppError::CliStudentSelectError(
+            "You don't observe any students.".to_string(),
+        )),
+        (Some(name), 0) => Err(error::AppError::CliStudentSelectError(
+            format!("You don't observe a student named '{}'.", name),
+        )),
+        (Some(name), n) if n > 1 => Err(
+            error::AppError::CliStudentSelectError(format!(
+                "More than one student you observe is named '{}', so give \
+                 an id instead: {}.",
+                name,
+                selected
+                    .iter()
+                    .map(|s| s.id.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            )),
+        ),
+        _ => Ok(selected),
+    }
+}
+
+// Canvas looks users up by SIS id itself, where the caller can see their
+// profile. Failing that, the student has to be found among the observees and
+// the enrollments in the caller's courses, which carry SIS ids of their own.
+async fn student_by_sis_id(
+    config: &cli::CliValid,
+    user_ref: &id::IdRef<id::UserId>,
+) -> Result<Student, error::AppError> {
+    match canvas::profile(config, Some(user_ref.clone())).await {
+        Ok(profile) => {
+            return Ok(Student {
+                id: profile.id,
+                name: profile.short_name.unwrap_or(profile.name),
+                observed: observee_ids(config).await.contains(&profile.id),
+            });
+        },
+        Err(e) => log::info!(
+            "Could not look up {} through Canvas, searching enrollments \
+             instead: {:?}",
+            user_ref,
+            e,
+        ),
+    }
+    let observees = canvas::observees(config).await?;
+    if let Some(u) = observees.iter().find(|u| sis::user_matches(u, user_ref)) {
+        return Ok(Student {
+            id: u.id,
+            name: u.short_name.clone().unwrap_or_else(|| u.name.clone()),
+            observed: true,
+        });
+    }
+    let courses = canvas::courses(config, &config.course_query).await?;
+    let enrollments = canvas::enrollments_by_courses(
+        config,
+        &courses,
+        &canvas::EnrollmentQuery::default(),
+    ).await?;
+    enrollments
+        .iter()
+        .find(|e| sis::enrollment_user_matches(e, user_ref))
+        .map(|e| Student {
+            id: e.user_id,
+            name: e.user.short_name.clone()
+                .unwrap_or_else(|| e.user.name.clone()),
+            observed: false,
+        })
+        .ok_or_else(|| error::AppError::CliStudentSelectError(
+            format!("Couldn't find a student with {}.", user_ref),
+        ))
+}
+
+// The students the caller observes, or nobody if Canvas won't say.
+async fn observee_ids(config: &cli::CliValid) -> HashSet<id::UserId> {
+    match canvas::observees(config).await {
+        Ok(observees) => observees.into_iter().map(|u| u.id).collect(),
+        Err(e) => {
+            log::info!("Could not list the students you observe: {:?}", e);
+            HashSet::new()
+        },
+    }
+}
+
+// Any of the names Canvas keeps will do, ignoring case.
+fn user_named(user: &canvas::User, name: &str) -> bool {
+    [
+        Some(&user.name),
+        user.short_name.as_ref(),
+        Some(&user.sortable_name),
+        user.login_id.as_ref(),
+    ]
+    .iter()
+    .flatten()
+    .any(|n| n.eq_ignore_ascii_case(name))
+}
+
+/// A student's name cut down to something which can go in a file name, or
+/// their id if nothing of the name is left.
+pub fn file_label(student: &Student) -> String {
+    let label = output::slug(&student.name);
+    if label.is_empty() {
+        student.id.to_string()
+    } else {
+        label
+    }
+}
+
+/// Where to write an output file. Running for several students one after
+/// another, each student's label goes on the end of the file name so nobody
+/// overwrites anybody else.
+pub fn output_path(config: &cli::CliValid, path: &Path) -> PathBuf {
+    let label = match &config.output_label {
+        Some(label) => label,
+        None => return path.to_path_buf(),
+    };
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!(
+            "{}-{}.{}",
+            stem,
+            label,
+            extension.to_string_lossy(),
+        ),
+        None => format!("{}-{}", stem, label),
+    };
+    path.with_file_name(name)
+}
@@ -0,0 +1,93 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+use crate::cli;
+use crate::error;
+use crate::observer;
+
+/// Write text output either to the given file or, without one, to stdout.
+/// Running for several students, stdout would run each student's output into
+/// the next, so a file is required.
+pub fn write(
+    config: &cli::CliValid,
+    path: Option<&Path>,
+    text: &str,
+) -> Result<(), error::AppError> {
+    match path.map(|p| observer::output_path(config, p)) {
+        Some(p) => {
+            log::info!("Writing output to {:?}.", p);
+            fs::write(&p, text).map_err(error::AppError::OutputWriteError)
+        },
+        None if config.output_label.is_some() => {
+            Err(error::AppError::OutputStdoutError(
+                "Running for several students, give --file so each \
+                 student's output goes to a file of its own."
+                    .to_string(),
+            ))
+        },
+        None => {
+            print!("{}", text);
+            Ok(())
+        },
+    }
+}
+
+/// Rough plain text for the HTML Canvas keeps descriptions and messages in,
+/// for places which can't show markup.
+pub fn html_to_text(html: &str) -> String {
+    lazy_static! {
+        static ref BREAK: Regex = Regex::new(r"(?i)<br\s*/?>|</p>|</li>|</h\d>")
+            .unwrap();
+        static ref TAG: Regex = Regex::new(r"<[^>]*>").unwrap();
+        static ref BLANK_LINES: Regex = Regex::new(r"\n\s*\n\s*\n+").unwrap();
+    }
+    let text = BREAK.replace_all(html, "\n");
+    let text = TAG.replace_all(&text, "");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    BLANK_LINES.replace_all(text.trim(), "\n\n").to_string()
+}
+
+/// A lowercase, dash separated form of a name for use in file names, or
+/// nothing if the name has no letters or digits at all.
+pub fn slug(name: &str) -> String {
+    name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slug_lowercases_and_dashes() {
+        assert_eq!(slug("Week 3: Essays & Drafts"), "week-3-essays-drafts");
+    }
+
+    #[test]
+    fn slug_trims_separators() {
+        assert_eq!(slug("  --Lab 1--  "), "lab-1");
+    }
+
+    #[test]
+    fn slug_keeps_other_alphabets() {
+        assert_eq!(slug("Été Général"), "été-général");
+    }
+
+    #[test]
+    fn slug_of_punctuation_is_empty() {
+        assert_eq!(slug("../.."), "");
+        assert_eq!(slug(""), "");
+    }
+}
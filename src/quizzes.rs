@@ -0,0 +1,687 @@
+use futures::future::join_all;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::canvas;
+use crate::chart;
+use crate::cli;
+use crate::error;
+use crate::id;
+use crate::section;
+use crate::sis;
+use crate::term;
+
+// A classic quiz or a New Quiz, along with how the caller has done on it so
+// far.
+struct QuizRow {
+    course: String,
+    title: String,
+    kind: &'static str,
+    // In minutes.
+    time_limit: Option<u64>,
+    // None when there's no limit.
+    allowed_attempts: Option<u64>,
+    attempts: Option<u64>,
+    score: Option<f64>,
+    points_possible: Option<f64>,
+    due_at: Option<chrono::DateTime<chrono::Local>>,
+    // The class's mean score as a percentage, for those who teach it.
+    average: Option<f64>,
+}
+
+// Every scored attempt at a quiz, oldest first.
+struct QuizAttempts {
+    name: String,
+    // When each attempt was turned in, and its score as a percentage.
+    attempts: Vec<(chrono::NaiveDateTime, f64)>,
+}
+
+/// The caller's score on each attempt at their quizzes, over time, so retakes
+/// can be compared against each other.
+pub struct QuizAttemptsChart {
+    quizzes: Vec<QuizAttempts>,
+    // Midnight on the first day shown.
+    start: chrono::NaiveDateTime,
+    days: f64,
+}
+
+/// List the classic quizzes and New Quizzes across your courses, with their
+/// time limits, allowed attempts and your scores.
+pub async fn list(
+    config: &cli::CliValid,
+    args: &cli::QuizzesArgs,
+) -> Result<(), error::AppError> {
+    let (courses, _terms) = term::courses_selected(
+        config,
+        &config.course_query,
+    ).await?;
+    let courses = sis::courses_selected(
+        config,
+        courses,
+        &args.courses,
+    ).await?;
+    let mut rows = vec![];
+    for course in &courses {
+        rows.extend(course_quizzes(config, course).await);
+    }
+    rows.sort_by(|a, b| {
+        a.course.cmp(&b.course).then_with(|| a.due_at.cmp(&b.due_at))
+    });
+    let averages = rows.iter().any(|r| r.average.is_some());
+    println!("{}", quiz_line(&QUIZ_HEADERS, averages));
+    for row in rows {
+        println!("{}", quiz_line(&quiz_columns(&row), averages));
+    }
+    Ok(())
+}
+
+pub async fn graph(
+    config: &cli::CliValid,
+    args: &cli::QuizAttemptsArgs,
+) -> Result<(), error::AppError> {
+    let (courses, _terms) = term::courses_selected(
+        config,
+        &config.course_query,
+    ).await?;
+    let courses = sis::courses_selected(
+        config,
+        courses,
+        &args.courses,
+    ).await?;
+    let mut quizzes = vec![];
+    for course in &courses {
+        quizzes.extend(course_attempts(config, course).await);
+    }
+    chart::render(
+        &cli::chart_output(config, &args.chart, "quizzes"),
+        &quiz_attempts_chart(quizzes, chrono::Local::now()),
+    )
+}
+
+async fn course_quizzes(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+) -> Vec<QuizRow> {
+    let teaching = course.teaching();
+    // Graded quizzes of either kind are backed by assignments, whose
+    // submissions say how many attempts have been used and what score was
+    // kept. Without them the quizzes are still worth listing.
+    let submissions = canvas::assignments(config, course.id)
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!(
+                "Could not list assignments for course {}: {:?}",
+                course.id,
+                e,
+            );
+            vec![]
+        })
+        .into_iter()
+        .filter_map(|a| Some((a.id, a.submission?)))
+        .collect::<HashMap<id::AssignmentId, canvas::Submission>>();
+    // Whose scores the class average is over: the whole class, or only the
+    // students in the given sections. Without it there's no average.
+    let class = if teaching {
+        section::students_selected(config, course.id)
+            .await
+            .map_err(|e| {
+                log::warn!(
+                    "Could not list the sections of course {}: {:?}",
+                    course.id,
+                    e,
+                )
+            })
+            .ok()
+    } else {
+        None
+    };
+    let quizzes = classic_quizzes(config, course).await;
+    let averages = match &class {
+        Some(students) => {
+            let average_futures = quizzes
+                .iter()
+                .map(|q| {
+                    class_average(config, course.id, q, students.as_ref())
+                });
+            join_all(average_futures).await
+        },
+        None => vec![None; quizzes.len()],
+    };
+    let mut rows = vec![];
+    for (quiz, average) in quizzes.into_iter().zip(averages) {
+        let submission = quiz.assignment_id.and_then(|id| submissions.get(&id));
+        let (attempts, score) = match submission {
+            Some(s) => (s.attempt, s.score),
+            // Practice quizzes have no assignment, so go to the quiz's own
+            // submissions. Those only ever cover the caller though.
+            None if !teaching && config.student.is_none() => {
+                latest_quiz_submission(config, course.id, quiz.id)
+                    .await
+                    .map(|s| (s.attempt, s.kept_score.or(s.score)))
+                    .unwrap_or((None, None))
+            },
+            None => (None, None),
+        };
+        rows.push(QuizRow {
+            course: course.name.clone(),
+            title: quiz.title.clone(),
+            kind: classic_kind(&quiz.quiz_type),
+            time_limit: quiz.time_limit,
+            allowed_attempts: classic_allowed_attempts(quiz.allowed_attempts),
+            attempts,
+            score,
+            points_possible: quiz.points_possible,
+            due_at: quiz.due_at,
+            average,
+        });
+    }
+    for quiz in new_quizzes(config, course).await {
+        let submission = submissions.get(&quiz.id);
+        let settings = quiz.quiz_settings.as_ref();
+        rows.push(QuizRow {
+            course: course.name.clone(),
+            title: quiz.title.clone(),
+            kind: "new quiz",
+            time_limit: settings
+                .filter(|s| s.has_time_limit.unwrap_or(false))
+                .and_then(|s| s.session_time_limit_in_seconds)
+                .map(|seconds| seconds / 60),
+            allowed_attempts: new_quiz_allowed_attempts(
+                settings.and_then(|s| s.multiple_attempts.as_ref()),
+            ),
+            attempts: submission.and_then(|s| s.attempt),
+            score: submission.and_then(|s| s.score),
+            points_possible: quiz.points_possible,
+            due_at: quiz.due_at,
+            average: None,
+        });
+    }
+    rows
+}
+
+// How many attempts a classic quiz allows, or None for unlimited, which
+// Canvas writes as -1.
+fn classic_allowed_attempts(allowed_attempts: Option<i64>) -> Option<u64> {
+    match allowed_attempts {
+        Some(n) if n < 0 => None,
+        Some(n) => Some(n as u64),
+        None => Some(1),
+    }
+}
+
+// How many attempts a New Quiz allows, or None for unlimited.
+fn new_quiz_allowed_attempts(
+    multiple: Option<&canvas::NewQuizMultipleAttempts>,
+) -> Option<u64> {
+    match multiple {
+        Some(m) if m.multiple_attempts_enabled.unwrap_or(false) => {
+            if m.attempt_limit.unwrap_or(false) {
+                m.max_attempts
+            } else {
+                None
+            }
+        },
+        _ => Some(1),
+    }
+}
+
+// Courses with the quizzes tab hidden refuse to list them to students, which
+// shouldn't sink every other course.
+async fn classic_quizzes(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+) -> Vec<canvas::Quiz> {
+    canvas::quizzes(config, course.id)
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!(
+                "Could not list quizzes for course {}: {:?}",
+                course.id,
+                e,
+            );
+            vec![]
+        })
+}
+
+// Plenty of instances don't have New Quizzes turned on at all, so not being
+// able to list them is nothing to warn about.
+async fn new_quizzes(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+) -> Vec<canvas::NewQuiz> {
+    canvas::new_quizzes(config, course.id)
+        .await
+        .unwrap_or_else(|e| {
+            log::info!(
+                "Could not list New Quizzes for course {}: {:?}",
+                course.id,
+                e,
+            );
+            vec![]
+        })
+}
+
+async fn latest_quiz_submission(
+    config: &cli::CliValid,
+    course_id: id::CourseId,
+    quiz_id: id::QuizId,
+) -> Option<canvas::QuizSubmission> {
+    canvas::quiz_submissions(config, course_id, quiz_id)
+        .await
+        .map_err(|e| {
+            log::info!(
+                "Could not get submissions for quiz {}: {:?}",
+                quiz_id,
+                e,
+            )
+        })
+        .ok()?
+        .into_iter()
+        .max_by_key(|s| s.attempt)
+}
+
+// The mean score on a quiz as a percentage. The quiz's statistics cover the
+// whole class, so an average over only some students is worked out from
+// their submissions instead.
+async fn class_average(
+    config: &cli::CliValid,
+    course_id: id::CourseId,
+    quiz: &canvas::Quiz,
+    students: Option<&HashSet<id::UserId>>,
+) -> Option<f64> {
+    let points = quiz.points_possible.filter(|p| *p > 0.0)?;
+    if let Some(students) = students {
+        let scores = canvas::quiz_submissions(config, course_id, quiz.id)
+            .await
+            .map_err(|e| {
+                log::info!(
+                    "Could not get submissions for quiz {}: {:?}",
+                    quiz.id,
+                    e,
+                )
+            })
+            .ok()?
+            .into_iter()
+            .filter(|s| {
+                s.user_id.map(|u| students.contains(&u)).unwrap_or(false)
+            })
+            .filter_map(|s| s.kept_score.or(s.score))
+            .collect::<Vec<f64>>();
+        if scores.is_empty() {
+            return None;
+        }
+        let average = scores.iter().sum::<f64>() / scores.len() as f64;
+        return Some(100.0 * average / points);
+    }
+    let statistics = canvas::quiz_statistics(config, course_id, quiz.id)
+        .await
+        .map_err(|e| {
+            log::info!(
+                "Could not get statistics for quiz {}: {:?}",
+                quiz.id,
+                e,
+            )
+        })
+        .ok()??;
+    let average = statistics.submission_statistics?.score_average?;
+    Some(100.0 * average / points)
+}
+
+// Every scored attempt at the graded quizzes in a course. Quizzes nobody can
+// see the history of are left out with a warning.
+async fn course_attempts(
+    config: &cli::CliValid,
+    course: &canvas::Course,
+) -> Vec<QuizAttempts> {
+    let mut quizzes = classic_quizzes(config, course)
+        .await
+        .into_iter()
+        .filter_map(|q| Some((q.assignment_id?, q.title, q.points_possible)))
+        .collect::<Vec<(id::AssignmentId, String, Option<f64>)>>();
+    quizzes.extend(
+        new_quizzes(config, course)
+            .await
+            .into_iter()
+            .map(|q| (q.id, q.title, q.points_possible)),
+    );
+    let history_futures = quizzes
+        .iter()
+        .map(|(id, _, _)| {
+            canvas::submission_history(config, course.id, *id, None)
+        });
+    let histories = join_all(history_futures).await;
+    quizzes
+        .into_iter()
+        .zip(histories)
+        .filter_map(|((id, name, points), history)| {
+            let history = history
+                .map_err(|e| {
+                    log::warn!(
+                        "Could not get the attempts at quiz {} in course \
+                         {}: {:?}",
+                        id,
+                        course.id,
+                        e,
+                    )
+                })
+                .ok()?;
+            let points = points.filter(|p| *p > 0.0)?;
+            let mut attempts = history
+                .iter()
+                .filter_map(|s| {
+                    Some((
+                        s.submitted_at?.naive_local(),
+                        100.0 * s.score? / points,
+                    ))
+                })
+                .collect::<Vec<(chrono::NaiveDateTime, f64)>>();
+            attempts.sort_by_key(|(at, _)| *at);
+            if attempts.is_empty() {
+                None
+            } else {
+                Some(QuizAttempts {
+                    name: format!("{}: {}", course.course_code, name),
+                    attempts,
+                })
+            }
+        })
+        .collect()
+}
+
+fn quiz_attempts_chart(
+    quizzes: Vec<QuizAttempts>,
+    now: chrono::DateTime<chrono::Local>,
+) -> QuizAttemptsChart {
+    let times = quizzes
+        .iter()
+        .flat_map(|q| q.attempts.iter().map(|(at, _)| *at))
+        .collect::<Vec<chrono::NaiveDateTime>>();
+    // Without any attempts, show the last few weeks so there's still an axis
+    // to look at.
+    let first = times
+        .iter()
+        .min()
+        .map(|at| at.date())
+        .unwrap_or_else(|| {
+            now.naive_local().date() - chrono::Duration::weeks(4)
+        });
+    let last = times
+        .iter()
+        .max()
+        .map(|at| at.date())
+        .unwrap_or_else(|| now.naive_local().date());
+    QuizAttemptsChart {
+        quizzes,
+        start: first.and_hms(0, 0, 0),
+        days: ((last - first).num_days() + 1) as f64,
+    }
+}
+
+impl chart::Chart for QuizAttemptsChart {
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        root.fill(&WHITE)?;
+        // Extra credit can push a score past 100.
+        let y_max = self.quizzes
+            .iter()
+            .flat_map(|q| q.attempts.iter().map(|(_, score)| *score))
+            .fold(100.0, f64::max);
+        let mut chart = ChartBuilder::on(root)
+            .caption("Quiz scores by attempt", ("sans-serif", 24))
+            .margin(10)
+            .margin_right(30)
+            .x_label_area_size(40)
+            .y_label_area_size(50)
+            .build_cartesian_2d(0.0..self.days, 0.0..y_max)?;
+        let day_label = |day: &f64| {
+            (self.start + chrono::Duration::hours((day * 24.0) as i64))
+                .format("%b %d")
+                .to_string()
+        };
+        chart.configure_mesh()
+            .x_labels(self.days.min(6.0) as usize + 1)
+            .x_label_formatter(&day_label)
+            .y_desc("Score (%)")
+            .draw()?;
+        for (i, quiz) in self.quizzes.iter().enumerate() {
+            let color = chart::series_color(i);
+            let points = quiz.attempts
+                .iter()
+                .map(|(at, score)| (self.day_of(at), *score))
+                .collect::<Vec<(f64, f64)>>();
+            chart.draw_series(LineSeries::new(
+                points.clone(),
+                color.stroke_width(2),
+            ))?
+                .label(chart::truncate(&quiz.name, 40))
+                .legend(move |(x, y)| {
+                    Rectangle::new(
+                        [(x, y - 5), (x + 10, y + 5)],
+                        chart::series_color(i).filled(),
+                    )
+                });
+            chart.draw_series(
+                points
+                    .into_iter()
+                    .map(|point| Circle::new(point, 4, color.filled())),
+            )?;
+        }
+        if !self.quizzes.is_empty() {
+            chart.configure_series_labels()
+                .position(SeriesLabelPosition::LowerRight)
+                .background_style(WHITE.mix(0.8))
+                .border_style(BLACK)
+                .draw()?;
+        }
+        Ok(())
+    }
+}
+
+impl QuizAttemptsChart {
+    fn day_of(&self, at: &chrono::NaiveDateTime) -> f64 {
+        (*at - self.start).num_minutes() as f64 / (24.0 * 60.0)
+    }
+}
+
+const QUIZ_HEADERS: [&str; 8] = [
+    "Course",
+    "Quiz",
+    "Kind",
+    "Time limit",
+    "Attempts",
+    "Score",
+    "Due",
+    "Average",
+];
+
+fn quiz_columns(row: &QuizRow) -> [String; 8] {
+    [
+        chart::truncate(&row.course, 20),
+        chart::truncate(&row.title, 30),
+        row.kind.to_string(),
+        row.time_limit
+            .map(|minutes| format!("{} min", minutes))
+            .unwrap_or_else(|| "none".to_string()),
+        format!(
+            "{}/{}",
+            row.attempts.unwrap_or(0),
+            row.allowed_attempts
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| "unlimited".to_string()),
+        ),
+        match (row.score, row.points_possible) {
+            (Some(score), Some(points)) => format!("{}/{}", score, points),
+            (None, Some(points)) => format!("-/{}", points),
+            (Some(score), None) => score.to_string(),
+            (None, None) => "-".to_string(),
+        },
+        row.due_at
+            .map(|at| at.format("%a %Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "No due date".to_string()),
+        row.average
+            .map(|average| format!("{:.1}%", average))
+            .unwrap_or_default(),
+    ]
+}
+
+// The class average only gets a column when there's one to show.
+fn quiz_line<S: AsRef<str>>(columns: &[S; 8], averages: bool) -> String {
+    let line = format!(
+        "{:<20}  {:<30}  {:<13}  {:<10}  {:<11}  {:<9}  {:<20}",
+        columns[0].as_ref(),
+        columns[1].as_ref(),
+        columns[2].as_ref(),
+        columns[3].as_ref(),
+        columns[4].as_ref(),
+        columns[5].as_ref(),
+        columns[6].as_ref(),
+    );
+    if averages {
+        format!("{}  {}", line, columns[7].as_ref()).trim_end().to_string()
+    } else {
+        line.trim_end().to_string()
+    }
+}
+
+fn classic_kind(quiz_type: &str) -> &'static str {
+    match quiz_type {
+        "assignment" => "graded",
+        "practice_quiz" => "practice",
+        "graded_survey" => "graded survey",
+        "survey" => "survey",
+        _ => "quiz",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn row() -> QuizRow {
+        QuizRow {
+            course: "Biology 101".to_string(),
+            title: "Quiz 3".to_string(),
+            kind: "graded",
+            time_limit: Some(20),
+            allowed_attempts: Some(3),
+            attempts: Some(1),
+            score: Some(8.0),
+            points_possible: Some(10.0),
+            due_at: Some(chrono::Local.ymd(2021, 10, 22).and_hms(23, 59, 0)),
+            average: None,
+        }
+    }
+
+    fn multiple(
+        enabled: Option<bool>,
+        limit: Option<bool>,
+        max: Option<u64>,
+    ) -> canvas::NewQuizMultipleAttempts {
+        canvas::NewQuizMultipleAttempts {
+            multiple_attempts_enabled: enabled,
+            attempt_limit: limit,
+            max_attempts: max,
+            score_to_keep: None,
+        }
+    }
+
+    #[test]
+    fn classic_allowed_attempts_mapping() {
+        assert_eq!(classic_allowed_attempts(Some(-1)), None);
+        assert_eq!(classic_allowed_attempts(Some(3)), Some(3));
+        assert_eq!(classic_allowed_attempts(None), Some(1));
+    }
+
+    #[test]
+    fn new_quiz_allowed_attempts_mapping() {
+        assert_eq!(new_quiz_allowed_attempts(None), Some(1));
+        let single = multiple(Some(false), None, None);
+        assert_eq!(new_quiz_allowed_attempts(Some(&single)), Some(1));
+        let unlimited = multiple(Some(true), Some(false), Some(5));
+        assert_eq!(new_quiz_allowed_attempts(Some(&unlimited)), None);
+        let limited = multiple(Some(true), Some(true), Some(5));
+        assert_eq!(new_quiz_allowed_attempts(Some(&limited)), Some(5));
+    }
+
+    #[test]
+    fn classic_kinds() {
+        assert_eq!(classic_kind("assignment"), "graded");
+        assert_eq!(classic_kind("practice_quiz"), "practice");
+        assert_eq!(classic_kind("graded_survey"), "graded survey");
+        assert_eq!(classic_kind("survey"), "survey");
+        assert_eq!(classic_kind("something_new"), "quiz");
+    }
+
+    #[test]
+    fn quiz_columns_formatting() {
+        let columns = quiz_columns(&row());
+        assert_eq!(columns[3], "20 min");
+        assert_eq!(columns[4], "1/3");
+        assert_eq!(columns[5], "8/10");
+        assert_eq!(columns[6], "Fri 2021-10-22 23:59");
+        assert_eq!(columns[7], "");
+        let columns = quiz_columns(&QuizRow {
+            time_limit: None,
+            allowed_attempts: None,
+            attempts: None,
+            score: None,
+            due_at: None,
+            average: Some(72.54),
+            ..row()
+        });
+        assert_eq!(columns[3], "none");
+        assert_eq!(columns[4], "0/unlimited");
+        assert_eq!(columns[5], "-/10");
+        assert_eq!(columns[6], "No due date");
+        assert_eq!(columns[7], "72.5%");
+    }
+
+    #[test]
+    fn quiz_line_average_column_only_when_present() {
+        let without = quiz_line(&QUIZ_HEADERS, false);
+        assert!(without.ends_with("Due"));
+        assert!(!without.contains("Average"));
+        let with = quiz_line(&QUIZ_HEADERS, true);
+        assert_eq!(with, format!("{}{}Average", without, " ".repeat(19)));
+        // A row without an average leaves nothing trailing in the column.
+        let columns = quiz_columns(&row());
+        assert_eq!(
+            quiz_line(&columns, true),
+            quiz_line(&columns, false),
+        );
+    }
+
+    #[test]
+    fn attempts_chart_spans_the_attempts() {
+        let at = |day, hour| {
+            chrono::NaiveDate::from_ymd(2021, 10, day).and_hms(hour, 0, 0)
+        };
+        let chart = quiz_attempts_chart(
+            vec![QuizAttempts {
+                name: "Quiz".to_string(),
+                attempts: vec![(at(1, 10), 50.0), (at(3, 18), 90.0)],
+            }],
+            chrono::Local.ymd(2021, 11, 1).and_hms(12, 0, 0),
+        );
+        assert_eq!(chart.start, at(1, 0));
+        assert_eq!(chart.days, 3.0);
+        assert_eq!(chart.day_of(&at(2, 12)), 1.5);
+    }
+
+    #[test]
+    fn attempts_chart_without_attempts_shows_four_weeks() {
+        let chart = quiz_attempts_chart(
+            vec![],
+            chrono::Local.ymd(2021, 11, 1).and_hms(12, 0, 0),
+        );
+        assert_eq!(
+            chart.start,
+            chrono::NaiveDate::from_ymd(2021, 10, 4).and_hms(0, 0, 0),
+        );
+        assert_eq!(chart.days, 29.0);
+    }
+}
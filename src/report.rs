@@ -0,0 +1,540 @@
+use futures::future::try_join_all;
+use std::collections::HashMap;
+
+use crate::canvas;
+use crate::chart;
+use crate::cli;
+use crate::distribution;
+use crate::error;
+use crate::graph;
+use crate::id;
+use crate::output;
+use crate::scores;
+use crate::term;
+use crate::workload;
+
+// Charts are drawn at one size, and shrunk by the page's styles to fit
+// narrower windows.
+const CHART_SIZE: (u32, u32) = (960, 540);
+
+// Everything the page needs is inline, so it reads the same from an email or
+// an archive as it does today. No fonts, scripts or images are fetched.
+const STYLE: &str = "\
+body {
+  color: #222;
+  font-family: -apple-system, \"Segoe UI\", Helvetica, Arial, sans-serif;
+  margin: 2em auto;
+  max-width: 60em;
+  padding: 0 1em;
+}
+header p, p.empty {
+  color: #666;
+}
+table {
+  border-collapse: collapse;
+  margin-bottom: 1em;
+  width: 100%;
+}
+th, td {
+  border-bottom: 1px solid #ddd;
+  padding: 0.4em 0.6em;
+  text-align: left;
+}
+th {
+  background: #f2f2f2;
+}
+tr:nth-child(even) td {
+  background: #fafafa;
+}
+figure {
+  margin: 1em 0;
+}
+figure svg {
+  height: auto;
+  max-width: 100%;
+}
+figcaption {
+  color: #666;
+  font-size: 0.9em;
+}
+@media print {
+  section {
+    break-inside: avoid;
+  }
+}
+";
+
+/// Write a standalone HTML page covering either the caller across their
+/// courses, or everyone in a single course.
+pub async fn report(
+    config: &cli::CliValid,
+    args: &cli::ReportArgs,
+) -> Result<(), error::AppError> {
+    let page = match &args.course {
+        Some(course_id) => {
+            course_report(config, args, course_id.clone()).await?
+        },
+        None => student_report(config, args).await?,
+    };
+    output::write(config, args.file.as_deref(), &page)
+}
+
+async fn student_report(
+    config: &cli::CliValid,
+    args: &cli::ReportArgs,
+) -> Result<String, error::AppError> {
+    let profile = canvas::profile(config, None).await?;
+    let query = config.course_query
+        .clone()
+        .with_include(canvas::CourseInclude::TotalScores);
+    let (courses, terms) = term::courses_selected(config, &query).await?;
+    let course_ids = courses
+        .iter()
+        .map(|c| c.id)
+        .collect::<Vec<id::CourseId>>();
+    let missing = canvas::missing_submissions(
+        config,
+        None,
+        &course_ids,
+    ).await?;
+    let assignments = canvas::assignments_by_courses(
+        config,
+        &courses,
+    ).await?;
+    let course_rows = courses
+        .iter()
+        .map(|c| {
+            // Only a student enrollment carries a score.
+            let enrollment = c.enrollments
+                .iter()
+                .find(|e| e.computed_current_score.is_some());
+            vec![
+                escape(&c.name),
+                escape(&c.course_code),
+                escape(&term::term_label(&terms, c.enrollment_term_id)),
+                score_label(enrollment.and_then(|e| e.computed_current_score)),
+                grade_label(
+                    enrollment.and_then(|e| e.computed_current_grade.as_ref()),
+                ),
+            ]
+        })
+        .collect::<Vec<Vec<String>>>();
+    let codes = courses
+        .iter()
+        .map(|c| (c.id, c.course_code.as_str()))
+        .collect::<HashMap<id::CourseId, &str>>();
+    let missing_rows = missing
+        .iter()
+        .map(|a| {
+            vec![
+                escape(codes.get(&a.course_id).copied().unwrap_or("")),
+                assignment_link(a),
+                due_label(a),
+                points_label(a),
+            ]
+        })
+        .collect::<Vec<Vec<String>>>();
+    let now = chrono::Local::now();
+    Ok(page(
+        &profile.name,
+        &format!(
+            "Courses, scores and missing work as of {}.",
+            now.format("%Y-%m-%d %H:%M"),
+        ),
+        &[
+            section(
+                "Courses",
+                &table(
+                    &["Course", "Code", "Term", "Score", "Grade"],
+                    &course_rows,
+                    "No courses.",
+                ),
+            ),
+            section(
+                "Missing assignments",
+                &table(
+                    &["Course", "Assignment", "Due", "Points"],
+                    &missing_rows,
+                    "Nothing is missing.",
+                ),
+            ),
+            section(
+                "Charts",
+                &[
+                    figure(
+                        "Current score in each course.",
+                        &scores::scores_chart(&courses, &terms, None, None),
+                    ),
+                    figure(
+                        "Work due in the coming weeks, and the points it's \
+                         worth.",
+                        &workload::workload_chart(
+                            &courses,
+                            &assignments,
+                            now,
+                            args.weeks,
+                            workload::WorkloadLayout::Timeline,
+                        ),
+                    ),
+                ].concat(),
+            ),
+        ],
+    ))
+}
+
+async fn course_report(
+    config: &cli::CliValid,
+    args: &cli::ReportArgs,
+    course_id: id::IdRef<id::CourseId>,
+) -> Result<String, error::AppError> {
+    let course = canvas::course(config, course_id).await?;
+    // Teachers get the whole class, or just the student they're running for,
+    // and everyone else just themselves.
+    let teaching = course.teaching();
+    let assignments = canvas::assignments(config, course.id).await?;
+    let names = assignments
+        .iter()
+        .map(|a| (a.id, a))
+        .collect::<HashMap<id::AssignmentId, &canvas::Assignment>>();
+    let (score_rows, missing_rows, submissions) = if teaching {
+        let query = canvas::EnrollmentQuery {
+            types: vec![canvas::EnrollmentType::Student],
+            user_id: config.student.map(id::IdRef::Id),
+            sections: config.enrollment_query.sections.clone(),
+            ..Default::default()
+        };
+        let enrollments = canvas::enrollments_by_course(
+            config,
+            course.id,
+            &query,
+        ).await?;
+        let submission_futures = assignments
+            .iter()
+            .map(|a| canvas::submissions(config, course.id, a.id));
+        let mut submissions = assignments
+            .iter()
+            .map(|a| a.id)
+            .zip(try_join_all(submission_futures).await?)
+            .collect::<HashMap<id::AssignmentId, Vec<canvas::Submission>>>();
+        // Narrowed to sections or to one student, only the work of those
+        // enrolled counts.
+        if !query.sections.is_empty() || query.user_id.is_some() {
+            for assignment_submissions in submissions.values_mut() {
+                assignment_submissions.retain(|s| {
+                    enrollments.iter().any(|e| e.user_id == s.user_id)
+                });
+            }
+        }
+        let missing = submissions
+            .values()
+            .flatten()
+            .filter(|s| s.missing == Some(true))
+            .collect::<Vec<&canvas::Submission>>();
+        let score_rows = enrollments
+            .iter()
+            .map(|e| {
+                let grades = e.grades.as_ref();
+                vec![
+                    escape(&e.user.name),
+                    score_label(grades.and_then(|g| g.current_score)),
+                    grade_label(grades.and_then(|g| g.current_grade.as_ref())),
+                    missing
+                        .iter()
+                        .filter(|s| s.user_id == e.user_id)
+                        .count()
+                        .to_string(),
+                ]
+            })
+            .collect::<Vec<Vec<String>>>();
+        let student_names = enrollments
+            .iter()
+            .map(|e| (e.user_id, e.user.name.as_str()))
+            .collect::<HashMap<id::UserId, &str>>();
+        let mut missed = missing
+            .iter()
+            .filter_map(|s| {
+                let name = student_names.get(&s.user_id).copied()?;
+                Some((name, *names.get(&s.assignment_id)?))
+            })
+            .collect::<Vec<(&str, &canvas::Assignment)>>();
+        missed.sort_by_key(|(name, a)| (*name, a.due_at));
+        let missing_rows = missed
+            .iter()
+            .map(|(name, a)| {
+                vec![
+                    escape(name),
+                    assignment_link(a),
+                    due_label(a),
+                    points_label(a),
+                ]
+            })
+            .collect::<Vec<Vec<String>>>();
+        (score_rows, missing_rows, submissions)
+    } else {
+        let profile = canvas::profile(config, None).await?;
+        let missing = canvas::missing_submissions(
+            config,
+            None,
+            &[course.id],
+        ).await?;
+        let enrollment = course.enrollments
+            .iter()
+            .find(|e| e.computed_current_score.is_some());
+        let score_rows = vec![vec![
+            escape(&profile.name),
+            score_label(enrollment.and_then(|e| e.computed_current_score)),
+            grade_label(
+                enrollment.and_then(|e| e.computed_current_grade.as_ref()),
+            ),
+            missing.len().to_string(),
+        ]];
+        let missing_rows = missing
+            .iter()
+            .map(|a| {
+                vec![
+                    escape(&profile.name),
+                    assignment_link(a),
+                    due_label(a),
+                    points_label(a),
+                ]
+            })
+            .collect::<Vec<Vec<String>>>();
+        (score_rows, missing_rows, HashMap::new())
+    };
+    let terms = course.term
+        .iter()
+        .map(|t| (t.id, t.clone()))
+        .collect::<HashMap<id::TermId, canvas::EnrollmentTerm>>();
+    let course_rows = vec![vec![
+        escape(&course.name),
+        escape(&course.course_code),
+        escape(&term::term_label(&terms, course.enrollment_term_id)),
+        assignments.len().to_string(),
+    ]];
+    let now = chrono::Local::now();
+    Ok(page(
+        &course.name,
+        &format!(
+            "Scores and missing work as of {}.",
+            now.format("%Y-%m-%d %H:%M"),
+        ),
+        &[
+            section(
+                "Course",
+                &table(
+                    &["Course", "Code", "Term", "Assignments"],
+                    &course_rows,
+                    "",
+                ),
+            ),
+            section(
+                "Scores",
+                &table(
+                    &["Student", "Score", "Grade", "Missing"],
+                    &score_rows,
+                    "No students.",
+                ),
+            ),
+            section(
+                "Missing assignments",
+                &table(
+                    &["Student", "Assignment", "Due", "Points"],
+                    &missing_rows,
+                    "Nothing is missing.",
+                ),
+            ),
+            section(
+                "Charts",
+                &[
+                    figure(
+                        "How the class scored, overall and per assignment.",
+                        &distribution::distribution_chart(
+                            course.name.clone(),
+                            &assignments,
+                            &submissions,
+                            if teaching {
+                                distribution::Highlight::Nobody
+                            } else {
+                                distribution::Highlight::Own
+                            },
+                            false,
+                            10,
+                        ),
+                    ),
+                    figure(
+                        "When each assignment opens and is due.",
+                        &workload::workload_chart(
+                            std::slice::from_ref(&course),
+                            &assignments,
+                            now,
+                            args.weeks,
+                            workload::WorkloadLayout::Gantt,
+                        ),
+                    ),
+                ].concat(),
+            ),
+        ],
+    ))
+}
+
+/// A standalone HTML page, styled like the reports, with the given sections.
+pub fn page(title: &str, subtitle: &str, sections: &[String]) -> String {
+    format!(
+        "<!DOCTYPE html>\n\
+         <html lang=\"en\">\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, \
+         initial-scale=1\">\n\
+         <title>{}</title>\n\
+         <style>\n{}</style>\n\
+         </head>\n\
+         <body>\n\
+         <header>\n<h1>{}</h1>\n<p>{}</p>\n</header>\n\
+         {}\
+         </body>\n\
+         </html>\n",
+        escape(title),
+        STYLE,
+        escape(title),
+        escape(subtitle),
+        sections.concat(),
+    )
+}
+
+/// A headed section of a page.
+pub fn section(heading: &str, body: &str) -> String {
+    format!(
+        "<section>\n<h2>{}</h2>\n{}</section>\n",
+        escape(heading),
+        body,
+    )
+}
+
+// Cells are HTML already, so they can hold links.
+pub fn table(headers: &[&str], rows: &[Vec<String>], empty: &str) -> String {
+    if rows.is_empty() {
+        return format!("<p class=\"empty\">{}</p>\n", escape(empty));
+    }
+    let header = headers
+        .iter()
+        .map(|h| format!("<th>{}</th>", escape(h)))
+        .collect::<String>();
+    let body = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "<tr>{}</tr>\n",
+                row.iter()
+                    .map(|cell| format!("<td>{}</td>", cell))
+                    .collect::<String>(),
+            )
+        })
+        .collect::<String>();
+    format!(
+        "<table>\n<thead>\n<tr>{}</tr>\n</thead>\n<tbody>\n{}</tbody>\n\
+         </table>\n",
+        header,
+        body,
+    )
+}
+
+// The chart goes in as inline SVG, so it needs no file of its own. One which
+// fails to draw is left out rather than costing the rest of the report.
+fn figure<C: chart::Chart>(caption: &str, chart: &C) -> String {
+    match chart::render_svg(chart, CHART_SIZE) {
+        Ok(svg) => format!(
+            "<figure>\n{}\n<figcaption>{}</figcaption>\n</figure>\n",
+            svg.trim(),
+            escape(caption),
+        ),
+        Err(e) => {
+            log::warn!("Leaving a chart out of the report: {:?}", e);
+            String::new()
+        },
+    }
+}
+
+pub fn escape(s: &str) -> String {
+    graph::xml_escape(s)
+}
+
+fn assignment_link(assignment: &canvas::Assignment) -> String {
+    format!(
+        "<a href=\"{}\">{}</a>",
+        escape(&assignment.html_url),
+        escape(&assignment.name),
+    )
+}
+
+fn due_label(assignment: &canvas::Assignment) -> String {
+    assignment.due_at
+        .map(|at| at.format("%a %Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "No due date".to_string())
+}
+
+fn points_label(assignment: &canvas::Assignment) -> String {
+    assignment.points_possible
+        .map(|p| format!("{}", p))
+        .unwrap_or_default()
+}
+
+fn score_label(score: Option<f64>) -> String {
+    score
+        .map(|s| format!("{:.1}%", s))
+        .unwrap_or_else(|| "&mdash;".to_string())
+}
+
+fn grade_label(grade: Option<&String>) -> String {
+    grade
+        .map(|g| escape(g))
+        .unwrap_or_else(|| "&mdash;".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_escapes_headers_but_not_cells() {
+        let rows = vec![vec![
+            "<a href=\"https://c.test/a/1\">Essay</a>".to_string(),
+            "90%".to_string(),
+        ]];
+        assert_eq!(
+            table(&["Assignment <name>", "Score & grade"], &rows, "None."),
+            "<table>\n<thead>\n\
+             <tr><th>Assignment &lt;name&gt;</th>\
+             <th>Score &amp; grade</th></tr>\n\
+             </thead>\n<tbody>\n\
+             <tr><td><a href=\"https://c.test/a/1\">Essay</a></td>\
+             <td>90%</td></tr>\n\
+             </tbody>\n</table>\n",
+        );
+    }
+
+    #[test]
+    fn table_without_rows_says_so() {
+        assert_eq!(
+            table(&["Assignment"], &[], "Nothing's \"due\" <yet>."),
+            "<p class=\"empty\">Nothing&apos;s &quot;due&quot; \
+             &lt;yet&gt;.</p>\n",
+        );
+    }
+
+    #[test]
+    fn escape_covers_markup_and_quotes() {
+        assert_eq!(
+            escape("<b>\"A&B\"</b> 'x'"),
+            "&lt;b&gt;&quot;A&amp;B&quot;&lt;/b&gt; &apos;x&apos;",
+        );
+    }
+
+    #[test]
+    fn section_escapes_its_heading_only() {
+        assert_eq!(
+            section("Q&A", "<p>Hi</p>\n"),
+            "<section>\n<h2>Q&amp;A</h2>\n<p>Hi</p>\n</section>\n",
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::HashMap;
+
+use crate::canvas;
+use crate::chart;
+use crate::cli;
+use crate::error;
+use crate::id;
+use crate::term;
+
+// One bar per course the token holder has a score in.
+struct ScoreBar {
+    course: String,
+    term_id: id::TermId,
+    score: f64,
+}
+
+/// The token holder's current score in each course, optionally grouped or
+/// faceted so terms can be compared against each other.
+pub struct ScoresChart {
+    bars: Vec<ScoreBar>,
+    // Term ids in the order they should be shown, along with their names.
+    terms: Vec<(id::TermId, String)>,
+    group_by: Option<chart::Grouping>,
+    facet_by: Option<chart::Grouping>,
+}
+
+pub async fn graph(
+    config: &cli::CliValid,
+    args: &cli::ScoresArgs,
+) -> Result<(), error::AppError> {
+    let query = config.course_query
+        .clone()
+        .with_include(canvas::CourseInclude::TotalScores);
+    let (courses, terms) = term::courses_selected(config, &query).await?;
+    chart::render(
+        &cli::chart_output(config, &args.chart, "scores"),
+        &scores_chart(&courses, &terms, args.group_by, args.facet_by),
+    )
+}
+
+pub fn scores_chart(
+    courses: &[canvas::Course],
+    terms: &HashMap<id::TermId, canvas::EnrollmentTerm>,
+    group_by: Option<chart::Grouping>,
+    facet_by: Option<chart::Grouping>,
+) -> ScoresChart {
+    // Observer and teacher enrollments have no score of their own, so a course
+    // only gets a bar if one of the enrollments in it does.
+    let bars = courses
+        .iter()
+        .filter_map(|c| {
+            c.enrollments
+                .iter()
+                .find_map(|e| e.computed_current_score)
+                .map(|score| ScoreBar {
+                    course: c.course_code.clone(),
+                    term_id: c.enrollment_term_id,
+                    score,
+                })
+        })
+        .collect::<Vec<ScoreBar>>();
+    let term_ids = bars.iter().map(|b| b.term_id).collect::<Vec<id::TermId>>();
+    ScoresChart {
+        terms: term::term_order(terms, &term_ids)
+            .into_iter()
+            .map(|id| (id, term::term_label(terms, id)))
+            .collect(),
+        bars,
+        group_by,
+        facet_by,
+    }
+}
+
+impl chart::Chart for ScoresChart {
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        root.fill(&WHITE)?;
+        match self.facet_by {
+            Some(chart::Grouping::Term) => {
+                let panels = root.split_evenly((1, self.terms.len().max(1)));
+                for (i, (panel, (term_id, name))) in panels
+                    .iter()
+                    .zip(self.terms.iter())
+                    .enumerate() {
+                    let bars = self.bars
+                        .iter()
+                        .filter(|b| b.term_id == *term_id)
+                        .collect::<Vec<&ScoreBar>>();
+                    self.draw_bars(panel, name, &bars, |_| i)?;
+                }
+                Ok(())
+            },
+            None => {
+                // Keep a term's courses next to each other when grouping so
+                // the colors form contiguous blocks.
+                let mut bars = self.bars.iter().collect::<Vec<&ScoreBar>>();
+                if self.group_by == Some(chart::Grouping::Term) {
+                    bars.sort_by_key(|b| self.term_index(b.term_id));
+                }
+                let color = |b: &ScoreBar| match self.group_by {
+                    Some(chart::Grouping::Term) => self.term_index(b.term_id),
+                    None => 0,
+                };
+                self.draw_bars(root, "Current scores", &bars, color)
+            },
+        }
+    }
+}
+
+impl ScoresChart {
+    fn term_index(&self, term_id: id::TermId) -> usize {
+        self.terms
+            .iter()
+            .position(|(id, _)| *id == term_id)
+            .unwrap_or(0)
+    }
+
+    fn draw_bars<DB: DrawingBackend, F: Fn(&ScoreBar) -> usize>(
+        &self,
+        area: &DrawingArea<DB, Shift>,
+        caption: &str,
+        bars: &[&ScoreBar],
+        color: F,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        // Extra credit can push a score past 100.
+        let y_max = bars
+            .iter()
+            .map(|b| b.score)
+            .fold(100.0, f64::max);
+        let mut chart = ChartBuilder::on(area)
+            .caption(caption, ("sans-serif", 24))
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(50)
+            // Plotters treats integer ranges as inclusive when segmenting.
+            .build_cartesian_2d(
+                (0..bars.len().saturating_sub(1)).into_segmented(),
+                0.0..y_max,
+            )?;
+        let label = |v: &SegmentValue<usize>| match v {
+            SegmentValue::CenterOf(i) => bars
+                .get(*i)
+                .map(|b| b.course.clone())
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        chart.configure_mesh()
+            .disable_x_mesh()
+            .x_labels(bars.len().max(1))
+            .x_label_formatter(&label)
+            .y_desc("Score (%)")
+            .draw()?;
+        let grouped = self.group_by.is_some() && self.facet_by.is_none();
+        let mut colors = bars.iter().map(|b| color(b)).collect::<Vec<usize>>();
+        colors.sort_unstable();
+        colors.dedup();
+        for c in colors {
+            let series = chart.draw_series(
+                Histogram::vertical(&chart)
+                    .style(chart::series_color(c).filled())
+                    .margin(10)
+                    .data(
+                        bars.iter()
+                            .enumerate()
+                            .filter(|(_, b)| color(b) == c)
+                            .map(|(i, b)| (i, b.score)),
+                    ),
+            )?;
+            if grouped {
+                if let Some((_, name)) = self.terms.get(c) {
+                    series
+                        .label(name.clone())
+                        .legend(move |(x, y)| {
+                            Rectangle::new(
+                                [(x, y - 5), (x + 10, y + 5)],
+                                chart::series_color(c).filled(),
+                            )
+                        });
+                }
+            }
+        }
+        if grouped {
+            chart.configure_series_labels()
+                .background_style(WHITE.mix(0.8))
+                .border_style(BLACK)
+                .draw()?;
+        }
+        Ok(())
+    }
+}
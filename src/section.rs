@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+
+use crate::canvas;
+use crate::cli;
+use crate::error;
+use crate::id;
+use crate::sis;
+
+/// The sections given on the command line, with SIS ids swapped for Canvas
+/// ids where Canvas will look them up. Sections only carry their SIS ids for
+/// callers allowed to see SIS information, so matching on them is a last
+/// resort.
+pub async fn sections_resolved(
+    config: &cli::CliValid,
+) -> Vec<id::IdRef<id::SectionId>> {
+    let mut resolved = vec![];
+    for section_ref in &config.enrollment_query.sections {
+        if let id::IdRef::Id(_) = section_ref {
+            resolved.push(section_ref.clone());
+            continue;
+        }
+        match canvas::section(config, section_ref.clone()).await {
+            Ok(section) => resolved.push(id::IdRef::Id(section.id)),
+            Err(e) => {
+                log::info!(
+                    "Could not look up section {} through Canvas, matching \
+                     it by SIS id instead: {:?}",
+                    section_ref,
+                    e,
+                );
+                resolved.push(section_ref.clone());
+            },
+        }
+    }
+    resolved
+}
+
+/// A course's sections along with their students, narrowed to the sections
+/// given on the command line if there are any.
+pub async fn sections_selected(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Vec<canvas::Section>, error::AppError> {
+    let selected = &config.enrollment_query.sections;
+    let sections = canvas::sections(
+        config,
+        course_id,
+        &[
+            canvas::SectionInclude::Students,
+            canvas::SectionInclude::TotalStudents,
+        ],
+    ).await?;
+    Ok(
+        sections
+            .into_iter()
+            .filter(|s| {
+                selected.is_empty()
+                    || selected.iter().any(|r| sis::section_matches(s, r))
+            })
+            .collect()
+    )
+}
+
+/// Everyone enrolled as a student in any of the sections.
+pub fn student_ids(sections: &[canvas::Section]) -> HashSet<id::UserId> {
+    sections
+        .iter()
+        .flat_map(|s| s.students.iter().flatten())
+        .map(|u| u.id)
+        .collect()
+}
+
+/// The students in the sections given on the command line, or None if no
+/// sections were given and everyone in the course counts.
+pub async fn students_selected(
+    config: &cli::CliValid,
+    course_id: impl Into<id::IdRef<id::CourseId>>,
+) -> Result<Option<HashSet<id::UserId>>, error::AppError> {
+    if config.enrollment_query.sections.is_empty() {
+        return Ok(None);
+    }
+    let sections = sections_selected(config, course_id).await?;
+    Ok(Some(student_ids(&sections)))
+}
+
+/// A section's name, with how many students it has when Canvas said.
+pub fn section_label(section: &canvas::Section) -> String {
+    match section.total_students {
+        Some(1) => format!("{} (1 student)", section.name),
+        Some(n) => format!("{} ({} students)", section.name, n),
+        None => section.name.clone(),
+    }
+}
@@ -0,0 +1,297 @@
+use crate::canvas;
+use crate::cli;
+use crate::error;
+use crate::id;
+
+// Most endpoints take SIS ids in place of Canvas ids, but lists can't be
+// filtered by them, so the things in a list are matched up against the SIS
+// ids they came with instead. Those only come back for callers allowed to
+// see SIS information, so where Canvas can look an SIS id up itself, that's
+// done first.
+
+/// Whether a course is the one a reference names.
+pub fn course_matches(
+    course: &canvas::Course,
+    course_ref: &id::IdRef<id::CourseId>,
+) -> bool {
+    match course_ref {
+        id::IdRef::Id(id) => course.id == *id,
+        id::IdRef::Sis { id, .. } => {
+            course.sis_course_id.as_deref() == Some(id.as_str())
+        },
+    }
+}
+
+/// Whether a user is the one a reference names.
+pub fn user_matches(
+    user: &canvas::User,
+    user_ref: &id::IdRef<id::UserId>,
+) -> bool {
+    match user_ref {
+        id::IdRef::Id(id) => user.id == *id,
+        id::IdRef::Sis { prefix, id } => {
+            let sis_id = match *prefix {
+                "sis_user_id" => &user.sis_user_id,
+                "sis_login_id" => &user.login_id,
+                "sis_integration_id" => &user.integration_id,
+                _ => return false,
+            };
+            sis_id.as_deref() == Some(id.as_str())
+        },
+    }
+}
+
+/// Whether an enrollment belongs to the user a reference names.
+pub fn enrollment_user_matches(
+    enrollment: &canvas::Enrollment,
+    user_ref: &id::IdRef<id::UserId>,
+) -> bool {
+    match user_ref {
+        id::IdRef::Id(id) => enrollment.user_id == *id,
+        id::IdRef::Sis { prefix: "sis_user_id", id } => {
+            enrollment.sis_user_id.as_deref() == Some(id.as_str())
+                || user_matches(&enrollment.user, user_ref)
+        },
+        id::IdRef::Sis { .. } => user_matches(&enrollment.user, user_ref),
+    }
+}
+
+/// Narrow courses down to the ones given on the command line, where giving
+/// none means every course. Giving one which isn't among the courses is an
+/// error, rather than nothing to show.
+pub async fn courses_selected(
+    config: &cli::CliValid,
+    courses: Vec<canvas::Course>,
+    course_refs: &[id::IdRef<id::CourseId>],
+) -> Result<Vec<canvas::Course>, error::AppError> {
+    if course_refs.is_empty() {
+        return Ok(courses);
+    }
+    let mut ids = vec![];
+    for course_ref in course_refs {
+        let id = course_id(config, course_ref, &courses).await?;
+        if !courses.iter().any(|c| c.id == id) {
+            return Err(error::AppError::CliCourseSelectError(format!(
+                "Course {} isn't among the courses selected.",
+                course_ref,
+            )));
+        }
+        ids.push(id);
+    }
+    Ok(courses.into_iter().filter(|c| ids.contains(&c.id)).collect())
+}
+
+// Canvas looks courses up by SIS id itself, for anyone who can see the
+// course. Failing that, the courses we have may carry SIS ids of their own.
+async fn course_id(
+    config: &cli::CliValid,
+    course_ref: &id::IdRef<id::CourseId>,
+    courses: &[canvas::Course],
+) -> Result<id::CourseId, error::AppError> {
+    if let id::IdRef::Id(id) = course_ref {
+        return Ok(*id);
+    }
+    match canvas::course(config, course_ref.clone()).await {
+        Ok(course) => return Ok(course.id),
+        Err(e) => log::info!(
+            "Could not look up course {} through Canvas, searching your \
+             courses instead: {:?}",
+            course_ref,
+            e,
+        ),
+    }
+    courses
+        .iter()
+        .find(|c| course_matches(c, course_ref))
+        .map(|c| c.id)
+        .ok_or_else(|| error::AppError::CliCourseSelectError(
+            format!("Couldn't find a course with {}.", course_ref),
+        ))
+}
+
+/// Whether a section is the one a reference names.
+pub fn section_matches(
+    section: &canvas::Section,
+    section_ref: &id::IdRef<id::SectionId>,
+) -> bool {
+    match section_ref {
+        id::IdRef::Id(id) => section.id == *id,
+        id::IdRef::Sis { id, .. } => {
+            section.sis_section_id.as_deref() == Some(id.as_str())
+        },
+    }
+}
+
+/// Whether an enrollment is in the section a reference names.
+pub fn enrollment_section_matches(
+    enrollment: &canvas::Enrollment,
+    section_ref: &id::IdRef<id::SectionId>,
+) -> bool {
+    match section_ref {
+        id::IdRef::Id(id) => enrollment.course_section_id == *id,
+        id::IdRef::Sis { id, .. } => {
+            enrollment.sis_section_id.as_deref() == Some(id.as_str())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn course(sis_course_id: Option<&str>) -> canvas::Course {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "sis_course_id": sis_course_id,
+            "root_account_id": 1,
+            "account_id": 1,
+            "name": "Biology 101",
+            "enrollment_term_id": 1,
+            "uuid": "u",
+            "is_public": false,
+            "created_at": "2021-08-01T00:00:00Z",
+            "course_code": "BIO-101",
+            "default_view": "modules",
+            "license": "private",
+            "public_syllabus": false,
+            "public_syllabus_to_auth": false,
+            "storage_quota_mb": 500,
+            "is_public_to_auth_users": false,
+            "homeroom_course": false,
+            "apply_assignment_group_weights": false,
+            "calendar": { "ics": "https://c.test/feeds/course_1.ics" },
+            "time_zone": "America/Chicago",
+            "blueprint": false,
+            "template": false,
+            "enrollments": [],
+            "hide_final_grades": false,
+            "workflow_state": "available",
+            "restrict_enrollments_to_course_dates": false,
+            "overridden_course_visibility": "",
+        })).unwrap()
+    }
+
+    fn user() -> serde_json::Value {
+        serde_json::json!({
+            "id": 10,
+            "name": "Ann Lee",
+            "sortable_name": "Lee, Ann",
+            "last_name": "Lee",
+            "first_name": "Ann",
+            "sis_user_id": "S10",
+            "login_id": "alee",
+            "integration_id": "I10",
+        })
+    }
+
+    // An enrollment of user 10 in section 5, whose own SIS ids are given
+    // separately from those of the user nested in it.
+    fn enrollment(
+        sis_user_id: Option<&str>,
+        user: serde_json::Value,
+    ) -> canvas::Enrollment {
+        serde_json::from_value(serde_json::json!({
+            "id": 100,
+            "course_id": 1,
+            "course_section_id": 5,
+            "sis_section_id": "SEC5",
+            "sis_user_id": sis_user_id,
+            "enrollment_state": "active",
+            "limit_privileges_to_course_section": false,
+            "root_account_id": 1,
+            "type": "StudentEnrollment",
+            "user_id": 10,
+            "role": "StudentEnrollment",
+            "role_id": 3,
+            "created_at": "2021-08-01T00:00:00Z",
+            "updated_at": "2021-08-01T00:00:00Z",
+            "total_activity_time": 0,
+            "html_url": "https://c.test/courses/1/users/10",
+            "user": user,
+        })).unwrap()
+    }
+
+    fn id_ref<I: id::CanvasId>(s: &str) -> id::IdRef<I> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn course_by_id_or_sis_id() {
+        let course = course(Some("BIO-101-F21"));
+        assert!(course_matches(&course, &id_ref("1")));
+        assert!(!course_matches(&course, &id_ref("2")));
+        assert!(course_matches(&course, &id_ref("sis_course_id:BIO-101-F21")));
+        assert!(!course_matches(&course, &id_ref("sis_course_id:BIO-101")));
+    }
+
+    #[test]
+    fn course_without_visible_sis_id_never_matches_one() {
+        let course = course(None);
+        assert!(!course_matches(&course, &id_ref("sis_course_id:BIO-101")));
+    }
+
+    #[test]
+    fn user_by_each_sis_prefix() {
+        let user = serde_json::from_value::<canvas::User>(user()).unwrap();
+        assert!(user_matches(&user, &id_ref("10")));
+        assert!(user_matches(&user, &id_ref("sis_user_id:S10")));
+        assert!(user_matches(&user, &id_ref("sis_login_id:alee")));
+        assert!(user_matches(&user, &id_ref("sis_integration_id:I10")));
+        // Each prefix only looks at its own id.
+        assert!(!user_matches(&user, &id_ref("sis_login_id:S10")));
+        assert!(!user_matches(&user, &id_ref("sis_integration_id:alee")));
+        assert!(!user_matches(&user, &id_ref("sis_user_id:I10")));
+    }
+
+    #[test]
+    fn enrollment_user_by_own_or_nested_sis_id() {
+        let nested = enrollment(None, user());
+        assert!(enrollment_user_matches(&nested, &id_ref("10")));
+        assert!(enrollment_user_matches(&nested, &id_ref("sis_user_id:S10")));
+        assert!(enrollment_user_matches(
+            &nested,
+            &id_ref("sis_login_id:alee"),
+        ));
+        let mut bare_user = user();
+        bare_user["sis_user_id"] = serde_json::Value::Null;
+        let own = enrollment(Some("S10"), bare_user);
+        assert!(enrollment_user_matches(&own, &id_ref("sis_user_id:S10")));
+        assert!(!enrollment_user_matches(&own, &id_ref("sis_user_id:S11")));
+        // Only the user's SIS id is copied onto the enrollment.
+        assert!(!enrollment_user_matches(
+            &own,
+            &id_ref("sis_login_id:S10"),
+        ));
+    }
+
+    #[test]
+    fn section_by_id_or_sis_id() {
+        let section = serde_json::from_value::<canvas::Section>(
+            serde_json::json!({
+                "id": 5,
+                "name": "Section A",
+                "course_id": 1,
+                "sis_section_id": "SEC5",
+            }),
+        ).unwrap();
+        assert!(section_matches(&section, &id_ref("5")));
+        assert!(section_matches(&section, &id_ref("sis_section_id:SEC5")));
+        assert!(!section_matches(&section, &id_ref("6")));
+        assert!(!section_matches(&section, &id_ref("sis_section_id:SEC6")));
+    }
+
+    #[test]
+    fn enrollment_section_by_id_or_sis_id() {
+        let enrollment = enrollment(None, user());
+        assert!(enrollment_section_matches(&enrollment, &id_ref("5")));
+        assert!(enrollment_section_matches(
+            &enrollment,
+            &id_ref("sis_section_id:SEC5"),
+        ));
+        assert!(!enrollment_section_matches(&enrollment, &id_ref("6")));
+        assert!(!enrollment_section_matches(
+            &enrollment,
+            &id_ref("sis_section_id:SEC6"),
+        ));
+    }
+}
@@ -0,0 +1,48 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::cli;
+use crate::error;
+
+/// Read what a command remembered from an earlier run, by name. Nothing
+/// remembered yet and a file which can't be read both give the default, so a
+/// damaged file behaves like a first run rather than stopping the command.
+pub fn read<A: DeserializeOwned + Default>(
+    config: &cli::CliValid,
+    name: &str,
+) -> A {
+    let path = state_path(config, name);
+    fs::read_to_string(&path)
+        .ok()
+        .and_then(|text| {
+            serde_json::from_str::<A>(&text)
+                .map_err(|e| {
+                    log::warn!("Ignoring unreadable state {:?}: {}", path, e);
+                })
+                .ok()
+        })
+        .unwrap_or_default()
+}
+
+/// Remember something for the next run, by name.
+pub fn write<A: Serialize>(
+    config: &cli::CliValid,
+    name: &str,
+    state: &A,
+) -> Result<(), error::AppError> {
+    let path = state_path(config, name);
+    log::debug!("Writing state to {:?}.", path);
+    fs::create_dir_all(&config.state_dir)
+        .and_then(|_| {
+            serde_json::to_string_pretty(state)
+                .map_err(std::io::Error::from)
+        })
+        .and_then(|text| fs::write(&path, text))
+        .map_err(error::AppError::StateWriteError)
+}
+
+fn state_path(config: &cli::CliValid, name: &str) -> PathBuf {
+    config.state_dir.join(format!("{}.json", name))
+}
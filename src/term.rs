@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::canvas;
+use crate::cli;
+use crate::error;
+use crate::id;
+
+/// How a term is picked out on the command line: by id, by name, or whichever
+/// term is running right now.
+#[derive(Clone, Debug)]
+pub enum TermSelector {
+    Current,
+    Id(id::TermId),
+    Name(String),
+}
+
+impl FromStr for TermSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("current") {
+            Ok(TermSelector::Current)
+        } else {
+            Ok(
+                s.parse::<id::TermId>()
+                    .map(TermSelector::Id)
+                    .unwrap_or_else(|_| TermSelector::Name(s.to_string()))
+            )
+        }
+    }
+}
+
+/// Fetch the courses for a course-based command, narrowed to the term given on
+/// the command line if there is one. The terms the courses belong to come back
+/// alongside them, keyed by term id.
+pub async fn courses_selected(
+    config: &cli::CliValid,
+    query: &canvas::CourseQuery,
+) -> Result<
+    (Vec<canvas::Course>, HashMap<id::TermId, canvas::EnrollmentTerm>),
+    error::AppError,
+> {
+    let query = query.clone().with_include(canvas::CourseInclude::Term);
+    let courses = canvas::courses(config, &query).await?;
+    let terms = terms_by_id(config, &courses).await;
+    let selected = match &config.term {
+        Some(selector) => courses
+            .into_iter()
+            .filter(|c| {
+                course_in_term(c, terms.get(&c.enrollment_term_id), selector)
+            })
+            .collect(),
+        None => courses,
+    };
+    Ok((selected, terms))
+}
+
+/// A human readable name for a term, even if we couldn't find out anything
+/// about it.
+pub fn term_label(
+    terms: &HashMap<id::TermId, canvas::EnrollmentTerm>,
+    term_id: id::TermId,
+) -> String {
+    terms.get(&term_id)
+        .map(|t| t.name.clone())
+        .unwrap_or_else(|| format!("Term {}", term_id))
+}
+
+/// Term ids ordered chronologically, with undated terms at the end.
+pub fn term_order(
+    terms: &HashMap<id::TermId, canvas::EnrollmentTerm>,
+    term_ids: &[id::TermId],
+) -> Vec<id::TermId> {
+    let mut ids = term_ids.to_vec();
+    ids.sort_by_key(|id| {
+        let start = terms.get(id).and_then(|t| t.start_at);
+        (start.is_none(), start, *id)
+    });
+    ids.dedup();
+    ids
+}
+
+// Courses listed with include[]=term carry their term along, which is enough
+// for anyone. The accounts terms endpoint is restricted to account admins, so
+// it's only asked about terms a course came back without.
+async fn terms_by_id(
+    config: &cli::CliValid,
+    courses: &[canvas::Course],
+) -> HashMap<id::TermId, canvas::EnrollmentTerm> {
+    let mut terms = courses
+        .iter()
+        .filter_map(|c| c.term.as_ref())
+        .map(|t| (t.id, t.clone()))
+        .collect::<HashMap<id::TermId, canvas::EnrollmentTerm>>();
+    let mut account_ids = courses
+        .iter()
+        .filter(|c| !terms.contains_key(&c.enrollment_term_id))
+        .map(|c| c.root_account_id)
+        .collect::<Vec<id::AccountId>>();
+    account_ids.sort_unstable();
+    account_ids.dedup();
+    for account_id in account_ids {
+        match canvas::enrollment_terms(config, account_id).await {
+            Ok(account_terms) => {
+                for term in account_terms {
+                    terms.entry(term.id).or_insert(term);
+                }
+            },
+            Err(e) => log::info!(
+                "Could not list terms for account {}: {:?}",
+                account_id,
+                e,
+            ),
+        }
+    }
+    terms
+}
+
+fn course_in_term(
+    course: &canvas::Course,
+    term: Option<&canvas::EnrollmentTerm>,
+    selector: &TermSelector,
+) -> bool {
+    match selector {
+        TermSelector::Id(id) => course.enrollment_term_id == *id,
+        TermSelector::Name(name) => term
+            .map(|t| t.name.eq_ignore_ascii_case(name))
+            .unwrap_or(false),
+        // Terms without dates (such as Canvas' "Default Term") don't say
+        // anything about being current, so the course's own dates decide.
+        TermSelector::Current => {
+            let now = chrono::Local::now();
+            let (start_at, end_at) = match term {
+                Some(t) if t.start_at.is_some() || t.end_at.is_some() => {
+                    (t.start_at, t.end_at)
+                },
+                _ => (course.start_at, course.end_at),
+            };
+            start_at.map(|s| s <= now).unwrap_or(true)
+                && end_at.map(|e| now <= e).unwrap_or(true)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn term_selector_current_in_any_case() {
+        assert!(matches!("current".parse(), Ok(TermSelector::Current)));
+        assert!(matches!("Current".parse(), Ok(TermSelector::Current)));
+    }
+
+    #[test]
+    fn term_selector_id() {
+        assert!(matches!(
+            "42".parse(),
+            Ok(TermSelector::Id(id::TermId(42))),
+        ));
+    }
+
+    #[test]
+    fn term_selector_name() {
+        match "Fall 2021".parse() {
+            Ok(TermSelector::Name(name)) => assert_eq!(name, "Fall 2021"),
+            other => panic!("expected a name, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn term_selector_name_starting_with_digits() {
+        assert!(matches!("2021 Fall".parse(), Ok(TermSelector::Name(_))));
+    }
+}
@@ -0,0 +1,420 @@
+use plotters_backend::text_anchor::{HPos, VPos};
+use plotters_backend::{
+    BackendColor,
+    BackendCoord,
+    BackendTextStyle,
+    DrawingBackend,
+    DrawingErrorKind,
+    FontTransform,
+};
+use std::io::Write;
+use std::path::Path;
+
+// Charts are laid out in pixels, so each character cell stands in for a
+// block of pixels about the shape of a real terminal cell. That way margins,
+// label areas and the like keep their proportions.
+const CELL_WIDTH: i32 = 8;
+const CELL_HEIGHT: i32 = 16;
+// A braille character is a 2x4 grid of dots, which is the resolution we
+// actually draw at.
+const DOT_WIDTH: i32 = CELL_WIDTH / 2;
+const DOT_HEIGHT: i32 = CELL_HEIGHT / 4;
+// The bit for each dot of a braille character, by row and then column.
+const BRAILLE_DOTS: [[u8; 2]; 4] = [
+    [0x01, 0x08],
+    [0x02, 0x10],
+    [0x04, 0x20],
+    [0x40, 0x80],
+];
+const BRAILLE_TOP_HALF: u8 = 0x1b;
+const BRAILLE_BOTTOM_HALF: u8 = 0xe4;
+
+#[derive(Clone, Copy, Default)]
+struct Cell {
+    dots: u8,
+    color: Option<(u8, u8, u8)>,
+    // A character of text, along with which of the strings drawn it belongs
+    // to.
+    text: Option<(char, usize)>,
+}
+
+/// A plotters backend which draws into a grid of Unicode characters: braille
+/// dots for lines and points, block characters for filled areas, and text
+/// laid directly into the grid. Nothing is written out until the chart is
+/// presented.
+pub struct TextBackend<'a> {
+    path: Option<&'a Path>,
+    color: bool,
+    columns: i32,
+    rows: i32,
+    cells: Vec<Cell>,
+    texts: usize,
+}
+
+impl<'a> TextBackend<'a> {
+    /// A backend of the given size in character cells, which prints to stdout
+    /// without a path.
+    pub fn new(path: Option<&'a Path>, (columns, rows): (u32, u32)) -> Self {
+        TextBackend {
+            path,
+            color: path.is_none() && color_enabled(),
+            columns: columns as i32,
+            rows: rows as i32,
+            cells: vec![Cell::default(); (columns * rows) as usize],
+            texts: 0,
+        }
+    }
+
+    fn cell_mut(&mut self, (x, y): BackendCoord) -> Option<&mut Cell> {
+        let column = x.div_euclid(CELL_WIDTH);
+        let row = y.div_euclid(CELL_HEIGHT);
+        if column < 0 || row < 0 || column >= self.columns || row >= self.rows
+        {
+            None
+        } else {
+            self.cells.get_mut((row * self.columns + column) as usize)
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        for row in self.cells.chunks(self.columns as usize) {
+            let chars = row.iter().map(cell_char).collect::<Vec<char>>();
+            let end = chars
+                .iter()
+                .rposition(|c| *c != ' ')
+                .map_or(0, |i| i + 1);
+            let mut current = None;
+            for (cell, c) in row.iter().zip(chars).take(end) {
+                let color = cell.color.and_then(ansi_color);
+                if self.color && c != ' ' && color != current {
+                    match color {
+                        Some(code) => {
+                            text.push_str(&format!("\x1b[38;5;{}m", code))
+                        },
+                        None => text.push_str("\x1b[39m"),
+                    }
+                    current = color;
+                }
+                text.push(c);
+            }
+            if current.is_some() {
+                text.push_str("\x1b[39m");
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl<'a> DrawingBackend for TextBackend<'a> {
+    type ErrorType = std::io::Error;
+
+    fn get_size(&self) -> (u32, u32) {
+        (
+            (self.columns * CELL_WIDTH) as u32,
+            (self.rows * CELL_HEIGHT) as u32,
+        )
+    }
+
+    fn ensure_prepared(
+        &mut self,
+    ) -> Result<(), DrawingErrorKind<std::io::Error>> {
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), DrawingErrorKind<std::io::Error>> {
+        let text = self.to_text();
+        match self.path {
+            Some(path) => std::fs::write(path, text),
+            None => std::io::stdout().write_all(text.as_bytes()),
+        }.map_err(DrawingErrorKind::DrawingError)
+    }
+
+    fn draw_pixel(
+        &mut self,
+        (x, y): BackendCoord,
+        color: BackendColor,
+    ) -> Result<(), DrawingErrorKind<std::io::Error>> {
+        // Faint colors are mostly grid lines and anti-aliasing, which only
+        // clutter things up at this resolution.
+        if color.alpha < 0.3 {
+            return Ok(());
+        }
+        let dot_row = y.rem_euclid(CELL_HEIGHT) / DOT_HEIGHT;
+        let dot_column = x.rem_euclid(CELL_WIDTH) / DOT_WIDTH;
+        let bit = BRAILLE_DOTS[dot_row as usize][dot_column as usize];
+        if let Some(cell) = self.cell_mut((x, y)) {
+            // The terminal is the background, so drawing in white erases.
+            if is_background(color.rgb) {
+                cell.dots &= !bit;
+                cell.text = None;
+            } else {
+                cell.dots |= bit;
+                if cell.text.is_none() {
+                    cell.color = Some(color.rgb);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_text<TStyle: BackendTextStyle>(
+        &mut self,
+        text: &str,
+        style: &TStyle,
+        (x, y): BackendCoord,
+    ) -> Result<(), DrawingErrorKind<std::io::Error>> {
+        let color = style.color();
+        if color.alpha == 0.0 {
+            return Ok(());
+        }
+        let transform = style.transform();
+        // Rotated text runs down (or up) a column, one character per row.
+        let advance = match transform {
+            FontTransform::Rotate90 | FontTransform::Rotate270 => CELL_HEIGHT,
+            _ => CELL_WIDTH,
+        };
+        let width = text.chars().count() as i32 * advance;
+        let dx = match style.anchor().h_pos {
+            HPos::Left => 0,
+            HPos::Right => -width,
+            HPos::Center => -width / 2,
+        };
+        let dy = match style.anchor().v_pos {
+            VPos::Top => 0,
+            VPos::Center => -CELL_HEIGHT / 2,
+            VPos::Bottom => -CELL_HEIGHT,
+        };
+        // Place each character by its middle, so rounding to a cell doesn't
+        // shift text over by one.
+        let placed = text
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let (cx, cy) = transform.transform(
+                    dx + i as i32 * advance + advance / 2,
+                    dy + CELL_HEIGHT / 2,
+                );
+                ((x + cx, y + cy), c)
+            })
+            .collect::<Vec<(BackendCoord, char)>>();
+        // Layouts meant for real fonts can crowd text together once it's
+        // snapped to cells, and half of one label written over another can't
+        // be read. Plotters draws axis descriptions ahead of the tick labels
+        // they'd crowd, so the later text wins and the earlier goes entirely.
+        let crowded = placed
+            .iter()
+            .filter_map(|(point, _)| self.cell_mut(*point)?.text)
+            .map(|(_, id)| id)
+            .collect::<Vec<usize>>();
+        for cell in self.cells.iter_mut() {
+            if let Some((_, id)) = cell.text {
+                if crowded.contains(&id) {
+                    cell.text = None;
+                }
+            }
+        }
+        let id = self.texts;
+        self.texts += 1;
+        for (point, c) in placed {
+            if let Some(cell) = self.cell_mut(point) {
+                cell.text = Some((c, id));
+                cell.color = Some(color.rgb);
+            }
+        }
+        Ok(())
+    }
+
+    fn estimate_text_size<TStyle: BackendTextStyle>(
+        &self,
+        text: &str,
+        _style: &TStyle,
+    ) -> Result<(u32, u32), DrawingErrorKind<std::io::Error>> {
+        Ok((
+            (text.chars().count() as i32 * CELL_WIDTH) as u32,
+            CELL_HEIGHT as u32,
+        ))
+    }
+}
+
+/// How many columns and rows a terminal chart gets: the whole terminal less a
+/// line for the prompt, or 80x24 if stdout isn't a terminal we can measure.
+pub fn size() -> (u32, u32) {
+    match terminal_size::terminal_size() {
+        Some((terminal_size::Width(w), terminal_size::Height(h))) => {
+            (u32::from(w), u32::from(h).saturating_sub(1).max(1))
+        },
+        None => (80, 24),
+    }
+}
+
+/// Whether to color terminal output. Only a terminal will show colors rather
+/// than escape codes, and anyone setting NO_COLOR (see https://no-color.org)
+/// doesn't want them at all.
+pub fn color_enabled() -> bool {
+    color_wanted(
+        atty::is(atty::Stream::Stdout),
+        std::env::var_os("NO_COLOR").as_deref(),
+    )
+}
+
+// NO_COLOR set to an empty string counts the same as not set at all.
+fn color_wanted(tty: bool, no_color: Option<&std::ffi::OsStr>) -> bool {
+    tty && no_color.map(|v| v.is_empty()).unwrap_or(true)
+}
+
+fn cell_char(cell: &Cell) -> char {
+    match (cell.text, cell.dots) {
+        (Some((c, _)), _) => c,
+        (None, 0) => ' ',
+        (None, 0xff) => '█',
+        (None, BRAILLE_TOP_HALF) => '▀',
+        (None, BRAILLE_BOTTOM_HALF) => '▄',
+        (None, dots) => {
+            std::char::from_u32(0x2800 + u32::from(dots)).unwrap_or(' ')
+        },
+    }
+}
+
+fn is_background((r, g, b): (u8, u8, u8)) -> bool {
+    r.min(g).min(b) >= 0xf0
+}
+
+// The nearest color in the 256 color palette's 6x6x6 cube, which more
+// terminals support than true color. Dark colors are left in the terminal's
+// own foreground color, so black axes and labels show up on dark backgrounds
+// too.
+fn ansi_color((r, g, b): (u8, u8, u8)) -> Option<u8> {
+    if r.max(g).max(b) < 0x50 {
+        return None;
+    }
+    let level = |c: u8| ((u16::from(c) * 5 + 127) / 255) as u8;
+    Some(16 + 36 * level(r) + 6 * level(g) + level(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plotters::style::{IntoFont, TextStyle};
+
+    const RED: BackendColor = BackendColor { alpha: 1.0, rgb: (255, 0, 0) };
+    const WHITE: BackendColor = BackendColor {
+        alpha: 1.0,
+        rgb: (255, 255, 255),
+    };
+
+    fn backend(columns: u32, rows: u32) -> TextBackend<'static> {
+        let mut backend = TextBackend::new(None, (columns, rows));
+        backend.color = false;
+        backend
+    }
+
+    // Every pixel of a cell's dot rows from first to last, inclusive.
+    fn fill_rows(
+        backend: &mut TextBackend,
+        column: i32,
+        rows: std::ops::RangeInclusive<i32>,
+    ) {
+        for dot_row in rows {
+            for y in dot_row * DOT_HEIGHT..(dot_row + 1) * DOT_HEIGHT {
+                for x in column * CELL_WIDTH..(column + 1) * CELL_WIDTH {
+                    backend.draw_pixel((x, y), RED).unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pixels_set_braille_dots() {
+        let mut backend = backend(2, 1);
+        backend.draw_pixel((0, 0), RED).unwrap();
+        backend.draw_pixel((CELL_WIDTH + DOT_WIDTH, 3 * DOT_HEIGHT), RED)
+            .unwrap();
+        assert_eq!(backend.to_text(), "\u{2801}\u{2880}\n");
+    }
+
+    #[test]
+    fn each_pixel_of_a_dot_sets_the_same_bit() {
+        let mut backend = backend(1, 1);
+        for (row, bits) in BRAILLE_DOTS.iter().enumerate() {
+            for (column, bit) in bits.iter().enumerate() {
+                let x = column as i32 * DOT_WIDTH + DOT_WIDTH - 1;
+                let y = row as i32 * DOT_HEIGHT + DOT_HEIGHT - 1;
+                backend.draw_pixel((x, y), RED).unwrap();
+                assert_eq!(backend.cells[0].dots & bit, *bit);
+            }
+        }
+        assert_eq!(backend.cells[0].dots, 0xff);
+    }
+
+    #[test]
+    fn filled_cells_use_blocks() {
+        let mut backend = backend(3, 1);
+        fill_rows(&mut backend, 0, 0..=3);
+        fill_rows(&mut backend, 1, 0..=1);
+        fill_rows(&mut backend, 2, 2..=3);
+        assert_eq!(backend.to_text(), "█▀▄\n");
+    }
+
+    #[test]
+    fn white_erases_and_faint_is_ignored() {
+        let mut backend = backend(1, 1);
+        fill_rows(&mut backend, 0, 0..=3);
+        backend.draw_pixel((0, 0), WHITE).unwrap();
+        backend.draw_pixel((0, 0), BackendColor { alpha: 0.2, rgb: (0, 0, 0) })
+            .unwrap();
+        assert_eq!(backend.cells[0].dots, !0x01);
+    }
+
+    #[test]
+    fn pixels_outside_are_dropped() {
+        let mut backend = backend(1, 1);
+        backend.draw_pixel((-1, 0), RED).unwrap();
+        backend.draw_pixel((0, CELL_HEIGHT), RED).unwrap();
+        assert_eq!(backend.to_text(), "\n");
+    }
+
+    #[test]
+    fn text_replaces_dots_and_crowded_text() {
+        let mut backend = backend(6, 1);
+        backend.draw_pixel((5 * CELL_WIDTH, 0), RED).unwrap();
+        let style = TextStyle::from(("sans-serif", 12).into_font());
+        backend.draw_text("abc", &style, (0, 0)).unwrap();
+        assert_eq!(backend.to_text(), "abc  \u{2801}\n");
+        // Overlapping the first label drops it entirely.
+        backend.draw_text("xy", &style, (2 * CELL_WIDTH, 0)).unwrap();
+        assert_eq!(backend.to_text(), "  xy \u{2801}\n");
+    }
+
+    #[test]
+    fn colored_output_switches_only_when_needed() {
+        let mut backend = backend(3, 1);
+        backend.color = true;
+        backend.draw_pixel((0, 0), RED).unwrap();
+        backend.draw_pixel((CELL_WIDTH, 0), RED).unwrap();
+        assert_eq!(
+            backend.to_text(),
+            "\x1b[38;5;196m\u{2801}\u{2801}\x1b[39m\n",
+        );
+    }
+
+    #[test]
+    fn ansi_color_picks_from_the_cube() {
+        assert_eq!(ansi_color((255, 0, 0)), Some(196));
+        assert_eq!(ansi_color((255, 255, 255)), Some(231));
+        assert_eq!(ansi_color((0x80, 0x80, 0x80)), Some(145));
+        assert_eq!(ansi_color((0, 0, 0)), None);
+        assert_eq!(ansi_color((0x4f, 0x4f, 0x4f)), None);
+    }
+
+    #[test]
+    fn color_only_on_a_terminal_without_no_color() {
+        let empty = std::ffi::OsStr::new("");
+        let set = std::ffi::OsStr::new("1");
+        assert!(color_wanted(true, None));
+        assert!(color_wanted(true, Some(empty)));
+        assert!(!color_wanted(true, Some(set)));
+        assert!(!color_wanted(false, None));
+    }
+}
@@ -0,0 +1,426 @@
+use chrono::Datelike;
+use clap::ArgEnum;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::canvas;
+use crate::chart;
+use crate::cli;
+use crate::error;
+use crate::term;
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum WorkloadLayout {
+    // A row per course, with a mark at each due date.
+    Timeline,
+    // A row per assignment, spanning from when it unlocks to when it's due.
+    Gantt,
+}
+
+struct CourseSpan {
+    name: String,
+    start: Option<chrono::NaiveDateTime>,
+    end: Option<chrono::NaiveDateTime>,
+}
+
+// Dates are kept as wall clock time in the course's own time zone, so
+// something due at midnight lands on the day the course means.
+struct WorkloadItem {
+    // Index into the chart's courses.
+    course: usize,
+    name: String,
+    points: f64,
+    unlock: Option<chrono::NaiveDateTime>,
+    due: Option<chrono::NaiveDateTime>,
+    lock: Option<chrono::NaiveDateTime>,
+}
+
+/// What's due over the coming weeks across every active course, and how many
+/// points each week is worth.
+pub struct WorkloadChart {
+    courses: Vec<CourseSpan>,
+    items: Vec<WorkloadItem>,
+    // The Monday the chart starts on.
+    start: chrono::NaiveDate,
+    weeks: u32,
+    now: chrono::NaiveDateTime,
+    layout: WorkloadLayout,
+}
+
+pub async fn graph(
+    config: &cli::CliValid,
+    args: &cli::WorkloadArgs,
+) -> Result<(), error::AppError> {
+    let mut query = config.course_query.clone();
+    query.enrollment_state = query.enrollment_state
+        .or(Some(canvas::CourseEnrollmentState::Active));
+    let (courses, _terms) = term::courses_selected(config, &query).await?;
+    let assignments = canvas::assignments_by_courses(
+        config,
+        &courses,
+    ).await?;
+    chart::render(
+        &cli::chart_output(config, &args.chart, "workload"),
+        &workload_chart(
+            &courses,
+            &assignments,
+            chrono::Local::now(),
+            args.weeks,
+            args.layout,
+        ),
+    )
+}
+
+pub fn workload_chart(
+    courses: &[canvas::Course],
+    assignments: &[canvas::Assignment],
+    now: chrono::DateTime<chrono::Local>,
+    weeks: u32,
+    layout: WorkloadLayout,
+) -> WorkloadChart {
+    let today = now.naive_local().date();
+    let start = today - chrono::Duration::days(
+        today.weekday().num_days_from_monday() as i64,
+    );
+    let window_start = start.and_hms(0, 0, 0);
+    let window_end = window_start + chrono::Duration::weeks(weeks as i64);
+    let in_window = |at: &Option<chrono::NaiveDateTime>| {
+        at.map(|at| at >= window_start && at < window_end)
+            .unwrap_or(false)
+    };
+    let zones = courses.iter().map(course_zone).collect::<Vec<_>>();
+    let items = assignments
+        .iter()
+        .filter_map(|a| {
+            let course = courses.iter().position(|c| c.id == a.course_id)?;
+            let zone = zones[course];
+            Some(WorkloadItem {
+                course,
+                name: a.name.clone(),
+                points: a.points_possible.unwrap_or(0.0),
+                unlock: a.unlock_at.map(|at| course_time(zone, &at)),
+                due: a.due_at.map(|at| course_time(zone, &at)),
+                lock: a.lock_at.map(|at| course_time(zone, &at)),
+            })
+        })
+        // Only show work due within the chart's weeks, or closing within
+        // them when it has no due date.
+        .filter(|i| {
+            in_window(&i.due) || (i.due.is_none() && in_window(&i.lock))
+        })
+        .collect::<Vec<WorkloadItem>>();
+    WorkloadChart {
+        courses: courses
+            .iter()
+            .zip(zones.iter())
+            .map(|(c, zone)| CourseSpan {
+                name: c.course_code.clone(),
+                start: c.start_at.map(|at| course_time(*zone, &at)),
+                end: c.end_at.map(|at| course_time(*zone, &at)),
+            })
+            .collect(),
+        items,
+        start,
+        weeks: weeks.max(1),
+        now: now.naive_local(),
+        layout,
+    }
+}
+
+// Canvas names zones the IANA way. Anything it names that we don't know falls
+// back to the local zone.
+fn course_zone(course: &canvas::Course) -> Option<chrono_tz::Tz> {
+    match course.time_zone.parse::<chrono_tz::Tz>() {
+        Ok(zone) => Some(zone),
+        Err(e) => {
+            log::warn!(
+                "Unknown time zone '{}' for course {}, using local time: {}",
+                course.time_zone,
+                course.id,
+                e,
+            );
+            None
+        },
+    }
+}
+
+fn course_time(
+    zone: Option<chrono_tz::Tz>,
+    at: &chrono::DateTime<chrono::Local>,
+) -> chrono::NaiveDateTime {
+    match zone {
+        Some(zone) => at.with_timezone(&zone).naive_local(),
+        None => at.naive_local(),
+    }
+}
+
+const HOURS_PER_WEEK: u32 = 7 * 24;
+
+// Plotters can't label an f64 axis at points of our choosing, so time runs
+// along the x axis in whole hours from the start of the chart.
+impl WorkloadChart {
+    fn hours(&self) -> u32 {
+        self.weeks * HOURS_PER_WEEK
+    }
+
+    fn hour_of(&self, at: &chrono::NaiveDateTime) -> u32 {
+        let hours = (*at - self.start.and_hms(0, 0, 0)).num_hours();
+        hours.max(0).min(self.hours() as i64) as u32
+    }
+
+    fn week_label(&self, hour: &u32) -> String {
+        (self.start + chrono::Duration::hours(*hour as i64))
+            .format("%b %d")
+            .to_string()
+    }
+
+    fn week_starts(&self) -> Vec<u32> {
+        (0..=self.weeks).map(|w| w * HOURS_PER_WEEK).collect()
+    }
+}
+
+impl chart::Chart for WorkloadChart {
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        root.fill(&WHITE)?;
+        let (upper, lower) = root.split_vertically(
+            root.relative_to_height(0.6),
+        );
+        match self.layout {
+            WorkloadLayout::Timeline => self.draw_timeline(&upper)?,
+            WorkloadLayout::Gantt => self.draw_gantt(&upper)?,
+        }
+        self.draw_points(&lower)
+    }
+}
+
+impl WorkloadChart {
+    fn draw_timeline<DB: DrawingBackend>(
+        &self,
+        area: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        let names = self.courses
+            .iter()
+            .map(|c| c.name.clone())
+            .collect::<Vec<String>>();
+        let mut chart = self.rows_chart(area, "Upcoming work", &names)?;
+        for (i, course) in self.courses.iter().enumerate() {
+            let color = chart::series_color(i);
+            let row = row_of(i, names.len());
+            let start = course.start.map(|at| self.hour_of(&at)).unwrap_or(0);
+            let end = course.end
+                .map(|at| self.hour_of(&at))
+                .unwrap_or_else(|| self.hours());
+            let mut span = Rectangle::new(
+                [(start, row - 1), (end, row + 1)],
+                color.mix(0.15).filled(),
+            );
+            span.set_margin(4, 4, 0, 0);
+            chart.draw_series(std::iter::once(span))?;
+            let items = self.items.iter().filter(|item| item.course == i);
+            chart.draw_series(items.clone().filter_map(|item| {
+                item.lock.map(|at| {
+                    PathElement::new(
+                        vec![
+                            (self.hour_of(&at), row - 1),
+                            (self.hour_of(&at), row + 1),
+                        ],
+                        color.stroke_width(1),
+                    )
+                })
+            }))?;
+            // Bigger assignments get bigger marks.
+            chart.draw_series(items.filter_map(|item| {
+                item.due.map(|at| {
+                    Circle::new(
+                        (self.hour_of(&at), row),
+                        (3.0 + item.points.sqrt()).min(12.0) as u32,
+                        color.filled(),
+                    )
+                })
+            }))?;
+        }
+        self.draw_now(&mut chart, names.len())
+    }
+
+    fn draw_gantt<DB: DrawingBackend>(
+        &self,
+        area: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        let mut items = self.items.iter().collect::<Vec<&WorkloadItem>>();
+        items.sort_by_key(|i| (i.course, i.due.or(i.lock)));
+        let names = items
+            .iter()
+            .map(|i| format!("{}: {}", self.courses[i.course].name, i.name))
+            .collect::<Vec<String>>();
+        let mut chart = self.rows_chart(area, "Upcoming work", &names)?;
+        for (i, item) in items.iter().enumerate() {
+            let color = chart::series_color(item.course);
+            let row = row_of(i, names.len());
+            let unlock = item.unlock.map(|at| self.hour_of(&at)).unwrap_or(0);
+            let due = item.due.map(|at| self.hour_of(&at));
+            let lock = item.lock.map(|at| self.hour_of(&at));
+            // Solid up to the due date, then faded for as long as late work
+            // is still accepted.
+            let mut bars = vec![];
+            if let Some(due) = due {
+                bars.push((unlock, due, color.filled()));
+            }
+            if let Some(lock) = lock {
+                let from = due.unwrap_or(unlock);
+                bars.push((from, lock, color.mix(0.3).filled()));
+            }
+            // Keep even an exam open for an hour wide enough to see.
+            chart.draw_series(bars.into_iter().map(|(from, to, style)| {
+                let mut bar = Rectangle::new(
+                    [(from, row - 1), (to.max(from + 6), row + 1)],
+                    style,
+                );
+                bar.set_margin(2, 2, 0, 0);
+                bar
+            }))?;
+        }
+        self.draw_now(&mut chart, names.len())
+    }
+
+    // One labelled row per name, top to bottom, over the chart's weeks.
+    fn rows_chart<'a, DB: DrawingBackend>(
+        &self,
+        area: &'a DrawingArea<DB, Shift>,
+        caption: &str,
+        names: &[String],
+    ) -> Result<
+        ChartContext<'a, DB, RowsCoord>,
+        DrawingAreaErrorKind<DB::ErrorType>,
+    > {
+        let rows = names.len().max(1);
+        let mut chart = ChartBuilder::on(area)
+            .caption(caption, ("sans-serif", 20))
+            .margin(10)
+            .margin_right(30)
+            .x_label_area_size(30)
+            .y_label_area_size(160)
+            .build_cartesian_2d(
+                (0..self.hours()).with_key_points(self.week_starts()),
+                (0..2 * rows as i32).with_key_points(
+                    (0..rows).map(|i| row_of(i, rows)).collect(),
+                ),
+            )?;
+        let week_label = |hour: &u32| self.week_label(hour);
+        let row_label = |y: &i32| {
+            let index = rows as i32 - 1 - (*y - 1) / 2;
+            names.get(index as usize)
+                .map(|n| chart::truncate(n, 28))
+                .unwrap_or_default()
+        };
+        chart.configure_mesh()
+            .disable_y_mesh()
+            .x_label_formatter(&week_label)
+            .y_label_formatter(&row_label)
+            .draw()?;
+        Ok(chart)
+    }
+
+    fn draw_now<DB: DrawingBackend>(
+        &self,
+        chart: &mut ChartContext<DB, RowsCoord>,
+        rows: usize,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        let now = self.hour_of(&self.now);
+        chart.draw_series(std::iter::once(PathElement::new(
+            vec![(now, 0), (now, 2 * rows.max(1) as i32)],
+            BLACK.stroke_width(2),
+        )))?;
+        Ok(())
+    }
+
+    // A bar per week stacked by course, so the crunch weeks stand out.
+    fn draw_points<DB: DrawingBackend>(
+        &self,
+        area: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        let mut points = vec![
+            vec![0.0; self.courses.len()];
+            self.weeks as usize
+        ];
+        for item in &self.items {
+            if let Some(due) = item.due {
+                let week = (self.hour_of(&due) / HOURS_PER_WEEK) as usize;
+                if let Some(week) = points.get_mut(week) {
+                    week[item.course] += item.points;
+                }
+            }
+        }
+        let y_max = points
+            .iter()
+            .map(|w| w.iter().sum::<f64>())
+            .fold(0.0, f64::max)
+            .max(1.0) * 1.1;
+        let mut chart = ChartBuilder::on(area)
+            .caption("Points due per week", ("sans-serif", 20))
+            .margin(10)
+            .margin_right(30)
+            .x_label_area_size(30)
+            .y_label_area_size(160)
+            .build_cartesian_2d(
+                (0..self.hours()).with_key_points(self.week_starts()),
+                0.0..y_max,
+            )?;
+        let week_label = |hour: &u32| self.week_label(hour);
+        chart.configure_mesh()
+            .disable_x_mesh()
+            .x_label_formatter(&week_label)
+            .y_desc("Points")
+            .draw()?;
+        for (course, span) in self.courses.iter().enumerate() {
+            let color = chart::series_color(course);
+            let bars = points.iter().enumerate().filter_map(|(week, w)| {
+                if w[course] <= 0.0 {
+                    return None;
+                }
+                let below = w[..course].iter().sum::<f64>();
+                let from = week as u32 * HOURS_PER_WEEK;
+                let mut bar = Rectangle::new(
+                    [
+                        (from, below),
+                        (from + HOURS_PER_WEEK, below + w[course]),
+                    ],
+                    color.filled(),
+                );
+                bar.set_margin(0, 0, 8, 8);
+                Some(bar)
+            });
+            chart.draw_series(bars)?
+                .label(span.name.clone())
+                .legend(move |(x, y)| {
+                    Rectangle::new(
+                        [(x, y - 5), (x + 10, y + 5)],
+                        chart::series_color(course).filled(),
+                    )
+                });
+        }
+        chart.configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+        Ok(())
+    }
+}
+
+type RowsCoord = Cartesian2d<
+    plotters::coord::combinators::WithKeyPoints<
+        plotters::coord::types::RangedCoordu32,
+    >,
+    plotters::coord::combinators::WithKeyPoints<
+        plotters::coord::types::RangedCoordi32,
+    >,
+>;
+
+// Rows are two units tall so each has a whole number center to label. The
+// y axis counts up from the bottom, but the first row should sit highest.
+fn row_of(index: usize, rows: usize) -> i32 {
+    2 * (rows.max(1) - 1 - index) as i32 + 1
+}